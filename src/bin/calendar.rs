@@ -0,0 +1,17 @@
+use lambda_http::{run, service_fn, Error};
+use vehicle_management_lambda::{
+    handler::{self, calendar},
+    DBDataAccess,
+};
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    handler::init_tracing();
+
+    let data_access = DBDataAccess::from_env().await?;
+
+    run(service_fn(|request| {
+        calendar::get_calendar(&data_access, request)
+    }))
+    .await
+}
@@ -0,0 +1,119 @@
+use std::{convert::Infallible, net::SocketAddr, sync::Arc};
+
+use http_body_util::{BodyExt, Full};
+use hyper::{body::Bytes, server::conn::http1, service::service_fn};
+use hyper_util::rt::TokioIo;
+use lambda_http::{
+    aws_lambda_events::query_map::QueryMap, lambda_runtime::Context, tracing, Body, Error, Request,
+    RequestExt,
+};
+use tokio::net::TcpListener;
+use uuid::Uuid;
+use vehicle_management_lambda::{
+    error::error_response, handler, renewal::RenewalRules, router, DBDataAccess, DataAccess,
+    InMemoryDataAccess,
+};
+
+/// Serves the `api` routes over plain HTTP without the Lambda runtime.
+///
+/// * `DEV_SERVER_ADDR` - address to listen on, `127.0.0.1:9000` by default.
+/// * `DATA_STORE` - `memory` for an in-process store, anything else uses
+///   DynamoDB (set `DYNAMODB_ENDPOINT` to point it at DynamoDB Local).
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    handler::init_tracing();
+
+    let addr: SocketAddr = std::env::var("DEV_SERVER_ADDR")
+        .unwrap_or("127.0.0.1:9000".to_string())
+        .parse()?;
+
+    match std::env::var("DATA_STORE").as_deref() {
+        Ok("memory") => {
+            let data_access =
+                InMemoryDataAccess::new().with_renewal_rules(RenewalRules::from_env()?);
+            serve(data_access, addr).await
+        }
+        _ => serve(DBDataAccess::from_env().await?, addr).await,
+    }
+}
+
+async fn serve<T>(data_access: T, addr: SocketAddr) -> Result<(), Error>
+where
+    T: DataAccess + Send + Sync + 'static,
+{
+    let data_access = Arc::new(data_access);
+    let listener = TcpListener::bind(addr).await?;
+    tracing::info!("dev-server listening on http://{}", addr);
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let data_access = data_access.clone();
+
+        tokio::spawn(async move {
+            let service = service_fn(|request| handle(data_access.as_ref(), request));
+            if let Err(err) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                tracing::error!(%err, "Connection Error");
+            }
+        });
+    }
+}
+
+async fn handle(
+    data_access: &impl DataAccess,
+    request: hyper::Request<hyper::body::Incoming>,
+) -> Result<hyper::Response<Full<Bytes>>, Infallible> {
+    let (parts, body) = request.into_parts();
+
+    let body = match body.collect().await {
+        Ok(body) => body.to_bytes(),
+        Err(err) => {
+            return Ok(into_hyper(error_response(
+                400,
+                "BAD_REQUEST",
+                &err.to_string(),
+            )))
+        }
+    };
+    let body = match String::from_utf8(body.to_vec()) {
+        _ if body.is_empty() => Body::Empty,
+        Ok(text) => Body::Text(text),
+        Err(_) => Body::Binary(body.to_vec()),
+    };
+
+    let query = parts
+        .uri
+        .query()
+        .unwrap_or_default()
+        .parse::<QueryMap>()
+        .unwrap();
+    let path = parts.uri.path().to_string();
+    let mut context = Context::default();
+    context.request_id = Uuid::new_v4().to_string();
+
+    let request = Request::from_parts(parts, body)
+        .with_query_string_parameters(query)
+        .with_raw_http_path(path)
+        .with_lambda_context(context);
+
+    tracing::info!("{} {}", request.method(), request.uri());
+
+    let response = router::route(data_access, request)
+        .await
+        .unwrap_or_else(|err| error_response(500, "INTERNAL_ERROR", &err.to_string()));
+
+    Ok(into_hyper(response))
+}
+
+fn into_hyper(response: lambda_http::Response<Body>) -> hyper::Response<Full<Bytes>> {
+    let (parts, body) = response.into_parts();
+    let body = match body {
+        Body::Empty => Bytes::new(),
+        Body::Text(text) => Bytes::from(text),
+        Body::Binary(binary) => Bytes::from(binary),
+    };
+
+    hyper::Response::from_parts(parts, Full::new(body))
+}
@@ -0,0 +1,23 @@
+use lambda_http::Error;
+use vehicle_management_lambda::{handler, DBDataAccess};
+
+/// Moves the items written by older versions to the current layout, see
+/// `DBDataAccess::migrate`. Run it once against the table after deploying,
+/// it prints what it changed and can safely run again.
+///
+/// * `TABLE_NAME` / `DYNAMODB_ENDPOINT` - the table, as for the functions.
+/// * `LEGACY_OWNER` - username whose first organization gets the vehicles
+///   added before organizations existed.
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    handler::init_tracing();
+
+    let legacy_owner = std::env::var("LEGACY_OWNER").ok();
+    let report = DBDataAccess::from_env()
+        .await?
+        .migrate(legacy_owner.as_deref())
+        .await?;
+
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
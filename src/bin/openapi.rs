@@ -0,0 +1,9 @@
+use lambda_http::{run, service_fn, Error};
+use vehicle_management_lambda::handler::{self, openapi};
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    handler::init_tracing();
+
+    run(service_fn(openapi::get_openapi)).await
+}
@@ -0,0 +1,44 @@
+use lambda_http::{
+    lambda_runtime::{self, service_fn, LambdaEvent},
+    Error,
+};
+use serde_json::Value;
+use vehicle_management_lambda::{
+    handler::{self, reminder},
+    notifier::{LogNotifier, SmsNotifier},
+    DBDataAccess,
+};
+
+/// Runs on an EventBridge schedule and reminds every organization of the
+/// fees that expire soon or already expired.
+///
+/// * `REMINDER_DAYS` - how many days ahead to look, `7` by default.
+/// * `NOTIFIER` - `log` writes the reminders to the log (and to the file in
+///   `REMINDER_LOG` when set) instead of texting them.
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    handler::init_tracing();
+
+    let data_access = DBDataAccess::from_env().await?;
+    let days = std::env::var("REMINDER_DAYS")
+        .ok()
+        .and_then(|days| days.parse::<u32>().ok())
+        .unwrap_or(7);
+
+    match std::env::var("NOTIFIER").as_deref() {
+        Ok("log") => {
+            let notifier = LogNotifier::from_env();
+            lambda_runtime::run(service_fn(|_event: LambdaEvent<Value>| {
+                reminder::send_reminders(&data_access, &notifier, days)
+            }))
+            .await
+        }
+        _ => {
+            let notifier = SmsNotifier::from_env().await;
+            lambda_runtime::run(service_fn(|_event: LambdaEvent<Value>| {
+                reminder::send_reminders(&data_access, &notifier, days)
+            }))
+            .await
+        }
+    }
+}
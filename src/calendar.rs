@@ -0,0 +1,208 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+use crate::{handler::FEE_TYPES, model::vehicle::Vehicle};
+
+/// Lead times, in days, of the alarms of every event unless the feed URL asks
+/// for others.
+pub const DEFAULT_ALARM_DAYS: [u32; 2] = [7, 1];
+
+/// Reads the `alarms` parameter of a feed URL, e.g. `14,3,0`. An empty value
+/// turns the alarms off.
+pub fn alarm_days(alarms: &str) -> Result<Vec<u32>, String> {
+    alarms
+        .split(',')
+        .map(str::trim)
+        .filter(|days| !days.is_empty())
+        .map(|days| match days.parse::<u32>() {
+            Ok(days) if days <= 365 => Ok(days),
+            _ => Err("alarms must be a list of days between 0 and 365, like 7,1".to_string()),
+        })
+        .collect()
+}
+
+/// An iCalendar (RFC 5545) document with an all-day event for every fee
+/// expiry of `vehicles`.
+///
+/// The UID of an event only depends on the organization, the plate and the
+/// fee type, so when a payment or an update moves the date, calendar apps
+/// move the event they already have instead of adding another one.
+pub fn fee_calendar(
+    org_id: &str,
+    vehicles: &[Vehicle],
+    alarms: &[u32],
+    now: DateTime<Utc>,
+) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//vehicle-management-lambda//Fee expiries//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        "X-WR-CALNAME:Fee expiries".to_string(),
+        "REFRESH-INTERVAL;VALUE=DURATION:PT6H".to_string(),
+        "X-PUBLISHED-TTL:PT6H".to_string(),
+    ];
+    let stamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+
+    for vehicle in vehicles {
+        for fee_type in FEE_TYPES {
+            let date = match NaiveDate::parse_from_str(vehicle.fee_date(fee_type), "%Y-%m-%d") {
+                Ok(date) => date,
+                Err(_) => continue,
+            };
+            let summary = format!("{} {} expires", vehicle.vehicle_no, fee_type);
+
+            lines.extend([
+                "BEGIN:VEVENT".to_string(),
+                format!(
+                    "UID:{}-{}-{}@vehicle-management",
+                    org_id,
+                    vehicle.vehicle_no.as_key(),
+                    fee_type
+                ),
+                format!("DTSTAMP:{}", stamp),
+                format!("DTSTART;VALUE=DATE:{}", date.format("%Y%m%d")),
+                format!(
+                    "DTEND;VALUE=DATE:{}",
+                    (date + Duration::days(1)).format("%Y%m%d")
+                ),
+                format!("SUMMARY:{}", escape(&summary)),
+                format!(
+                    "DESCRIPTION:{}",
+                    escape(&format!(
+                        "Owner: {}\nThe {} of {} expires on {}.",
+                        vehicle.owner,
+                        fee_type,
+                        vehicle.vehicle_no,
+                        date.format("%Y-%m-%d")
+                    ))
+                ),
+                "TRANSP:TRANSPARENT".to_string(),
+            ]);
+            for days in alarms {
+                lines.extend([
+                    "BEGIN:VALARM".to_string(),
+                    "ACTION:DISPLAY".to_string(),
+                    format!("DESCRIPTION:{}", escape(&summary)),
+                    format!("TRIGGER:-P{}D", days),
+                    "END:VALARM".to_string(),
+                ]);
+            }
+            lines.push("END:VEVENT".to_string());
+        }
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines
+        .iter()
+        .map(|line| fold(line))
+        .collect::<Vec<String>>()
+        .concat()
+}
+
+/// Escapes the characters RFC 5545 reserves in text values.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Ends `line` with CRLF, folding it into continuation lines of at most 75
+/// bytes without splitting a character.
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vehicle(tax_date: &str) -> Vehicle {
+        Vehicle::new(
+            "DHA-KA-11-1234".parse().unwrap(),
+            "Rahim, Karim".to_string(),
+            tax_date.to_string(),
+            "unknown".to_string(),
+            "unknown".to_string(),
+            "unknown".to_string(),
+        )
+    }
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2026-01-10T08:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn alarms_are_days_between_0_and_365() {
+        assert_eq!(alarm_days("14, 3,0").unwrap(), [14, 3, 0]);
+        assert!(alarm_days("").unwrap().is_empty());
+        assert!(alarm_days("366").is_err());
+        assert!(alarm_days("-1").is_err());
+        assert!(alarm_days("soon").is_err());
+    }
+
+    #[test]
+    fn an_event_for_every_fee_date() {
+        let calendar = fee_calendar("org", &[vehicle("2026-01-31")], &[7, 1], now());
+
+        assert!(calendar.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(calendar.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!(calendar.matches("BEGIN:VEVENT").count(), 1);
+        for line in [
+            "UID:org-DHAKA-KA-11-1234-tax@vehicle-management\r\n",
+            "DTSTAMP:20260110T083000Z\r\n",
+            "DTSTART;VALUE=DATE:20260131\r\n",
+            "DTEND;VALUE=DATE:20260201\r\n",
+            "TRIGGER:-P7D\r\n",
+            "TRIGGER:-P1D\r\n",
+        ] {
+            assert!(calendar.contains(line), "{}", line);
+        }
+        assert!(calendar.contains("Owner: Rahim\\, Karim\\n"));
+    }
+
+    #[test]
+    fn moved_dates_keep_their_uid() {
+        let uid = |calendar: String| {
+            calendar
+                .lines()
+                .find(|line| line.starts_with("UID:"))
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(
+            uid(fee_calendar("org", &[vehicle("2026-01-31")], &[], now())),
+            uid(fee_calendar("org", &[vehicle("2027-01-31")], &[], now()))
+        );
+    }
+
+    #[test]
+    fn no_alarms_when_turned_off() {
+        let calendar = fee_calendar("org", &[vehicle("2026-01-31")], &[], now());
+        assert!(!calendar.contains("BEGIN:VALARM"));
+    }
+
+    #[test]
+    fn long_lines_are_folded_between_characters() {
+        let line = "ক".repeat(30);
+        let folded = fold(&line);
+        assert!(folded
+            .split("\r\n")
+            .all(|part| part.len() <= 75 && std::str::from_utf8(part.as_bytes()).is_ok()));
+        assert_eq!(folded.replace("\r\n ", ""), line + "\r\n");
+    }
+}
@@ -0,0 +1,176 @@
+use std::fmt;
+
+use aws_sdk_dynamodb::error::SdkError;
+use lambda_http::{tracing, Body, Response};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Debug)]
+pub enum DataAccessError {
+    /// The token is missing, unknown or the credentials are wrong.
+    Unauthorized(String),
+    /// The token was valid once but its session is no longer usable.
+    SessionExpired(String),
+    /// The caller is known but their role does not allow the operation.
+    Forbidden(String),
+    NotFound(String),
+    Conflict(String),
+    Validation(String),
+    /// DynamoDB (or whatever backs the store) failed. The detail is logged,
+    /// never sent to the client.
+    Storage(String),
+}
+
+impl DataAccessError {
+    pub fn status_code(&self) -> u16 {
+        match self {
+            DataAccessError::Unauthorized(_) | DataAccessError::SessionExpired(_) => 401,
+            DataAccessError::Forbidden(_) => 403,
+            DataAccessError::NotFound(_) => 404,
+            DataAccessError::Conflict(_) => 409,
+            DataAccessError::Validation(_) => 422,
+            DataAccessError::Storage(_) => 500,
+        }
+    }
+
+    /// Stable machine-readable error code sent as `code` in the response body.
+    pub fn code(&self) -> &'static str {
+        match self {
+            DataAccessError::Unauthorized(_) => "UNAUTHORIZED",
+            DataAccessError::SessionExpired(_) => "SESSION_EXPIRED",
+            DataAccessError::Forbidden(_) => "FORBIDDEN",
+            DataAccessError::NotFound(_) => "NOT_FOUND",
+            DataAccessError::Conflict(_) => "CONFLICT",
+            DataAccessError::Validation(_) => "VALIDATION_ERROR",
+            DataAccessError::Storage(_) => "STORAGE_ERROR",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            DataAccessError::Unauthorized(msg)
+            | DataAccessError::SessionExpired(msg)
+            | DataAccessError::Forbidden(msg)
+            | DataAccessError::NotFound(msg)
+            | DataAccessError::Conflict(msg)
+            | DataAccessError::Validation(msg)
+            | DataAccessError::Storage(msg) => msg,
+        }
+    }
+
+    pub fn to_response(&self) -> Response<Body> {
+        match self {
+            DataAccessError::Storage(msg) => {
+                tracing::error!("Storage Error: {}", msg);
+                error_response(self.status_code(), self.code(), "Something went wrong")
+            }
+            _ => error_response(self.status_code(), self.code(), self.message()),
+        }
+    }
+}
+
+impl fmt::Display for DataAccessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+impl std::error::Error for DataAccessError {}
+
+impl From<FieldError> for DataAccessError {
+    fn from(err: FieldError) -> Self {
+        DataAccessError::Validation(err.message)
+    }
+}
+
+impl<E, R> From<SdkError<E, R>> for DataAccessError
+where
+    E: std::error::Error + 'static,
+    R: fmt::Debug,
+{
+    fn from(err: SdkError<E, R>) -> Self {
+        DataAccessError::Storage(format!(
+            "{}",
+            aws_sdk_dynamodb::error::DisplayErrorContext(&err)
+        ))
+    }
+}
+
+/// An input of a request that did not pass validation. `message` names the
+/// field, so it reads on its own.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FieldError {
+    #[schema(example = "tax_date")]
+    pub field: String,
+    #[schema(example = "tax_date must be a date like 2024-01-31")]
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, message: impl Into<String>) -> Self {
+        Self {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// The JSON error body shared by every handler.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorBody<'a> {
+    /// `BAD_REQUEST`, `UNAUTHORIZED`, `SESSION_EXPIRED`, `FORBIDDEN`,
+    /// `NOT_FOUND`, `CONFLICT`, `VALIDATION_ERROR`, `STORAGE_ERROR` or
+    /// `EXPORT_ERROR`.
+    #[schema(example = "NOT_FOUND")]
+    pub code: &'a str,
+    pub message: &'a str,
+    /// Every input that failed, only sent with `VALIDATION_ERROR` from a
+    /// handler.
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub errors: &'a [FieldError],
+}
+
+/// Builds the JSON error body shared by every handler:
+/// `{"code": "...", "message": "..."}`.
+pub fn error_response(status: u16, code: &str, message: &str) -> Response<Body> {
+    body_response(
+        status,
+        &ErrorBody {
+            code,
+            message,
+            errors: &[],
+        },
+    )
+}
+
+/// A 422 `VALIDATION_ERROR` listing every field in `errors`; `message` joins
+/// them for clients that only show one line.
+pub fn validation_response(errors: &[FieldError]) -> Response<Body> {
+    let message = errors
+        .iter()
+        .map(|error| error.message.as_str())
+        .collect::<Vec<&str>>()
+        .join("; ");
+    body_response(
+        422,
+        &ErrorBody {
+            code: "VALIDATION_ERROR",
+            message: &message,
+            errors,
+        },
+    )
+}
+
+fn body_response(status: u16, body: &ErrorBody) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(serde_json::to_string(body).unwrap().into())
+        .unwrap()
+}
@@ -0,0 +1,340 @@
+use std::str::FromStr;
+
+use chrono::{
+    format::{Item, StrftimeItems},
+    NaiveDate,
+};
+use lambda_http::{aws_lambda_events::query_map::QueryMap, Body, Response};
+use rust_xlsxwriter::{Format, Workbook};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::FieldError;
+
+/// Columns of a vehicle export, in their default order.
+pub const VEHICLE_COLUMNS: &[&str] = &[
+    "vehicle_no",
+    "owner",
+    "tax_date",
+    "fitness_date",
+    "insurance_date",
+    "route_date",
+    "vehicle_class",
+];
+
+/// Columns of a history export, in their default order.
+pub const HISTORY_COLUMNS: &[&str] = &[
+    "vehicle_no",
+    "transaction_type",
+    "created_at",
+    "exp_date",
+    "payer",
+    "owner",
+    "amount",
+    "receipt_no",
+    "payment_method",
+    "note",
+    "from_owner",
+    "to_owner",
+];
+
+/// Written as numbers in a spreadsheet rather than text.
+const NUMBER_COLUMNS: &[&str] = &["amount"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Xlsx,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Xlsx => {
+                "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+            }
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Xlsx => "xlsx",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.trim().to_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "xlsx" => Ok(ExportFormat::Xlsx),
+            _ => Err("format must be csv or xlsx".to_string()),
+        }
+    }
+}
+
+/// How to lay out an export, read from the query string:
+///
+/// * `format` - `csv` (default) or `xlsx`.
+/// * `columns` - the columns to write, in order, each optionally renamed:
+///   `vehicle_no:Plate,owner:Owner,tax_date`.
+/// * `date_format` - a `strftime` pattern for the dates, `%Y-%m-%d` by
+///   default, e.g. `%d/%m/%Y`.
+#[derive(Debug)]
+pub struct Export {
+    pub format: ExportFormat,
+    /// Field and header of every column.
+    pub columns: Vec<(String, String)>,
+    pub date_format: String,
+}
+
+impl Export {
+    pub fn from_query(params: Option<&QueryMap>, fields: &[&str]) -> Result<Self, FieldError> {
+        let param = |name: &str| params.and_then(|params| params.first(name));
+
+        let format = param("format")
+            .map(str::parse::<ExportFormat>)
+            .transpose()
+            .map_err(|err| FieldError::new("format", err))?
+            .unwrap_or(ExportFormat::Csv);
+
+        let columns = match param("columns") {
+            Some(columns) => columns
+                .split(',')
+                .filter(|column| !column.trim().is_empty())
+                .map(|column| {
+                    let (field, header) = match column.split_once(':') {
+                        Some((field, header)) => (field.trim(), header.trim()),
+                        None => (column.trim(), column.trim()),
+                    };
+                    match fields.contains(&field) {
+                        true => Ok((field.to_string(), header.to_string())),
+                        false => Err(FieldError::new(
+                            "columns",
+                            format!(
+                                "{} is not a column, expected one of {}",
+                                field,
+                                fields.join(", ")
+                            ),
+                        )),
+                    }
+                })
+                .collect::<Result<Vec<(String, String)>, FieldError>>()?,
+            None => Vec::new(),
+        };
+        let columns = match columns.is_empty() {
+            true => fields
+                .iter()
+                .map(|field| (field.to_string(), field.to_string()))
+                .collect(),
+            false => columns,
+        };
+
+        let date_format = param("date_format").unwrap_or("%Y-%m-%d").to_string();
+        if StrftimeItems::new(&date_format).any(|item| matches!(item, Item::Error)) {
+            return Err(FieldError::new(
+                "date_format",
+                format!("{} is not a valid date_format", date_format),
+            ));
+        }
+
+        Ok(Self {
+            format,
+            columns,
+            date_format,
+        })
+    }
+
+    /// The cells of every row as text, dates in `date_format`. Fields a row
+    /// doesn't have are left empty.
+    fn cells<T: Serialize>(&self, rows: &[T]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|row| {
+                let row = serde_json::to_value(row).unwrap();
+                self.columns
+                    .iter()
+                    .map(|(field, _)| match row.get(field) {
+                        Some(Value::String(text)) => {
+                            match NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+                                Ok(date) => date.format(&self.date_format).to_string(),
+                                Err(_) => text.to_string(),
+                            }
+                        }
+                        Some(Value::Null) | None => String::new(),
+                        Some(value) => value.to_string(),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn to_csv<T: Serialize>(&self, rows: &[T]) -> Result<Vec<u8>, String> {
+        // Excel only reads a CSV as UTF-8, and so Bengali plates and names
+        // correctly, when it starts with a byte order mark.
+        let mut writer = csv::Writer::from_writer("\u{FEFF}".as_bytes().to_vec());
+        writer
+            .write_record(self.columns.iter().map(|(_, header)| header))
+            .map_err(|err| err.to_string())?;
+        for cells in self.cells(rows) {
+            writer.write_record(cells).map_err(|err| err.to_string())?;
+        }
+        writer.into_inner().map_err(|err| err.to_string())
+    }
+
+    fn to_xlsx<T: Serialize>(&self, rows: &[T], sheet: &str) -> Result<Vec<u8>, String> {
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name(sheet).map_err(|err| err.to_string())?;
+
+        let bold = Format::new().set_bold();
+        for (column, (_, header)) in self.columns.iter().enumerate() {
+            worksheet
+                .write_string_with_format(0, column as u16, header, &bold)
+                .map_err(|err| err.to_string())?;
+        }
+
+        for (row, cells) in self.cells(rows).iter().enumerate() {
+            for (column, cell) in cells.iter().enumerate() {
+                let number = match NUMBER_COLUMNS.contains(&self.columns[column].0.as_str()) {
+                    true => cell.parse::<f64>().ok(),
+                    false => None,
+                };
+                let row = row as u32 + 1;
+                match number {
+                    Some(number) => worksheet.write_number(row, column as u16, number),
+                    None => worksheet.write_string(row, column as u16, cell),
+                }
+                .map_err(|err| err.to_string())?;
+            }
+        }
+        worksheet.autofit();
+
+        workbook.save_to_buffer().map_err(|err| err.to_string())
+    }
+
+    /// A download of `rows` named `name.csv` or `name.xlsx`.
+    pub fn to_response<T: Serialize>(
+        &self,
+        rows: &[T],
+        name: &str,
+    ) -> Result<Response<Body>, String> {
+        let body = match self.format {
+            ExportFormat::Csv => Body::Text(String::from_utf8(self.to_csv(rows)?).unwrap()),
+            ExportFormat::Xlsx => Body::Binary(self.to_xlsx(rows, name)?),
+        };
+
+        Ok(Response::builder()
+            .status(200)
+            .header("Content-Type", self.format.content_type())
+            .header(
+                "Content-Disposition",
+                format!(
+                    "attachment; filename=\"{}.{}\"",
+                    name,
+                    self.format.extension()
+                ),
+            )
+            .body(body)
+            .unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn query(params: &[(&str, &str)]) -> QueryMap {
+        params
+            .iter()
+            .map(|(name, value)| (name.to_string(), vec![value.to_string()]))
+            .collect::<HashMap<String, Vec<String>>>()
+            .into()
+    }
+
+    fn rows() -> Vec<Value> {
+        vec![
+            json!({ "vehicle_no": "DHA-KA-11-1234", "owner": "Rahim", "tax_date": "2030-01-31" }),
+            json!({ "vehicle_no": "ঢাকা-ক-১১-১২৩৫", "owner": "Karim, Jr.", "tax_date": null }),
+        ]
+    }
+
+    fn csv(export: &Export) -> String {
+        String::from_utf8(export.to_csv(&rows()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn every_column_in_its_default_order() {
+        let export = Export::from_query(None, VEHICLE_COLUMNS).unwrap();
+
+        assert_eq!(export.format, ExportFormat::Csv);
+        assert_eq!(
+            csv(&export).lines().next().unwrap(),
+            "\u{FEFF}vehicle_no,owner,tax_date,fitness_date,insurance_date,route_date,vehicle_class"
+        );
+    }
+
+    #[test]
+    fn columns_come_out_in_the_order_and_names_asked_for() {
+        let params = query(&[("columns", "owner:Owner, tax_date ,vehicle_no:Plate")]);
+        let export = Export::from_query(Some(&params), VEHICLE_COLUMNS).unwrap();
+
+        assert_eq!(
+            csv(&export),
+            "\u{FEFF}Owner,tax_date,Plate\n\
+             Rahim,2030-01-31,DHA-KA-11-1234\n\
+             \"Karim, Jr.\",,ঢাকা-ক-১১-১২৩৫\n"
+        );
+    }
+
+    #[test]
+    fn dates_are_written_in_the_date_format() {
+        let params = query(&[("columns", "tax_date,owner"), ("date_format", "%d/%m/%Y")]);
+        let export = Export::from_query(Some(&params), VEHICLE_COLUMNS).unwrap();
+
+        assert_eq!(
+            csv(&export),
+            "\u{FEFF}tax_date,owner\n31/01/2030,Rahim\n,\"Karim, Jr.\"\n"
+        );
+    }
+
+    #[test]
+    fn bad_parameters_name_their_field() {
+        for (params, field) in [
+            (query(&[("format", "pdf")]), "format"),
+            (query(&[("columns", "vehicle_no,price")]), "columns"),
+            (query(&[("date_format", "%Q")]), "date_format"),
+        ] {
+            let err = Export::from_query(Some(&params), VEHICLE_COLUMNS).unwrap_err();
+            assert_eq!(err.field, field);
+        }
+    }
+
+    #[test]
+    fn xlsx_is_a_named_workbook_download() {
+        let params = query(&[("format", "XLSX")]);
+        let export = Export::from_query(Some(&params), HISTORY_COLUMNS).unwrap();
+        let response = export.to_response(&rows(), "history").unwrap();
+
+        assert_eq!(
+            response.headers()["Content-Type"],
+            ExportFormat::Xlsx.content_type()
+        );
+        assert_eq!(
+            response.headers()["Content-Disposition"],
+            "attachment; filename=\"history.xlsx\""
+        );
+        match response.body() {
+            // An xlsx file is a zip archive.
+            Body::Binary(bytes) => assert!(bytes.starts_with(b"PK")),
+            body => panic!("{:?}", body),
+        }
+    }
+}
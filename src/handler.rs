@@ -0,0 +1,90 @@
+use std::future::Future;
+
+use lambda_http::{request::RequestContext, tracing, Request, RequestExt};
+
+use crate::{
+    error::DataAccessError,
+    model::session::Device,
+    pagination::{Page, PageRequest},
+};
+
+pub mod calendar;
+pub mod history;
+pub mod openapi;
+pub mod organization;
+pub mod reminder;
+pub mod session;
+pub mod user;
+pub mod vehicle;
+
+/// Fee types served by `vehicle::get_vehicles_by_type`.
+pub const FEE_TYPES: [&str; 4] = ["tax", "fitness", "insurance", "route"];
+
+/// Follows `next_cursor` to the last page and returns every item, for the
+/// downloads that are not paged.
+pub(crate) async fn all_pages<T, F, Fut>(mut fetch: F) -> Result<Vec<T>, DataAccessError>
+where
+    F: FnMut(PageRequest) -> Fut,
+    Fut: Future<Output = Result<Page<T>, DataAccessError>>,
+{
+    let mut items = Vec::new();
+    let mut page = PageRequest::default();
+    loop {
+        let output = fetch(page).await?;
+        items.extend(output.items);
+        match output.next_cursor {
+            Some(cursor) => page = PageRequest::new(None, Some(cursor)),
+            None => return Ok(items),
+        }
+    }
+}
+
+/// The session token of the `Authorization` header, `None` when it is
+/// missing or not plain text.
+pub(crate) fn auth_token(request: &Request) -> Option<&str> {
+    request
+        .headers()
+        .get("Authorization")
+        .and_then(|token| token.to_str().ok())
+}
+
+/// The device a request comes from: its `User-Agent` and the client address
+/// API Gateway saw, or the first `X-Forwarded-For` hop behind a load
+/// balancer or the dev server's proxy.
+pub(crate) fn device(request: &Request, name: Option<String>) -> Device {
+    let header = |name: &str| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+    };
+    let source_ip = match request.request_context_ref() {
+        Some(RequestContext::ApiGatewayV1(context)) => context.identity.source_ip.clone(),
+        Some(RequestContext::ApiGatewayV2(context)) => context.http.source_ip.clone(),
+        Some(RequestContext::WebSocket(context)) => context.identity.source_ip.clone(),
+        _ => None,
+    }
+    .or_else(|| {
+        header("X-Forwarded-For")
+            .and_then(|hops| hops.split(',').next())
+            .map(|ip| ip.trim().to_string())
+    });
+
+    Device {
+        name: name.filter(|name| !name.trim().is_empty()),
+        user_agent: header("User-Agent").map(str::to_string),
+        source_ip,
+    }
+}
+
+/// JSON logging setup shared by every Lambda binary.
+pub fn init_tracing() {
+    tracing_subscriber::fmt()
+        .json()
+        .with_max_level(tracing::Level::INFO)
+        .with_current_span(false)
+        .with_ansi(false)
+        .with_target(false)
+        .without_time()
+        .init();
+}
@@ -0,0 +1,112 @@
+use chrono::Utc;
+use lambda_http::{Body, Error, Request, RequestExt, Response};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    calendar::{alarm_days, fee_calendar, DEFAULT_ALARM_DAYS},
+    error::{error_response, validation_response, ErrorBody, FieldError},
+    handler::auth_token,
+    model::calendar::CalendarFeed,
+    DataAccess,
+};
+
+/// A calendar feed and the path to subscribe to.
+#[derive(Debug, Serialize, ToSchema)]
+struct FeedUrl {
+    #[serde(flatten)]
+    feed: CalendarFeed,
+    /// `/calendar.ics?token=...`, relative to the API base URL.
+    path: String,
+}
+
+/// Creates the caller's calendar feed of the session's organization. Calling
+/// it again gives a new token and the old feed URL stops working.
+#[utoipa::path(
+    post,
+    path = "/calendar/feed",
+    tag = "calendar",
+    summary = "Create a calendar feed",
+    responses(
+        (status = 201, description = "The feed token and URL path", body = FeedUrl),
+        (status = 401, description = "The token is missing, unknown or expired", body = ErrorBody),
+    ),
+    security(("token" = [])),
+)]
+pub async fn create_calendar_feed(
+    data_access: &impl DataAccess,
+    request: Request,
+) -> Result<Response<Body>, Error> {
+    let token = match auth_token(&request) {
+        Some(token) => token,
+        None => return Ok(error_response(401, "UNAUTHORIZED", "Unauthorized")),
+    };
+
+    data_access
+        .create_calendar_feed(token)
+        .await
+        .map(|feed| {
+            let feed = FeedUrl {
+                path: format!("/calendar.ics?token={}", feed.token),
+                feed,
+            };
+            Response::builder()
+                .status(201)
+                .header("Content-Type", "application/json")
+                .body(serde_json::to_string(&feed).unwrap().into())
+                .unwrap()
+        })
+        .or_else(|err| Ok(err.to_response()))
+}
+
+/// The iCalendar feed behind `?token=`, with alarms `?alarms=7,1` days before
+/// every expiry.
+#[utoipa::path(
+    get,
+    path = "/calendar.ics",
+    tag = "calendar",
+    summary = "Calendar of fee expiries",
+    params(
+        ("token" = String, Query, description = "Token of the feed"),
+        ("alarms" = Option<String>, Query, description = "Alarm lead times in days, `7,1` by default, empty for none"),
+    ),
+    responses(
+        (status = 200, description = "iCalendar feed", body = String, content_type = "text/calendar"),
+        (status = 401, description = "The feed token is missing or not valid", body = ErrorBody),
+        (status = 422, description = "An input is not valid", body = ErrorBody),
+    ),
+)]
+pub async fn get_calendar(
+    data_access: &impl DataAccess,
+    request: Request,
+) -> Result<Response<Body>, Error> {
+    let params = request.query_string_parameters_ref();
+
+    let feed_token = match params.and_then(|params| params.first("token")) {
+        Some(feed_token) => feed_token,
+        None => return Ok(error_response(401, "UNAUTHORIZED", "Unauthorized")),
+    };
+
+    let alarms = match params.and_then(|params| params.first("alarms")) {
+        Some(alarms) => match alarm_days(alarms) {
+            Ok(alarms) => alarms,
+            Err(err) => return Ok(validation_response(&[FieldError::new("alarms", err)])),
+        },
+        None => DEFAULT_ALARM_DAYS.to_vec(),
+    };
+
+    let (feed, vehicles) = match data_access.calendar_vehicles(feed_token).await {
+        Ok(feed) => feed,
+        Err(err) => return Ok(err.to_response()),
+    };
+
+    Ok(Response::builder()
+        .status(200)
+        .header("Content-Type", "text/calendar; charset=utf-8")
+        .header(
+            "Content-Disposition",
+            "inline; filename=\"fee-expiries.ics\"",
+        )
+        .body(fee_calendar(&feed.org_id, &vehicles, &alarms, Utc::now()).into())
+        .unwrap())
+}
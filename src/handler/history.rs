@@ -0,0 +1,282 @@
+use lambda_http::{
+    aws_lambda_events::query_map::QueryMap, tracing, Body, Error, Request, RequestExt, Response,
+};
+
+use crate::{
+    error::{error_response, ErrorBody},
+    export::{Export, HISTORY_COLUMNS},
+    handler::{all_pages, auth_token},
+    model::{history::TransactionHistory, report::SpendingReport},
+    openapi::{Binary, Message},
+    pagination::{Page, PageRequest},
+    validation::{self, json_body, Validator},
+    DataAccess, DeleteHistory, HistoryFilter,
+};
+
+#[utoipa::path(
+    get,
+    path = "/history",
+    tag = "history",
+    summary = "List transactions",
+    params(
+        ("days" = Option<u32>, Query, description = "Transactions of the last days, 30 by default"),
+        PageRequest,
+    ),
+    responses(
+        (status = 200, description = "A page of transactions", body = Page<TransactionHistory>),
+        (status = 401, description = "The token is missing, unknown or expired", body = ErrorBody),
+        (status = 422, description = "An input is not valid", body = ErrorBody),
+    ),
+    security(("token" = [])),
+)]
+#[tracing::instrument( skip(data_access, request), fields(request_id = request.lambda_context().request_id))]
+pub async fn get_history(
+    data_access: &impl DataAccess,
+    request: Request,
+) -> Result<Response<Body>, Error> {
+    let token = match auth_token(&request) {
+        Some(token) => token,
+        None => return Ok(error_response(401, "UNAUTHORIZED", "Unauthorized")),
+    };
+
+    let params = request.query_string_parameters_ref();
+    let mut validator = Validator::new();
+    let days = validator.check(
+        params
+            .and_then(|params| params.first("days"))
+            .map_or(Ok(30), |days| validation::days("days", days, 0)),
+    );
+    let page = validator.check(PageRequest::from_query(params));
+    let (Some(days), Some(page)) = (days, page) else {
+        return Ok(validator.to_response());
+    };
+
+    data_access
+        .view_history(token, days, page)
+        .await
+        .map(|history_output| {
+            let history = serde_json::to_string(&history_output).unwrap();
+            Response::builder()
+                .status(200)
+                .body(history.into())
+                .unwrap()
+        })
+        .or_else(|err| Ok(err.to_response()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/vehicles/history",
+    tag = "history",
+    summary = "History of a vehicle",
+    params(
+        ("vehicle_no" = String, Query, description = "Registration plate, e.g. `DHA-KA-11-1234`"),
+        HistoryFilter,
+        PageRequest,
+    ),
+    responses(
+        (status = 200, description = "A page of transactions", body = Page<TransactionHistory>),
+        (status = 401, description = "The token is missing, unknown or expired", body = ErrorBody),
+        (status = 422, description = "An input is not valid", body = ErrorBody),
+    ),
+    security(("token" = [])),
+)]
+#[tracing::instrument( skip(data_access, request), fields(request_id = request.lambda_context().request_id))]
+pub async fn get_vehicle_history(
+    data_access: &impl DataAccess,
+    request: Request,
+) -> Result<Response<Body>, Error> {
+    let token = match auth_token(&request) {
+        Some(token) => token,
+        None => return Ok(error_response(401, "UNAUTHORIZED", "Unauthorized")),
+    };
+
+    let params = request.query_string_parameters_ref();
+    let mut validator = Validator::new();
+    let vehicle_no = validator.check(
+        validation::required(
+            "vehicle_no",
+            params.and_then(|params| params.first("vehicle_no")),
+        )
+        .and_then(|vehicle_no| validation::vehicle_no("vehicle_no", vehicle_no)),
+    );
+    let filter = history_filter(params, &mut validator);
+    let page = validator.check(PageRequest::from_query(params));
+    let (Some(vehicle_no), true, Some(page)) = (vehicle_no, validator.is_valid(), page) else {
+        return Ok(validator.to_response());
+    };
+
+    data_access
+        .get_vehicle_history(token, &vehicle_no, filter, page)
+        .await
+        .map(|history_output| {
+            let history = serde_json::to_string(&history_output).unwrap();
+            Response::builder()
+                .status(200)
+                .body(history.into())
+                .unwrap()
+        })
+        .or_else(|err| Ok(err.to_response()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/history",
+    tag = "history",
+    summary = "Undo a transaction",
+    request_body = DeleteHistory,
+    responses(
+        (status = 200, description = "The transaction is undone", body = Message),
+        (status = 400, description = "The request is malformed", body = ErrorBody),
+        (status = 401, description = "The token is missing, unknown or expired", body = ErrorBody),
+        (status = 403, description = "The role of the caller does not allow it", body = ErrorBody),
+        (status = 404, description = "The transaction is not in the record", body = ErrorBody),
+        (status = 422, description = "An input is not valid", body = ErrorBody),
+    ),
+    security(("token" = [])),
+)]
+#[tracing::instrument( skip(data_access, request), fields(request_id = request.lambda_context().request_id))]
+pub async fn undo_history(
+    data_access: &impl DataAccess,
+    request: Request,
+) -> Result<Response<Body>, Error> {
+    let token = match auth_token(&request) {
+        Some(token) => token,
+        None => return Ok(error_response(401, "UNAUTHORIZED", "Unauthorized")),
+    };
+
+    let undo_vehicle_history = match json_body::<DeleteHistory>(request.body()) {
+        Ok(value) => value,
+        Err(response) => return Ok(response),
+    };
+
+    data_access
+        .undo_history(token, undo_vehicle_history)
+        .await
+        .map(|_| {
+            Response::builder()
+                .status(200)
+                .body("{\"message\": \"The transaction undo successfully!!\"}".into())
+                .unwrap()
+        })
+        .or_else(|err| Ok(err.to_response()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/reports/spending",
+    tag = "reports",
+    summary = "Spending report",
+    params(
+        HistoryFilter,
+    ),
+    responses(
+        (status = 200, description = "Fee payments summed up", body = SpendingReport),
+        (status = 401, description = "The token is missing, unknown or expired", body = ErrorBody),
+        (status = 422, description = "An input is not valid", body = ErrorBody),
+    ),
+    security(("token" = [])),
+)]
+#[tracing::instrument( skip(data_access, request), fields(request_id = request.lambda_context().request_id))]
+pub async fn get_spending_report(
+    data_access: &impl DataAccess,
+    request: Request,
+) -> Result<Response<Body>, Error> {
+    let token = match auth_token(&request) {
+        Some(token) => token,
+        None => return Ok(error_response(401, "UNAUTHORIZED", "Unauthorized")),
+    };
+
+    let mut validator = Validator::new();
+    let filter = history_filter(request.query_string_parameters_ref(), &mut validator);
+    if !validator.is_valid() {
+        return Ok(validator.to_response());
+    }
+
+    data_access
+        .spending_report(token, filter)
+        .await
+        .map(|report| {
+            Response::builder()
+                .status(200)
+                .body(serde_json::to_string(&report).unwrap().into())
+                .unwrap()
+        })
+        .or_else(|err| Ok(err.to_response()))
+}
+
+/// Reads the optional `type`, `from` and `to` query parameters, recording
+/// the bad ones in `validator`.
+fn history_filter(params: Option<&QueryMap>, validator: &mut Validator) -> HistoryFilter {
+    let param = |name: &str| params.and_then(|params| params.first(name));
+    let mut date =
+        |name: &str| param(name).and_then(|date| validator.check(validation::iso_date(name, date)));
+
+    let from = date("from");
+    let to = date("to");
+    let transaction_type = param("type")
+        .and_then(|fee_type| validator.check(validation::fee_type("type", fee_type)))
+        .map(str::to_string);
+
+    HistoryFilter {
+        transaction_type,
+        from,
+        to,
+    }
+}
+
+/// The transactions of the last `days` (30 by default) as a CSV or XLSX
+/// download laid out by `Export::from_query`.
+#[utoipa::path(
+    get,
+    path = "/history/export",
+    tag = "history",
+    summary = "Export transactions",
+    description = "The transactions of the last `days` as a CSV or XLSX download.",
+    params(
+        ("format" = Option<String>, Query, description = "`csv` (default) or `xlsx`"),
+        ("columns" = Option<String>, Query, description = "Columns in order, each optionally renamed: `vehicle_no:Plate,owner`"),
+        ("date_format" = Option<String>, Query, description = "`strftime` pattern of the dates, `%Y-%m-%d` by default"),
+        ("days" = Option<u32>, Query, description = "Transactions of the last days, 30 by default"),
+    ),
+    responses(
+        (status = 200, description = "The download", content((String = "text/csv"), (Binary = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"))),
+        (status = 401, description = "The token is missing, unknown or expired", body = ErrorBody),
+        (status = 422, description = "An input is not valid", body = ErrorBody),
+        (status = 500, description = "The download could not be written", body = ErrorBody),
+    ),
+    security(("token" = [])),
+)]
+#[tracing::instrument( skip(data_access, request), fields(request_id = request.lambda_context().request_id))]
+pub async fn export_history(
+    data_access: &impl DataAccess,
+    request: Request,
+) -> Result<Response<Body>, Error> {
+    let token = match auth_token(&request) {
+        Some(token) => token,
+        None => return Ok(error_response(401, "UNAUTHORIZED", "Unauthorized")),
+    };
+    let params = request.query_string_parameters_ref();
+    let mut validator = Validator::new();
+    let export = validator.check(Export::from_query(params, HISTORY_COLUMNS));
+    let days = validator.check(
+        params
+            .and_then(|params| params.first("days"))
+            .map_or(Ok(30), |days| validation::days("days", days, 0)),
+    );
+    let (Some(export), Some(days)) = (export, days) else {
+        return Ok(validator.to_response());
+    };
+
+    let historys = match all_pages(|page| data_access.view_history(token, days, page)).await {
+        Ok(historys) => historys,
+        Err(err) => return Ok(err.to_response()),
+    };
+
+    Ok(export
+        .to_response(&historys, "history")
+        .unwrap_or_else(|err| {
+            tracing::error!(%err, "Export is not written");
+            error_response(500, "EXPORT_ERROR", "The export could not be written")
+        }))
+}
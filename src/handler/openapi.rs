@@ -0,0 +1,22 @@
+use lambda_http::{Body, Error, Request, Response};
+use utoipa::OpenApi;
+
+use crate::openapi::ApiDoc;
+
+/// The OpenAPI document of the API.
+#[utoipa::path(
+    get,
+    path = "/openapi.json",
+    tag = "meta",
+    summary = "OpenAPI document",
+    responses(
+        (status = 200, description = "This document", content_type = "application/json"),
+    ),
+)]
+pub async fn get_openapi(_request: Request) -> Result<Response<Body>, Error> {
+    Ok(Response::builder()
+        .status(200)
+        .header("Content-Type", "application/json")
+        .body(ApiDoc::openapi().to_json()?.into())
+        .unwrap())
+}
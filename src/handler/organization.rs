@@ -0,0 +1,212 @@
+use lambda_http::{tracing, Body, Error, Request, RequestExt, Response};
+
+use crate::{
+    error::{error_response, ErrorBody},
+    handler::auth_token,
+    model::{organization::Organization, user::Role},
+    openapi::Message,
+    validation::{json_body, not_blank, Validate, Validator},
+    DataAccess,
+};
+
+#[utoipa::path(
+    post,
+    path = "/organizations",
+    tag = "organizations",
+    summary = "Create an organization",
+    request_body = NewOrganization,
+    responses(
+        (status = 201, description = "The organization, with the caller as admin", body = Organization),
+        (status = 400, description = "The request is malformed", body = ErrorBody),
+        (status = 401, description = "The token is missing, unknown or expired", body = ErrorBody),
+        (status = 422, description = "An input is not valid", body = ErrorBody),
+    ),
+    security(("token" = [])),
+)]
+#[tracing::instrument( skip(data_access, request), fields(request_id = request.lambda_context().request_id))]
+pub async fn create_organization(
+    data_access: &impl DataAccess,
+    request: Request,
+) -> Result<Response<Body>, Error> {
+    let token = match auth_token(&request) {
+        Some(token) => token,
+        None => return Ok(error_response(401, "UNAUTHORIZED", "Unauthorized")),
+    };
+
+    let new_org = match json_body::<NewOrganization>(request.body()) {
+        Ok(new_org) => new_org,
+        Err(response) => return Ok(response),
+    };
+    data_access
+        .create_organization(token, &new_org.name)
+        .await
+        .map(|organization| {
+            Response::builder()
+                .status(201)
+                .header("Content-Type", "application/json")
+                .body(serde_json::to_string(&organization).unwrap().into())
+                .unwrap()
+        })
+        .or_else(|err| Ok(err.to_response()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/organizations",
+    tag = "organizations",
+    summary = "List organizations",
+    responses(
+        (status = 200, description = "Organizations of the caller", body = Vec<Organization>),
+        (status = 401, description = "The token is missing, unknown or expired", body = ErrorBody),
+    ),
+    security(("token" = [])),
+)]
+#[tracing::instrument( skip(data_access, request), fields(request_id = request.lambda_context().request_id))]
+pub async fn get_organizations(
+    data_access: &impl DataAccess,
+    request: Request,
+) -> Result<Response<Body>, Error> {
+    let token = match auth_token(&request) {
+        Some(token) => token,
+        None => return Ok(error_response(401, "UNAUTHORIZED", "Unauthorized")),
+    };
+
+    data_access
+        .get_organizations(token)
+        .await
+        .map(|organizations| {
+            Response::builder()
+                .status(200)
+                .header("Content-Type", "application/json")
+                .body(serde_json::to_string(&organizations).unwrap().into())
+                .unwrap()
+        })
+        .or_else(|err| Ok(err.to_response()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/organizations/members",
+    tag = "organizations",
+    summary = "Add a member",
+    request_body = NewMember,
+    responses(
+        (status = 201, description = "The user is added", body = Message),
+        (status = 400, description = "The request is malformed", body = ErrorBody),
+        (status = 401, description = "The token is missing, unknown or expired", body = ErrorBody),
+        (status = 403, description = "The role of the caller does not allow it", body = ErrorBody),
+        (status = 404, description = "The user is not found", body = ErrorBody),
+        (status = 409, description = "The user is already a member", body = ErrorBody),
+        (status = 422, description = "An input is not valid", body = ErrorBody),
+    ),
+    security(("token" = [])),
+)]
+#[tracing::instrument( skip(data_access, request), fields(request_id = request.lambda_context().request_id))]
+pub async fn add_member(
+    data_access: &impl DataAccess,
+    request: Request,
+) -> Result<Response<Body>, Error> {
+    let token = match auth_token(&request) {
+        Some(token) => token,
+        None => return Ok(error_response(401, "UNAUTHORIZED", "Unauthorized")),
+    };
+
+    let member = match json_body::<NewMember>(request.body()) {
+        Ok(member) => member,
+        Err(response) => return Ok(response),
+    };
+    data_access
+        .add_member(token, &member.username, member.role)
+        .await
+        .map(|_| {
+            Response::builder()
+                .status(201)
+                .body(
+                    format!(
+                        "{{\"message\": \"{} is added to the organization\"}}",
+                        member.username
+                    )
+                    .into(),
+                )
+                .unwrap()
+        })
+        .or_else(|err| Ok(err.to_response()))
+}
+
+#[derive(Debug, serde::Deserialize, utoipa::ToSchema)]
+struct NewOrganization {
+    name: String,
+}
+
+impl Validate for NewOrganization {
+    fn validate(&self, validator: &mut Validator) {
+        validator.check(not_blank("name", &self.name));
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/organizations/members",
+    tag = "organizations",
+    summary = "Change the role of a member",
+    request_body = NewMember,
+    responses(
+        (status = 200, description = "The role is changed", body = Message),
+        (status = 400, description = "The request is malformed", body = ErrorBody),
+        (status = 401, description = "The token is missing, unknown or expired", body = ErrorBody),
+        (status = 403, description = "The role of the caller does not allow it", body = ErrorBody),
+        (status = 404, description = "The user is not a member", body = ErrorBody),
+        (status = 409, description = "It would leave the organization without an admin", body = ErrorBody),
+        (status = 422, description = "An input is not valid", body = ErrorBody),
+    ),
+    security(("token" = [])),
+)]
+#[tracing::instrument( skip(data_access, request), fields(request_id = request.lambda_context().request_id))]
+pub async fn set_role(
+    data_access: &impl DataAccess,
+    request: Request,
+) -> Result<Response<Body>, Error> {
+    let token = match auth_token(&request) {
+        Some(token) => token,
+        None => return Ok(error_response(401, "UNAUTHORIZED", "Unauthorized")),
+    };
+
+    let member = match json_body::<NewMember>(request.body()) {
+        Ok(member) => member,
+        Err(response) => return Ok(response),
+    };
+    data_access
+        .set_role(token, &member.username, member.role)
+        .await
+        .map(|_| {
+            Response::builder()
+                .status(200)
+                .body(
+                    format!(
+                        "{{\"message\": \"{} is now {}\"}}",
+                        member.username, member.role
+                    )
+                    .into(),
+                )
+                .unwrap()
+        })
+        .or_else(|err| Ok(err.to_response()))
+}
+
+#[derive(Debug, serde::Deserialize, utoipa::ToSchema)]
+struct NewMember {
+    username: String,
+    /// New members only read unless told otherwise.
+    #[serde(default = "default_role")]
+    role: Role,
+}
+
+fn default_role() -> Role {
+    Role::Viewer
+}
+
+impl Validate for NewMember {
+    fn validate(&self, validator: &mut Validator) {
+        validator.check(not_blank("username", &self.username));
+    }
+}
@@ -0,0 +1,191 @@
+use lambda_http::{tracing, Error};
+use serde_json::{json, Value};
+
+use crate::{notifier::Notifier, DataAccess};
+
+/// Sends a reminder for every fee that expires within `days` or already
+/// expired, skipping the recipients it was sent to before. Each recipient is
+/// claimed before the message goes out, so overlapping runs never send it
+/// twice. A recipient the notifier fails on is released and tried again on
+/// the next run, the others still get it.
+pub async fn send_reminders(
+    data_access: &impl DataAccess,
+    notifier: &impl Notifier,
+    days: u32,
+) -> Result<Value, Error> {
+    let mut sent = 0;
+    let mut failed = 0;
+
+    for reminder in data_access.due_reminders(days).await? {
+        if reminder.recipients.is_empty() {
+            tracing::warn!(
+                org_id = reminder.org_id,
+                vehicle_no = %reminder.vehicle_no,
+                "Nobody with a phone number to remind"
+            );
+            continue;
+        }
+
+        for recipient in &reminder.recipients {
+            if !data_access.claim_reminder(&reminder, recipient).await? {
+                continue;
+            }
+
+            match notifier.notify(&reminder, recipient).await {
+                Ok(()) => sent += 1,
+                Err(err) => {
+                    tracing::error!(
+                        %err,
+                        vehicle_no = %reminder.vehicle_no,
+                        username = recipient.username,
+                        "Reminder is not sent"
+                    );
+                    if let Err(err) = data_access.release_reminder(&reminder, recipient).await {
+                        tracing::error!(%err, "Reminder is not released, it won't be retried");
+                    }
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    Ok(json!({ "sent": sent, "failed": failed }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+    use chrono::{Days, Local};
+
+    use super::*;
+    use crate::{
+        model::{
+            reminder::{Recipient, Reminder},
+            session::Device,
+            user::{Role, User},
+            vehicle::Vehicle,
+        },
+        InMemoryDataAccess,
+    };
+
+    /// Keeps what it was asked to send, failing for `failing`.
+    #[derive(Default)]
+    struct Outbox {
+        sent: Mutex<Vec<String>>,
+        failing: Option<&'static str>,
+    }
+
+    #[async_trait]
+    impl Notifier for Outbox {
+        async fn notify(&self, reminder: &Reminder, recipient: &Recipient) -> Result<(), Error> {
+            if self.failing == Some(recipient.username.as_str()) {
+                return Err("the SMS gateway is down".into());
+            }
+            self.sent.lock().unwrap().push(format!(
+                "{} {} {}",
+                recipient.username, reminder.vehicle_no, reminder.fee_type
+            ));
+            Ok(())
+        }
+    }
+
+    fn user(username: &str) -> User {
+        User::new(
+            username.to_string(),
+            "secret".to_string(),
+            Some("+8801700000000".to_string()),
+        )
+    }
+
+    /// An organization of `rahim` and the operator `karim`, with a tax that
+    /// is due in 10 days.
+    async fn fleet() -> InMemoryDataAccess {
+        let data_access = InMemoryDataAccess::new();
+        data_access.create_user(user("rahim")).await.unwrap();
+        data_access.create_user(user("karim")).await.unwrap();
+        let token = data_access
+            .get_session(user("rahim"), Device::default())
+            .await
+            .unwrap()
+            .token;
+        data_access
+            .add_member(&token, "karim", Role::Operator)
+            .await
+            .unwrap();
+
+        let due = Local::now()
+            .date_naive()
+            .checked_add_days(Days::new(10))
+            .unwrap()
+            .to_string();
+        let vehicle = Vehicle::new(
+            "DHA-KA-11-1234".parse().unwrap(),
+            "Rahim".to_string(),
+            due,
+            "2099-01-01".to_string(),
+            "2099-01-01".to_string(),
+            "2099-01-01".to_string(),
+        );
+        data_access.add_vehicle(&token, vehicle).await.unwrap();
+        data_access
+    }
+
+    #[tokio::test]
+    async fn a_reminder_goes_to_each_recipient_once() {
+        let data_access = fleet().await;
+        let outbox = Outbox::default();
+
+        let first = send_reminders(&data_access, &outbox, 30).await.unwrap();
+        assert_eq!(first, json!({ "sent": 2, "failed": 0 }));
+        let second = send_reminders(&data_access, &outbox, 30).await.unwrap();
+        assert_eq!(second, json!({ "sent": 0, "failed": 0 }));
+
+        let mut sent = outbox.sent.lock().unwrap().clone();
+        sent.sort();
+        assert_eq!(
+            sent,
+            ["karim DHA-KA-11-1234 tax", "rahim DHA-KA-11-1234 tax"]
+        );
+    }
+
+    #[tokio::test]
+    async fn a_claimed_recipient_is_not_sent_to_again() {
+        let data_access = fleet().await;
+        let reminder = data_access.due_reminders(30).await.unwrap().remove(0);
+        let recipient = &reminder.recipients[0];
+
+        assert!(data_access
+            .claim_reminder(&reminder, recipient)
+            .await
+            .unwrap());
+        assert!(!data_access
+            .claim_reminder(&reminder, recipient)
+            .await
+            .unwrap());
+
+        let outbox = Outbox::default();
+        let report = send_reminders(&data_access, &outbox, 30).await.unwrap();
+        assert_eq!(report, json!({ "sent": 1, "failed": 0 }));
+        assert!(!outbox.sent.lock().unwrap()[0].starts_with(&recipient.username));
+    }
+
+    #[tokio::test]
+    async fn a_failed_recipient_does_not_stop_the_others_and_is_retried() {
+        let data_access = fleet().await;
+
+        let failing = Outbox {
+            failing: Some("karim"),
+            ..Outbox::default()
+        };
+        let first = send_reminders(&data_access, &failing, 30).await.unwrap();
+        assert_eq!(first, json!({ "sent": 1, "failed": 1 }));
+        assert_eq!(*failing.sent.lock().unwrap(), ["rahim DHA-KA-11-1234 tax"]);
+
+        let working = Outbox::default();
+        let retry = send_reminders(&data_access, &working, 30).await.unwrap();
+        assert_eq!(retry, json!({ "sent": 1, "failed": 0 }));
+        assert_eq!(*working.sent.lock().unwrap(), ["karim DHA-KA-11-1234 tax"]);
+    }
+}
@@ -0,0 +1,266 @@
+use lambda_http::{tracing, Body, Error, Request, RequestExt, Response};
+
+use crate::{
+    error::{error_response, validation_response, ErrorBody},
+    handler::{auth_token, device},
+    model::{
+        session::{DeviceSession, Tokens},
+        user::User,
+    },
+    openapi::Message,
+    validation::{self, json_body, not_blank, Validate, Validator},
+    DataAccess,
+};
+
+#[utoipa::path(
+    post,
+    path = "/sessions",
+    tag = "sessions",
+    summary = "Log in",
+    description = "Starts a session in `org_id`, or the first organization of the user. Send the token as the `Authorization` header.",
+    request_body = User,
+    responses(
+        (status = 200, description = "A new session and the refresh token that renews it", body = Tokens),
+        (status = 400, description = "The request is malformed", body = ErrorBody),
+        (status = 401, description = "The token is missing, unknown or expired", body = ErrorBody),
+        (status = 422, description = "An input is not valid", body = ErrorBody),
+    ),
+)]
+#[tracing::instrument(fields(request_id=req.lambda_context().request_id), skip(data_access, req))]
+pub async fn login(data_access: &impl DataAccess, req: Request) -> Result<Response<Body>, Error> {
+    let user = match json_body::<User>(req.body()) {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+
+    tracing::info!(username = %user.username, "Login");
+    let device = device(&req, user.device_name.clone());
+
+    data_access
+        .get_session(user, device)
+        .await
+        .map(|tokens| {
+            Response::builder()
+                .header("Content-Type", "Application/json")
+                .status(200)
+                .body(serde_json::to_string(&tokens).unwrap().into())
+                .unwrap()
+        })
+        .or_else(|err| {
+            tracing::error!(%err);
+            Ok(err.to_response())
+        })
+}
+
+#[utoipa::path(
+    post,
+    path = "/sessions/refresh",
+    tag = "sessions",
+    summary = "Refresh a session",
+    description = "Exchanges a refresh token for a new access token and refresh token. A refresh token works once; presenting a used one again revokes every session of that login.",
+    request_body = RefreshRequest,
+    responses(
+        (status = 200, description = "A new session and the refresh token that renews it", body = Tokens),
+        (status = 400, description = "The request is malformed", body = ErrorBody),
+        (status = 401, description = "The refresh token is unknown, expired or already used", body = ErrorBody),
+        (status = 422, description = "An input is not valid", body = ErrorBody),
+    ),
+)]
+#[tracing::instrument(fields(request_id=req.lambda_context().request_id), skip(data_access, req))]
+pub async fn refresh_session(
+    data_access: &impl DataAccess,
+    req: Request,
+) -> Result<Response<Body>, Error> {
+    let refresh = match json_body::<RefreshRequest>(req.body()) {
+        Ok(refresh) => refresh,
+        Err(response) => return Ok(response),
+    };
+
+    data_access
+        .refresh_session(&refresh.refresh_token, device(&req, None))
+        .await
+        .map(|tokens| {
+            Response::builder()
+                .header("Content-Type", "Application/json")
+                .status(200)
+                .body(serde_json::to_string(&tokens).unwrap().into())
+                .unwrap()
+        })
+        .or_else(|err| {
+            tracing::error!(%err);
+            Ok(err.to_response())
+        })
+}
+
+#[derive(Debug, serde::Deserialize, utoipa::ToSchema)]
+struct RefreshRequest {
+    refresh_token: String,
+}
+
+impl Validate for RefreshRequest {
+    fn validate(&self, validator: &mut Validator) {
+        validator.check(not_blank("refresh_token", &self.refresh_token));
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/sessions",
+    tag = "sessions",
+    summary = "Log out",
+    description = "Ends the login of this request. The other devices of the user stay signed in; use `DELETE /sessions/others` to end those.",
+    responses(
+        (status = 200, description = "The session of this device is deleted", body = Message),
+        (status = 401, description = "The token is missing, unknown or expired", body = ErrorBody),
+    ),
+    security(("token" = [])),
+)]
+#[tracing::instrument( skip(data_access, req), fields(request_id = req.lambda_context().request_id))]
+pub async fn delete_session<T: DataAccess>(
+    data_access: &T,
+    req: Request,
+) -> Result<Response<Body>, Error> {
+    let token = match auth_token(&req) {
+        Some(token) => token,
+        None => return Ok(error_response(401, "UNAUTHORIZED", "Unauthorized")),
+    };
+
+    data_access
+        .delete_session(token)
+        .await
+        .map(|usr| {
+            Response::builder()
+                .status(200)
+                .body(
+                    format!(
+                        "{{\"message\": \"The session of the user {} is deleted\"}}",
+                        usr
+                    )
+                    .into(),
+                )
+                .unwrap()
+        })
+        .or_else(|err| {
+            tracing::error!("ERROR: {:#?}", err);
+            Ok(err.to_response())
+        })
+}
+
+#[utoipa::path(
+    get,
+    path = "/sessions",
+    tag = "sessions",
+    summary = "List sessions",
+    description = "Every login of the user that has not expired, with the device it was made from. The one of this request has `current` set.",
+    responses(
+        (status = 200, description = "The logins, most recently used first", body = Vec<DeviceSession>),
+        (status = 401, description = "The token is missing, unknown or expired", body = ErrorBody),
+    ),
+    security(("token" = [])),
+)]
+#[tracing::instrument( skip(data_access, req), fields(request_id = req.lambda_context().request_id))]
+pub async fn list_sessions(
+    data_access: &impl DataAccess,
+    req: Request,
+) -> Result<Response<Body>, Error> {
+    let token = match auth_token(&req) {
+        Some(token) => token,
+        None => return Ok(error_response(401, "UNAUTHORIZED", "Unauthorized")),
+    };
+
+    data_access
+        .list_sessions(token)
+        .await
+        .map(|sessions| {
+            Response::builder()
+                .status(200)
+                .header("Content-Type", "application/json")
+                .body(serde_json::to_string(&sessions).unwrap().into())
+                .unwrap()
+        })
+        .or_else(|err| Ok(err.to_response()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/sessions/device",
+    tag = "sessions",
+    summary = "Log out one device",
+    description = "Revokes every session and refresh token of one login. Revoking the current one logs this device out.",
+    params(
+        ("id" = String, Query, description = "`id` of the login from `GET /sessions`"),
+    ),
+    responses(
+        (status = 200, description = "The login is revoked", body = Message),
+        (status = 401, description = "The token is missing, unknown or expired", body = ErrorBody),
+        (status = 404, description = "The user has no such login", body = ErrorBody),
+        (status = 422, description = "An input is not valid", body = ErrorBody),
+    ),
+    security(("token" = [])),
+)]
+#[tracing::instrument( skip(data_access, req), fields(request_id = req.lambda_context().request_id))]
+pub async fn revoke_session(
+    data_access: &impl DataAccess,
+    req: Request,
+) -> Result<Response<Body>, Error> {
+    let token = match auth_token(&req) {
+        Some(token) => token,
+        None => return Ok(error_response(401, "UNAUTHORIZED", "Unauthorized")),
+    };
+
+    let session_id = validation::required(
+        "id",
+        req.query_string_parameters_ref()
+            .and_then(|params| params.first("id")),
+    )
+    .and_then(|id| validation::not_blank("id", id).map(|_| id));
+    let session_id = match session_id {
+        Ok(session_id) => session_id,
+        Err(err) => return Ok(validation_response(&[err])),
+    };
+
+    data_access
+        .revoke_session(token, session_id)
+        .await
+        .map(|_| {
+            Response::builder()
+                .status(200)
+                .body("{\"message\": \"The session is revoked\"}".into())
+                .unwrap()
+        })
+        .or_else(|err| Ok(err.to_response()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/sessions/others",
+    tag = "sessions",
+    summary = "Log out every other device",
+    description = "Revokes every login of the user except the one of this request.",
+    responses(
+        (status = 200, description = "The other logins are revoked", body = Message),
+        (status = 401, description = "The token is missing, unknown or expired", body = ErrorBody),
+    ),
+    security(("token" = [])),
+)]
+#[tracing::instrument( skip(data_access, req), fields(request_id = req.lambda_context().request_id))]
+pub async fn revoke_other_sessions(
+    data_access: &impl DataAccess,
+    req: Request,
+) -> Result<Response<Body>, Error> {
+    let token = match auth_token(&req) {
+        Some(token) => token,
+        None => return Ok(error_response(401, "UNAUTHORIZED", "Unauthorized")),
+    };
+
+    data_access
+        .revoke_other_sessions(token)
+        .await
+        .map(|_| {
+            Response::builder()
+                .status(200)
+                .body("{\"message\": \"Every other session is revoked\"}".into())
+                .unwrap()
+        })
+        .or_else(|err| Ok(err.to_response()))
+}
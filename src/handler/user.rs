@@ -0,0 +1,106 @@
+use lambda_http::{tracing, Body, Error, Request, RequestExt, Response};
+
+use crate::{
+    error::{error_response, ErrorBody},
+    handler::auth_token,
+    model::user::User,
+    openapi::Message,
+    validation::{json_body, not_blank, Validate, Validator},
+    DataAccess,
+};
+
+#[utoipa::path(
+    post,
+    path = "/users",
+    tag = "users",
+    summary = "Sign up",
+    description = "Creates the user with an organization of its own, named `organization` or after the user.",
+    request_body = User,
+    responses(
+        (status = 201, description = "The user is created", body = Message),
+        (status = 400, description = "The request is malformed", body = ErrorBody),
+        (status = 409, description = "The username is taken", body = ErrorBody),
+        (status = 422, description = "An input is not valid", body = ErrorBody),
+    ),
+)]
+#[tracing::instrument(skip(data_access, req), fields(request_id = %req.lambda_context().request_id))]
+pub async fn signup<T: DataAccess>(data_access: &T, req: Request) -> Result<Response<Body>, Error> {
+    let user = match json_body::<User>(req.body()) {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+
+    data_access
+        .create_user(user)
+        .await
+        .map(|_| {
+            Response::builder()
+                .status(201)
+                .header("Content-Type", "Application/json")
+                .body("{\"message\":\"Signup successful!!\"}".into())
+                .unwrap()
+        })
+        .or_else(|err| Ok(err.to_response()))
+}
+
+#[utoipa::path(
+    put,
+    path = "/users/password",
+    tag = "users",
+    summary = "Change the password",
+    request_body = ChangePass,
+    responses(
+        (status = 200, description = "The password is changed", body = Message),
+        (status = 400, description = "The request is malformed", body = ErrorBody),
+        (status = 401, description = "The token is missing, unknown or expired", body = ErrorBody),
+        (status = 422, description = "An input is not valid", body = ErrorBody),
+    ),
+    security(("token" = [])),
+)]
+#[tracing::instrument( skip(data_access, request), fields(request_id = request.lambda_context().request_id))]
+pub async fn change_password(
+    data_access: &impl DataAccess,
+    request: Request,
+) -> Result<Response<Body>, Error> {
+    let token = match auth_token(&request) {
+        Some(token) => token,
+        None => return Ok(error_response(401, "UNAUTHORIZED", "Unauthorized")),
+    };
+
+    let passmsg = match json_body::<ChangePass>(request.body()) {
+        Ok(passmsg) => passmsg,
+        Err(response) => return Ok(response),
+    };
+
+    data_access
+        .change_pass(
+            token,
+            passmsg.old_password.as_ref(),
+            passmsg.new_password.as_ref(),
+            passmsg.revoke_other_sessions,
+        )
+        .await
+        .map(|_| {
+            Response::builder()
+                .status(200)
+                .body("{\"message\":\"Password Changed!!\"}".into())
+                .unwrap()
+        })
+        .or_else(|err| Ok(err.to_response()))
+}
+
+#[derive(Debug, serde::Deserialize, utoipa::ToSchema)]
+struct ChangePass {
+    old_password: String,
+    new_password: String,
+    /// Also log out every other device of the user.
+    #[serde(default)]
+    revoke_other_sessions: bool,
+}
+
+impl Validate for ChangePass {
+    fn validate(&self, validator: &mut Validator) {
+        validator.check(not_blank("old_password", &self.old_password));
+        validator.check(not_blank("new_password", &self.new_password));
+    }
+}
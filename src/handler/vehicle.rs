@@ -0,0 +1,616 @@
+use lambda_http::{tracing, Body, Error, Request, RequestExt, Response};
+
+use chrono::{Duration, Local};
+
+use crate::{
+    error::{error_response, validation_response, ErrorBody, FieldError},
+    export::{Export, VEHICLE_COLUMNS},
+    handler::{all_pages, auth_token, FEE_TYPES},
+    model::{
+        import::{parse_vehicles, ImportMode, ImportReport},
+        report::DueReport,
+        vehicle::{Vehicle, VehicleDetails},
+    },
+    openapi::{Binary, Message},
+    pagination::{Page, PageRequest},
+    pdf::due_report_pdf,
+    validation::{self, json_body, Validator},
+    DataAccess, SellVehicle, UpdateVehicle,
+};
+
+#[utoipa::path(
+    post,
+    path = "/vehicles",
+    tag = "vehicles",
+    summary = "Add a vehicle",
+    request_body = Vehicle,
+    responses(
+        (status = 201, description = "The vehicle is added", body = Message),
+        (status = 400, description = "The request is malformed", body = ErrorBody),
+        (status = 401, description = "The token is missing, unknown or expired", body = ErrorBody),
+        (status = 403, description = "The role of the caller does not allow it", body = ErrorBody),
+        (status = 409, description = "The vehicle is already in the record", body = ErrorBody),
+        (status = 422, description = "An input is not valid", body = ErrorBody),
+    ),
+    security(("token" = [])),
+)]
+#[tracing::instrument( skip(data_access, request), fields(request_id = request.lambda_context().request_id))]
+pub async fn add_vehicle(
+    data_access: &impl DataAccess,
+    request: Request,
+) -> Result<Response<Body>, Error> {
+    let token = match auth_token(&request) {
+        Some(token) => token,
+        None => return Ok(error_response(401, "UNAUTHORIZED", "Unauthorized")),
+    };
+
+    let car = match json_body::<Vehicle>(request.body()) {
+        Ok(car) => car,
+        Err(response) => return Ok(response),
+    };
+    data_access
+        .add_vehicle(token, car)
+        .await
+        .map(|_| {
+            Response::builder()
+                .status(201)
+                .body("{\"message\": \"new car is added\"}".into())
+                .unwrap()
+        })
+        .or_else(|err| Ok(err.to_response()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/vehicles",
+    tag = "vehicles",
+    summary = "List vehicles",
+    description = "Every unsold vehicle of the organization, or with `due` and `days` the ones due for that fee.",
+    params(
+        ("due" = Option<String>, Query, description = "Only the vehicles whose `tax`, `fitness`, `insurance` or `route` expires within `days`"),
+        ("days" = Option<u32>, Query, description = "Needed with `due`, 0 for the overdue ones"),
+        PageRequest,
+    ),
+    responses(
+        (status = 200, description = "A page of vehicles", body = Page<Vehicle>),
+        (status = 401, description = "The token is missing, unknown or expired", body = ErrorBody),
+        (status = 422, description = "An input is not valid", body = ErrorBody),
+    ),
+    security(("token" = [])),
+)]
+#[tracing::instrument( skip(data_access, request), fields(request_id = request.lambda_context().request_id))]
+pub async fn get_vehicles(
+    data_access: &impl DataAccess,
+    request: Request,
+) -> Result<Response<Body>, Error> {
+    let token = match auth_token(&request) {
+        Some(token) => token,
+        None => return Ok(error_response(401, "UNAUTHORIZED", "Unauthorized")),
+    };
+
+    let page = match PageRequest::from_query(request.query_string_parameters_ref()) {
+        Ok(page) => page,
+        Err(err) => return Ok(validation_response(&[err])),
+    };
+
+    data_access
+        .get_all_vehicle(token, page)
+        .await
+        .map(|output| {
+            let vehicles = serde_json::to_string(&output).unwrap();
+            Response::builder()
+                .status(200)
+                .body(vehicles.into())
+                .unwrap()
+        })
+        .or_else(|err| Ok(err.to_response()))
+}
+
+#[tracing::instrument( skip(data_access, req), fields(request_id = req.lambda_context().request_id))]
+pub async fn get_vehicles_by_type(
+    data_access: &impl DataAccess,
+    req: Request,
+    fee_type: &str,
+) -> Result<Response<Body>, Error> {
+    let token = match auth_token(&req) {
+        Some(token) => token,
+        None => return Ok(error_response(401, "UNAUTHORIZED", "Unauthorized")),
+    };
+
+    let params = req.query_string_parameters_ref();
+    let mut validator = Validator::new();
+    let day = validator.check(
+        validation::required("days", params.and_then(|params| params.first("days")))
+            .and_then(|days| validation::days("days", days, 0)),
+    );
+    let page = validator.check(PageRequest::from_query(params));
+    let (Some(day), Some(page)) = (day, page) else {
+        return Ok(validator.to_response());
+    };
+
+    data_access
+        .get_vehicles_by_type(token, fee_type, day, page)
+        .await
+        .map(|vehicles: Page<Vehicle>| {
+            let vehicles = serde_json::to_string(&vehicles).unwrap();
+            Response::builder()
+                .status(200)
+                .body(vehicles.into())
+                .unwrap()
+        })
+        .or_else(|err| Ok(err.to_response()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/vehicles/details",
+    tag = "vehicles",
+    summary = "Get a vehicle",
+    params(
+        ("vehicle_no" = String, Query, description = "Registration plate, e.g. `DHA-KA-11-1234`"),
+    ),
+    responses(
+        (status = 200, description = "The vehicle and its fee countdown", body = VehicleDetails),
+        (status = 401, description = "The token is missing, unknown or expired", body = ErrorBody),
+        (status = 404, description = "The vehicle is not in the record", body = ErrorBody),
+        (status = 422, description = "An input is not valid", body = ErrorBody),
+    ),
+    security(("token" = [])),
+)]
+#[tracing::instrument( skip(data_access, request), fields(request_id = request.lambda_context().request_id))]
+pub async fn get_vehicle(
+    data_access: &impl DataAccess,
+    request: Request,
+) -> Result<Response<Body>, Error> {
+    let token = match auth_token(&request) {
+        Some(token) => token,
+        None => return Ok(error_response(401, "UNAUTHORIZED", "Unauthorized")),
+    };
+
+    let vehicle_no = validation::required(
+        "vehicle_no",
+        request
+            .query_string_parameters_ref()
+            .and_then(|params| params.first("vehicle_no")),
+    )
+    .and_then(|vehicle_no| validation::vehicle_no("vehicle_no", vehicle_no));
+    let vehicle_no = match vehicle_no {
+        Ok(vehicle_no) => vehicle_no,
+        Err(err) => return Ok(validation_response(&[err])),
+    };
+
+    data_access
+        .get_vehicle(token, &vehicle_no)
+        .await
+        .map(|vehicle| {
+            let vehicle = serde_json::to_string(&vehicle).unwrap();
+            Response::builder()
+                .status(200)
+                .body(vehicle.into())
+                .unwrap()
+        })
+        .or_else(|err| Ok(err.to_response()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/vehicles/search",
+    tag = "vehicles",
+    summary = "Search vehicles",
+    params(
+        ("q" = String, Query, description = "Part of a plate, e.g. the last four digits"),
+    ),
+    responses(
+        (status = 200, description = "Matching vehicles", body = Vec<Vehicle>),
+        (status = 401, description = "The token is missing, unknown or expired", body = ErrorBody),
+        (status = 422, description = "An input is not valid", body = ErrorBody),
+    ),
+    security(("token" = [])),
+)]
+#[tracing::instrument( skip(data_access, request), fields(request_id = request.lambda_context().request_id))]
+pub async fn search_vehicles(
+    data_access: &impl DataAccess,
+    request: Request,
+) -> Result<Response<Body>, Error> {
+    let token = match auth_token(&request) {
+        Some(token) => token,
+        None => return Ok(error_response(401, "UNAUTHORIZED", "Unauthorized")),
+    };
+
+    let query = validation::required(
+        "q",
+        request
+            .query_string_parameters_ref()
+            .and_then(|params| params.first("q")),
+    )
+    .and_then(|query| validation::not_blank("q", query).map(|_| query));
+    let query = match query {
+        Ok(query) => query,
+        Err(err) => return Ok(validation_response(&[err])),
+    };
+
+    data_access
+        .search_vehicles(token, query)
+        .await
+        .map(|vehicles| {
+            let vehicles = serde_json::to_string(&vehicles).unwrap();
+            Response::builder()
+                .status(200)
+                .body(vehicles.into())
+                .unwrap()
+        })
+        .or_else(|err| Ok(err.to_response()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/payments",
+    tag = "vehicles",
+    summary = "Pay a fee",
+    description = "Moves the expiry date of `type` to the one sent, or the next one by the renewal rules, and records the payment in the history.",
+    params(
+        ("type" = String, Query, description = "`tax`, `fitness`, `insurance` or `route`"),
+    ),
+    request_body = UpdateVehicle,
+    responses(
+        (status = 200, description = "The expiry date is moved", body = Message),
+        (status = 400, description = "The request is malformed", body = ErrorBody),
+        (status = 401, description = "The token is missing, unknown or expired", body = ErrorBody),
+        (status = 403, description = "The role of the caller does not allow it", body = ErrorBody),
+        (status = 404, description = "The vehicle is not in the record", body = ErrorBody),
+        (status = 409, description = "The vehicle is sold", body = ErrorBody),
+        (status = 422, description = "An input is not valid", body = ErrorBody),
+    ),
+    security(("token" = [])),
+)]
+#[tracing::instrument( skip(data_access, request), fields(request_id = request.lambda_context().request_id))]
+pub async fn pay_fee(
+    data_access: &impl DataAccess,
+    request: Request,
+) -> Result<Response<Body>, Error> {
+    let token = match auth_token(&request) {
+        Some(token) => token,
+        None => return Ok(error_response(401, "UNAUTHORIZED", "Unauthorized")),
+    };
+
+    let fee_type = validation::required(
+        "type",
+        request
+            .query_string_parameters_ref()
+            .and_then(|params| params.first("type")),
+    )
+    .and_then(|fee_type| validation::fee_type("type", fee_type));
+    let fee_type = match fee_type {
+        Ok(fee_type) => fee_type,
+        Err(err) => return Ok(validation_response(&[err])),
+    };
+
+    let update_vehicle = match json_body::<UpdateVehicle>(request.body()) {
+        Ok(update_vehicle) => update_vehicle,
+        Err(response) => return Ok(response),
+    };
+
+    data_access
+        .pay_fee(token, fee_type, update_vehicle)
+        .await
+        .map(|_| {
+            Response::builder()
+                .status(200)
+                .body(format!("{{\"message\": \"the car {} date is updated\"}}", fee_type).into())
+                .unwrap()
+        })
+        .or_else(|err| Ok(err.to_response()))
+}
+
+#[utoipa::path(
+    put,
+    path = "/vehicles",
+    tag = "vehicles",
+    summary = "Update a vehicle",
+    request_body = UpdateVehicle,
+    responses(
+        (status = 200, description = "The vehicle is updated", body = Message),
+        (status = 400, description = "The request is malformed", body = ErrorBody),
+        (status = 401, description = "The token is missing, unknown or expired", body = ErrorBody),
+        (status = 403, description = "The role of the caller does not allow it", body = ErrorBody),
+        (status = 404, description = "The vehicle is not in the record", body = ErrorBody),
+        (status = 409, description = "The vehicle is sold", body = ErrorBody),
+        (status = 422, description = "An input is not valid", body = ErrorBody),
+    ),
+    security(("token" = [])),
+)]
+#[tracing::instrument( skip(data_access, request), fields(request_id = request.lambda_context().request_id))]
+pub async fn update_vehicle(
+    data_access: &impl DataAccess,
+    request: Request,
+) -> Result<Response<Body>, Error> {
+    let token = match auth_token(&request) {
+        Some(token) => token,
+        None => return Ok(error_response(401, "UNAUTHORIZED", "Unauthorized")),
+    };
+
+    let update_vehicle = match json_body::<UpdateVehicle>(request.body()) {
+        Ok(update_vehicle) => update_vehicle,
+        Err(response) => return Ok(response),
+    };
+
+    data_access
+        .update_vehicle(token, update_vehicle)
+        .await
+        .map(|_| {
+            Response::builder()
+                .status(200)
+                .body("{\"message\": \"the car is updated\"}".into())
+                .unwrap()
+        })
+        .or_else(|err| Ok(err.to_response()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/vehicles/sale",
+    tag = "vehicles",
+    summary = "Sell a vehicle",
+    request_body = SellVehicle,
+    responses(
+        (status = 200, description = "The vehicle is sold", body = Message),
+        (status = 400, description = "The request is malformed", body = ErrorBody),
+        (status = 401, description = "The token is missing, unknown or expired", body = ErrorBody),
+        (status = 403, description = "The role of the caller does not allow it", body = ErrorBody),
+        (status = 404, description = "The vehicle is not in the record", body = ErrorBody),
+        (status = 409, description = "The vehicle is already sold", body = ErrorBody),
+        (status = 422, description = "An input is not valid", body = ErrorBody),
+    ),
+    security(("token" = [])),
+)]
+#[tracing::instrument( skip(data_access, request), fields(request_id = request.lambda_context().request_id))]
+pub async fn sell_vehicle(
+    data_access: &impl DataAccess,
+    request: Request,
+) -> Result<Response<Body>, Error> {
+    let token = match auth_token(&request) {
+        Some(token) => token,
+        None => return Ok(error_response(401, "UNAUTHORIZED", "Unauthorized")),
+    };
+
+    let sale = match json_body::<SellVehicle>(request.body()) {
+        Ok(sale) => sale,
+        Err(response) => return Ok(response),
+    };
+
+    data_access
+        .sell_vehicle(token, sale)
+        .await
+        .map(|_| {
+            Response::builder()
+                .status(200)
+                .body("{\"message\": \"the car is sold\"}".into())
+                .unwrap()
+        })
+        .or_else(|err| Ok(err.to_response()))
+}
+
+/// Imports the vehicles of a CSV body, `?mode=upsert` to update the ones
+/// already in the record instead of skipping them. Rows that don't parse
+/// are reported next to the ones the data access rejected.
+#[utoipa::path(
+    post,
+    path = "/vehicles/import",
+    tag = "vehicles",
+    summary = "Import vehicles from CSV",
+    description = "Every row is checked on its own; the ones that are not written are listed by their CSV line.",
+    params(
+        ("mode" = Option<ImportMode>, Query, description = "What to do with vehicles already in the record"),
+    ),
+    request_body(
+        content = String,
+        content_type = "text/csv",
+        description = "`vehicle_no,owner,tax_date,fitness_date,insurance_date,route_date` and an optional `vehicle_class`"
+    ),
+    responses(
+        (status = 200, description = "What was added, updated, skipped or rejected", body = ImportReport),
+        (status = 400, description = "The request is malformed", body = ErrorBody),
+        (status = 401, description = "The token is missing, unknown or expired", body = ErrorBody),
+        (status = 403, description = "The role of the caller does not allow it", body = ErrorBody),
+        (status = 422, description = "An input is not valid", body = ErrorBody),
+    ),
+    security(("token" = [])),
+)]
+#[tracing::instrument( skip(data_access, request), fields(request_id = request.lambda_context().request_id))]
+pub async fn import_vehicles(
+    data_access: &impl DataAccess,
+    request: Request,
+) -> Result<Response<Body>, Error> {
+    let token = match auth_token(&request) {
+        Some(token) => token,
+        None => return Ok(error_response(401, "UNAUTHORIZED", "Unauthorized")),
+    };
+
+    let mode = match request
+        .query_string_parameters_ref()
+        .and_then(|params| params.first("mode"))
+        .map(|mode| mode.parse::<ImportMode>())
+        .transpose()
+    {
+        Ok(mode) => mode.unwrap_or_default(),
+        Err(err) => return Ok(validation_response(&[FieldError::new("mode", err)])),
+    };
+
+    let csv = match request.body() {
+        Body::Text(text) => text.as_str(),
+        Body::Binary(bytes) => match std::str::from_utf8(bytes) {
+            Ok(text) => text,
+            Err(_) => return Ok(error_response(400, "BAD_REQUEST", "The CSV is not UTF-8")),
+        },
+        Body::Empty => return Ok(error_response(400, "BAD_REQUEST", "The msg body is empty")),
+    };
+
+    let (rows, errors) = parse_vehicles(csv);
+    if rows.is_empty() && errors.is_empty() {
+        return Ok(error_response(
+            400,
+            "BAD_REQUEST",
+            "The CSV has no vehicles",
+        ));
+    }
+
+    data_access
+        .import_vehicles(token, rows, mode)
+        .await
+        .map(|mut report| {
+            report.errors.extend(errors);
+            report.errors.sort_by_key(|error| error.row);
+            Response::builder()
+                .status(200)
+                .body(serde_json::to_string(&report).unwrap().into())
+                .unwrap()
+        })
+        .or_else(|err| Ok(err.to_response()))
+}
+
+/// Every vehicle, or with `due` and `days` every vehicle due for that fee, as
+/// a CSV or XLSX download laid out by `Export::from_query`.
+#[utoipa::path(
+    get,
+    path = "/vehicles/export",
+    tag = "vehicles",
+    summary = "Export vehicles",
+    description = "Every vehicle, or with `due` and `days` every vehicle due for that fee, as a CSV or XLSX download.",
+    params(
+        ("format" = Option<String>, Query, description = "`csv` (default) or `xlsx`"),
+        ("columns" = Option<String>, Query, description = "Columns in order, each optionally renamed: `vehicle_no:Plate,owner`"),
+        ("date_format" = Option<String>, Query, description = "`strftime` pattern of the dates, `%Y-%m-%d` by default"),
+        ("due" = Option<String>, Query, description = "Only the vehicles due for `tax`, `fitness`, `insurance` or `route`"),
+        ("days" = Option<u32>, Query, description = "Needed with `due`"),
+    ),
+    responses(
+        (status = 200, description = "The download", content((String = "text/csv"), (Binary = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"))),
+        (status = 401, description = "The token is missing, unknown or expired", body = ErrorBody),
+        (status = 422, description = "An input is not valid", body = ErrorBody),
+        (status = 500, description = "The download could not be written", body = ErrorBody),
+    ),
+    security(("token" = [])),
+)]
+#[tracing::instrument( skip(data_access, request), fields(request_id = request.lambda_context().request_id))]
+pub async fn export_vehicles(
+    data_access: &impl DataAccess,
+    request: Request,
+) -> Result<Response<Body>, Error> {
+    let token = match auth_token(&request) {
+        Some(token) => token,
+        None => return Ok(error_response(401, "UNAUTHORIZED", "Unauthorized")),
+    };
+    let params = request.query_string_parameters_ref();
+
+    let mut validator = Validator::new();
+    let export = validator.check(Export::from_query(params, VEHICLE_COLUMNS));
+    let due = params.and_then(|params| params.first("due")).map(|due| {
+        let due = validator.check(validation::fee_type("due", due));
+        let days = validator.check(
+            validation::required("days", params.and_then(|params| params.first("days")))
+                .and_then(|days| validation::days("days", days, 0)),
+        );
+        due.zip(days)
+    });
+    let (Some(export), true) = (export, validator.is_valid()) else {
+        return Ok(validator.to_response());
+    };
+    let due = due.flatten();
+
+    let vehicles = all_pages(|page| async {
+        match &due {
+            Some((due, days)) => {
+                data_access
+                    .get_vehicles_by_type(token, due, *days, page)
+                    .await
+            }
+            None => data_access.get_all_vehicle(token, page).await,
+        }
+    })
+    .await;
+    let vehicles = match vehicles {
+        Ok(vehicles) => vehicles,
+        Err(err) => return Ok(err.to_response()),
+    };
+
+    let name = match &due {
+        Some((due, _)) => format!("{}-due", due),
+        None => "vehicles".to_string(),
+    };
+    Ok(export.to_response(&vehicles, &name).unwrap_or_else(|err| {
+        tracing::error!(%err, "Export is not written");
+        error_response(500, "EXPORT_ERROR", "The export could not be written")
+    }))
+}
+
+/// A printable PDF of the fees that are overdue or due within `days` (7 by
+/// default), from the same queries as `get_vehicles_by_type`.
+#[utoipa::path(
+    get,
+    path = "/reports/due",
+    tag = "reports",
+    summary = "Due report PDF",
+    description = "A section per fee type with a table per owner of the fees that are overdue or due within `days`.",
+    params(
+        ("days" = Option<u32>, Query, description = "Fees due within this many days, 7 by default"),
+    ),
+    responses(
+        (status = 200, description = "Printable report of the overdue and due fees", body = Binary, content_type = "application/pdf"),
+        (status = 401, description = "The token is missing, unknown or expired", body = ErrorBody),
+        (status = 422, description = "An input is not valid", body = ErrorBody),
+    ),
+    security(("token" = [])),
+)]
+#[tracing::instrument( skip(data_access, request), fields(request_id = request.lambda_context().request_id))]
+pub async fn get_due_report(
+    data_access: &impl DataAccess,
+    request: Request,
+) -> Result<Response<Body>, Error> {
+    let token = match auth_token(&request) {
+        Some(token) => token,
+        None => return Ok(error_response(401, "UNAUTHORIZED", "Unauthorized")),
+    };
+
+    let days = match request
+        .query_string_parameters_ref()
+        .and_then(|params| params.first("days"))
+        .map(|days| validation::days("days", days, 1))
+    {
+        None => 7,
+        Some(Ok(days)) => days,
+        Some(Err(err)) => return Ok(validation_response(&[err])),
+    };
+
+    let mut due = Vec::new();
+    for fee_type in FEE_TYPES {
+        // `days = 0` lists the overdue vehicles, any other the ones due from
+        // today on.
+        let mut vehicles = Vec::new();
+        for days in [0, days] {
+            let page =
+                all_pages(|page| data_access.get_vehicles_by_type(token, fee_type, days, page))
+                    .await;
+            match page {
+                Ok(page) => vehicles.extend(page),
+                Err(err) => return Ok(err.to_response()),
+            }
+        }
+        due.push((fee_type, vehicles));
+    }
+
+    let today = Local::now().date_naive();
+    let report = DueReport::new(today, today + Duration::days(days as i64), due);
+
+    Ok(Response::builder()
+        .status(200)
+        .header("Content-Type", "application/pdf")
+        .header(
+            "Content-Disposition",
+            format!(
+                "inline; filename=\"due-report-{}.pdf\"",
+                report.generated_on
+            ),
+        )
+        .body(Body::Binary(due_report_pdf(&report)))
+        .unwrap())
+}
@@ -1,59 +1,244 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use async_trait::async_trait;
+use aws_config::BehaviorVersion;
 use aws_sdk_dynamodb::{
-    types::{AttributeValue, Delete, Put, TransactWriteItem, Update},
+    error::SdkError,
+    operation::transact_write_items::TransactWriteItemsError,
+    types::{
+        AttributeValue, Delete, KeysAndAttributes, Put, ReturnValuesOnConditionCheckFailure,
+        TransactWriteItem, Update,
+    },
     Client,
 };
-use chrono::{Duration, Local, NaiveDate, SecondsFormat, Utc};
-use lambda_http::{
-    tracing::{self},
-    Error,
-};
+use chrono::{Duration, Local, SecondsFormat, Utc};
+use error::DataAccessError;
+use handler::FEE_TYPES;
+use lambda_http::tracing::{self};
 use model::{
-    history::{history_from_item, history_key, history_repo, TransactionHistory},
-    session::{session_key, Session},
-    user::{from_item, user_key, User},
-    vehicle::{vehicle_key, vehicle_repo, vehicle_search_key, Vehicle},
+    calendar::{calendar_feed_from_item, feed_key, CalendarFeed},
+    history::{
+        history_from_item, history_key, history_partition_key, history_repo, Payment,
+        TransactionHistory, OWNERSHIP_TRANSACTION,
+    },
+    import::{ImportMode, ImportReport, ImportRow},
+    organization::{member_item, org_from_item, org_key, Organization},
+    reminder::{reminder_partition_key, Recipient, Reminder},
+    report::SpendingReport,
+    session::{
+        caller_from_item, check_expiry, device_from_item, device_key, last_seen_is_stale, owner_of,
+        refresh_from_item, refresh_key, session_key, ttl_value, Caller, Device, DeviceSession,
+        Logins, RefreshToken, Tokens,
+    },
+    user::{from_item, roles_value, user_key, Role, User},
+    vehicle::{
+        search_partition_key, sold_vehicle_partition_key, vehicle_details_from_item, vehicle_key,
+        vehicle_no_from_item, vehicle_no_from_key, vehicle_partition_key, vehicle_repo,
+        vehicle_search_key, Vehicle, VehicleDetails,
+    },
+    vehicle_number::VehicleNumber,
 };
+use pagination::{Page, PageRequest};
 use pwhash::bcrypt;
+use renewal::RenewalRules;
 use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+pub use memory::InMemoryDataAccess;
 
+pub mod calendar;
+pub mod error;
+pub mod export;
+pub mod handler;
+pub mod memory;
+pub mod migration;
 pub mod model;
+pub mod notifier;
+pub mod openapi;
+pub mod pagination;
+pub mod pdf;
+pub mod renewal;
+pub mod router;
+pub mod validation;
 
 #[async_trait]
 pub trait DataAccess {
-    async fn create_user(&self, user: User) -> Result<(), Error>;
-    async fn get_session(&self, user: User) -> Result<Session, Error>;
-    async fn delete_session(&self, token: &str) -> Result<String, Error>;
-    async fn change_pass(&self, token: &str, old_pass: &str, new_pass: &str) -> Result<(), Error>;
-    async fn add_vehicle(&self, token: &str, car: Vehicle) -> Result<(), Error>;
-    async fn get_all_vehicle(&self, token: &str) -> Result<Vec<Vehicle>, Error>;
+    async fn create_user(&self, user: User) -> Result<(), DataAccessError>;
+    /// Logs the user in from `device`, starting a login that shows up in
+    /// `list_sessions`.
+    async fn get_session(&self, user: User, device: Device) -> Result<Tokens, DataAccessError>;
+    /// Exchanges a refresh token for a new session and refresh token. A token
+    /// that was already exchanged revokes every session and refresh token of
+    /// its login, since one of the two parties holding it is not the user.
+    /// The user agent and address of `device` replace the login's.
+    async fn refresh_session(
+        &self,
+        refresh_token: &str,
+        device: Device,
+    ) -> Result<Tokens, DataAccessError>;
+    /// Logs out the login `token` belongs to, leaving the user's other
+    /// devices signed in.
+    async fn delete_session(&self, token: &str) -> Result<String, DataAccessError>;
+    /// The logins of the caller that have not expired, the most recently
+    /// used first.
+    async fn list_sessions(&self, token: &str) -> Result<Vec<DeviceSession>, DataAccessError>;
+    /// Revokes one login of the caller by its `id` from `list_sessions`.
+    async fn revoke_session(&self, token: &str, session_id: &str) -> Result<(), DataAccessError>;
+    /// Revokes every login of the caller except the one `token` belongs to.
+    async fn revoke_other_sessions(&self, token: &str) -> Result<(), DataAccessError>;
+    /// Changes the password, and with `revoke_others` also revokes every
+    /// login of the user except the one `token` belongs to.
+    async fn change_pass(
+        &self,
+        token: &str,
+        old_pass: &str,
+        new_pass: &str,
+        revoke_others: bool,
+    ) -> Result<(), DataAccessError>;
+    async fn add_vehicle(&self, token: &str, car: Vehicle) -> Result<(), DataAccessError>;
+    /// Adds the vehicles of `rows`, skipping or updating the ones already in
+    /// the record as `mode` says. Each vehicle is written together with its
+    /// SEARCH item; rows that can't be written come back in `errors`.
+    async fn import_vehicles(
+        &self,
+        token: &str,
+        rows: Vec<ImportRow>,
+        mode: ImportMode,
+    ) -> Result<ImportReport, DataAccessError>;
+    async fn get_all_vehicle(
+        &self,
+        token: &str,
+        page: PageRequest,
+    ) -> Result<Page<Vehicle>, DataAccessError>;
     async fn get_vehicles_by_type(
         &self,
         token: &str,
         fee_type: &str,
         days: u32,
-    ) -> Result<Vec<Vehicle>, Error>;
+        page: PageRequest,
+    ) -> Result<Page<Vehicle>, DataAccessError>;
+    async fn get_vehicle(
+        &self,
+        token: &str,
+        vehicle_no: &VehicleNumber,
+    ) -> Result<VehicleDetails, DataAccessError>;
+    /// Finds vehicles by the start of their plate, or by their last digits
+    /// when `query` is at most four digits.
+    async fn search_vehicles(
+        &self,
+        token: &str,
+        query: &str,
+    ) -> Result<Vec<Vehicle>, DataAccessError>;
     async fn pay_fee(
         &self,
         token: &str,
         fee_type: &str,
         update_vehicle: UpdateVehicle,
-    ) -> Result<(), Error>;
-    async fn update_vehicle(&self, token: &str, update_vheicle: UpdateVehicle)
-        -> Result<(), Error>;
-    async fn view_history(&self, token: &str, days: u32) -> Result<Vec<TransactionHistory>, Error>;
-    async fn undo_history(&self, token: &str, delete_history: DeleteHistory) -> Result<(), Error>;
+    ) -> Result<(), DataAccessError>;
+    async fn update_vehicle(
+        &self,
+        token: &str,
+        update_vheicle: UpdateVehicle,
+    ) -> Result<(), DataAccessError>;
+    async fn view_history(
+        &self,
+        token: &str,
+        days: u32,
+        page: PageRequest,
+    ) -> Result<Page<TransactionHistory>, DataAccessError>;
+    /// Marks a vehicle as sold to the buyer, who becomes its owner, and
+    /// records the transfer in its history. Sold vehicles leave the GSI2
+    /// listings but keep their history.
+    async fn sell_vehicle(&self, token: &str, sale: SellVehicle) -> Result<(), DataAccessError>;
+    /// Payments of one vehicle, grouped by fee type and newest first.
+    async fn get_vehicle_history(
+        &self,
+        token: &str,
+        vehicle_no: &VehicleNumber,
+        filter: HistoryFilter,
+        page: PageRequest,
+    ) -> Result<Page<TransactionHistory>, DataAccessError>;
+    /// Fee payments of the organization in the range of `filter`, summed up
+    /// per vehicle, fee type, owner and month.
+    async fn spending_report(
+        &self,
+        token: &str,
+        filter: HistoryFilter,
+    ) -> Result<SpendingReport, DataAccessError>;
+    async fn undo_history(
+        &self,
+        token: &str,
+        delete_history: DeleteHistory,
+    ) -> Result<(), DataAccessError>;
+    /// Creates an organization with the caller as its first member.
+    async fn create_organization(
+        &self,
+        token: &str,
+        name: &str,
+    ) -> Result<Organization, DataAccessError>;
+    /// Adds an existing user to the organization of the caller's session.
+    async fn add_member(
+        &self,
+        token: &str,
+        username: &str,
+        role: Role,
+    ) -> Result<(), DataAccessError>;
+    /// Changes the role of a member of the caller's organization.
+    async fn set_role(
+        &self,
+        token: &str,
+        username: &str,
+        role: Role,
+    ) -> Result<(), DataAccessError>;
+    async fn get_organizations(&self, token: &str) -> Result<Vec<Organization>, DataAccessError>;
+    /// Fees of every organization that expire within `days` or already
+    /// expired, leaving out the recipients it was already sent to. Needs no
+    /// session, it is meant for the scheduled `reminders` job.
+    async fn due_reminders(&self, days: u32) -> Result<Vec<Reminder>, DataAccessError>;
+    /// Records that `reminder` is being sent to `recipient`, unless it was
+    /// already. Returns `false` when another run claimed it first, then the
+    /// caller must not send it.
+    async fn claim_reminder(
+        &self,
+        reminder: &Reminder,
+        recipient: &Recipient,
+    ) -> Result<bool, DataAccessError>;
+    /// Drops the claim of a reminder that could not be sent, so the next run
+    /// tries again.
+    async fn release_reminder(
+        &self,
+        reminder: &Reminder,
+        recipient: &Recipient,
+    ) -> Result<(), DataAccessError>;
+    /// Gives the caller a new calendar feed of the session's organization,
+    /// which replaces their previous one.
+    async fn create_calendar_feed(&self, token: &str) -> Result<CalendarFeed, DataAccessError>;
+    /// Every unsold vehicle of the organization behind a calendar feed token.
+    /// Needs no session; the feed stops working once its user leaves the
+    /// organization.
+    async fn calendar_vehicles(
+        &self,
+        feed_token: &str,
+    ) -> Result<(CalendarFeed, Vec<Vehicle>), DataAccessError>;
 }
 
-#[derive(Debug, Deserialize, Default)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct UpdateVehicle {
-    pub vehicle_no: String,
+    pub vehicle_no: VehicleNumber,
+    #[schema(format = Date)]
     pub tax_date: Option<String>,
+    #[schema(format = Date)]
     pub insurance_date: Option<String>,
+    #[schema(format = Date)]
     pub route_date: Option<String>,
+    #[schema(format = Date)]
     pub fitness_date: Option<String>,
+    pub owner: Option<String>,
+    /// Picks the renewal rules of the vehicle, e.g. `private` or `commercial`.
+    pub vehicle_class: Option<String>,
+    /// Written to the history entry by `pay_fee`, ignored by `update_vehicle`.
+    #[serde(flatten)]
+    pub payment: Payment,
 }
 
 struct UpdateVehicleIter<'a> {
@@ -90,71 +275,394 @@ impl<'a> Iterator for UpdateVehicleIter<'a> {
 }
 
 impl UpdateVehicle {
-    fn iter(&self) -> UpdateVehicleIter {
+    fn iter(&self) -> UpdateVehicleIter<'_> {
         UpdateVehicleIter {
             unpdate_vehicle: self,
             index: 0,
         }
     }
+
+    /// An update of the `fee_type` date of `vehicle_no` alone, as
+    /// `undo_history` writes it back. `None` for a type without a date.
+    fn fee_date(vehicle_no: VehicleNumber, fee_type: &str, date: String) -> Option<Self> {
+        let mut update_vehicle = Self {
+            vehicle_no,
+            tax_date: None,
+            insurance_date: None,
+            route_date: None,
+            fitness_date: None,
+            owner: None,
+            vehicle_class: None,
+            payment: Payment::default(),
+        };
+        let fee_date = match fee_type {
+            "tax" => &mut update_vehicle.tax_date,
+            "insurance" => &mut update_vehicle.insurance_date,
+            "fitness" => &mut update_vehicle.fitness_date,
+            "route" => &mut update_vehicle.route_date,
+            _ => return None,
+        };
+        *fee_date = Some(date);
+        Some(update_vehicle)
+    }
+
+    /// Fills in the new `fee_type` date from `rules` unless the client sent
+    /// one, counting from the current `expiry` or from today once it lapsed.
+    fn renew(
+        &mut self,
+        rules: &RenewalRules,
+        fee_type: &str,
+        vehicle_item: &HashMap<String, AttributeValue>,
+        expiry: &str,
+    ) {
+        let date = match fee_type {
+            "tax" => &mut self.tax_date,
+            "insurance" => &mut self.insurance_date,
+            "fitness" => &mut self.fitness_date,
+            "route" => &mut self.route_date,
+            _ => return,
+        };
+        if date.is_some() {
+            return;
+        }
+
+        let vehicle_class = self.vehicle_class.as_deref().or_else(|| {
+            vehicle_item
+                .get("vehicle_class")
+                .and_then(|vehicle_class| vehicle_class.as_s().ok())
+                .map(String::as_str)
+        });
+        // A vehicle stored without a valid date renews from today.
+        let today = Local::now().date_naive();
+        let expiry = validation::date(fee_type, expiry).unwrap_or(today);
+        *date = rules
+            .next_expiry(fee_type, vehicle_class, expiry, today)
+            .map(|date| date.format("%Y-%m-%d").to_string());
+    }
+
+    /// The fee dates sent, as `(":tax_date", "2024-01-31")`, each checked and
+    /// written back as `YYYY-MM-DD`.
+    fn fee_dates(&self) -> Result<Vec<(String, String)>, DataAccessError> {
+        self.iter()
+            .filter_map(|(fee, date)| date.map(|date| (fee, date)))
+            .map(|(fee, date)| {
+                let date = validation::iso_date(fee.trim_start_matches(':'), date)?;
+                Ok((fee, date))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SellVehicle {
+    pub vehicle_no: VehicleNumber,
+    pub buyer: String,
+    /// `YYYY-MM-DD`, today when left out.
+    #[schema(format = Date)]
+    pub sale_date: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+/// Identifies the history entry to undo, as listed by the history endpoints.
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct DeleteHistory {
-    vehicle_no: String,
-    transaction_type: String,
-    created_at: String,
+    pub vehicle_no: VehicleNumber,
+    pub transaction_type: String,
+    pub created_at: String,
+}
+
+/// Narrows `get_vehicle_history` and `spending_report` to one fee type and/or
+/// a range of payment dates (`YYYY-MM-DD`, both ends included).
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HistoryFilter {
+    /// `tax`, `fitness`, `insurance` or `route`.
+    #[param(rename = "type")]
+    pub transaction_type: Option<String>,
+    #[param(format = Date)]
+    pub from: Option<String>,
+    #[param(format = Date)]
+    pub to: Option<String>,
+}
+
+impl HistoryFilter {
+    fn date_range(&self) -> (String, String) {
+        (
+            self.from.clone().unwrap_or("0000-01-01".to_string()),
+            self.to.clone().unwrap_or("9999-12-31".to_string()),
+        )
+    }
+}
+
+fn is_sold(vehicle_item: &HashMap<String, AttributeValue>) -> bool {
+    vehicle_item
+        .get("Sold")
+        .and_then(|sold| sold.as_bool().ok())
+        .copied()
+        .unwrap_or(false)
+}
+
+/// Normalizes a plate search and tells whether it targets the last four
+/// digits (`LSI1SK`) rather than the start of the plate (`SK`).
+fn search_query(query: &str) -> Result<(String, bool), DataAccessError> {
+    let query = VehicleNumber::key_prefix(query);
+    if query.is_empty() {
+        return Err(DataAccessError::Validation(
+            "The search query is empty!!".to_string(),
+        ));
+    }
+    let last_digits = query.len() <= 4 && query.chars().all(|c| c.is_ascii_digit());
+    Ok((query, last_digits))
+}
+
+/// Picks the organization a new session is scoped to: the requested one when
+/// the user belongs to it, otherwise the user's first organization.
+fn session_org(requested: Option<&str>, orgs: &[String]) -> Result<String, DataAccessError> {
+    match requested {
+        Some(org_id) if orgs.iter().any(|org| org == org_id) => Ok(org_id.to_string()),
+        Some(_) => Err(DataAccessError::Unauthorized(
+            "You are not a member of this organization!!".to_string(),
+        )),
+        None => orgs.first().cloned().ok_or_else(|| {
+            DataAccessError::Unauthorized("You are not a member of any organization!!".to_string())
+        }),
+    }
+}
+
+/// Why the transaction of one import row was canceled, from the reason of
+/// its first item that failed. `None` when the import failed for another
+/// reason than a canceled transaction.
+fn import_failure<R>(err: &SdkError<TransactWriteItemsError, R>, update: bool) -> Option<String> {
+    let TransactWriteItemsError::TransactionCanceledException(canceled) = err.as_service_error()?
+    else {
+        return None;
+    };
+    let reason = canceled
+        .cancellation_reasons()
+        .iter()
+        .find(|reason| reason.code().is_some_and(|code| code != "None"));
+
+    Some(match reason.and_then(|reason| reason.code()) {
+        Some("ConditionalCheckFailed") => match (update, reason.and_then(|r| r.item())) {
+            (true, Some(item)) if is_sold(item) => "The car is sold!!".to_string(),
+            (true, _) => "The car is not in the record!!".to_string(),
+            (false, _) => "Vehicle already exists".to_string(),
+        },
+        Some("TransactionConflict") => {
+            "The vehicle is being changed by another request, try again".to_string()
+        }
+        Some("ThrottlingError") | Some("ProvisionedThroughputExceeded") => {
+            "The table is busy, try again".to_string()
+        }
+        code => {
+            let detail = reason
+                .and_then(|reason| reason.message())
+                .or(code)
+                .unwrap_or("unknown reason");
+            tracing::error!(detail, "Import row is not written");
+            format!("The vehicle is not written: {}", detail)
+        }
+    })
+}
+
+/// The error of a transaction that writes the `Update` of
+/// `DBDataAccess::update_vehicle`, whose condition fails once the vehicle is
+/// sold or no longer there.
+fn update_failure<R: std::fmt::Debug>(
+    err: SdkError<TransactWriteItemsError, R>,
+) -> DataAccessError {
+    let failed = match err.as_service_error() {
+        Some(TransactWriteItemsError::TransactionCanceledException(canceled)) => canceled
+            .cancellation_reasons()
+            .iter()
+            .find(|reason| reason.code() == Some("ConditionalCheckFailed")),
+        _ => None,
+    };
+
+    match failed {
+        Some(reason) if reason.item().is_some_and(is_sold) => {
+            DataAccessError::Conflict("The car is sold!!".to_string())
+        }
+        Some(_) => DataAccessError::NotFound("The car is not in the record!!".to_string()),
+        None => {
+            tracing::error!(%err, "Error Message");
+            err.into()
+        }
+    }
 }
 
 pub struct DBDataAccess {
     client: Client,
     table_name: String,
+    renewal_rules: RenewalRules,
 }
 
 impl DBDataAccess {
     pub fn new(client: Client, table_name: String) -> Self {
-        Self { client, table_name }
+        Self {
+            client,
+            table_name,
+            renewal_rules: RenewalRules::default(),
+        }
     }
 
-    fn date_formatter(&self, date: &str) -> NaiveDate {
-        let date: Vec<u32> = date.split("-").map(|d| d.parse::<u32>().unwrap()).collect();
-        NaiveDate::from_ymd_opt(date[0] as i32, date[1], date[2]).unwrap()
+    pub fn with_renewal_rules(self, renewal_rules: RenewalRules) -> Self {
+        Self {
+            renewal_rules,
+            ..self
+        }
     }
 
-    async fn create_session(&self, user: User) -> Result<Session, Error> {
-        tracing::warn!("USER: {:?}", user);
-        let session_item = Session::new().to_item(&user.username[..]);
-        tracing::info!("SESSION ==> {:#?}", session_item);
-        self.client
-            .put_item()
+    /// Loads the AWS config from the environment and uses the `TABLE_NAME`
+    /// table (`VehicleDB` when unset). Set `DYNAMODB_ENDPOINT` to talk to
+    /// DynamoDB Local, e.g. `http://localhost:8000`. Renewal rules come from
+    /// `RENEWAL_RULES`, see `RenewalRules::from_env`; invalid rules are an
+    /// error.
+    pub async fn from_env() -> Result<Self, String> {
+        let table_name = std::env::var("TABLE_NAME").unwrap_or("VehicleDB".to_string());
+        let mut sdk_config = aws_config::defaults(BehaviorVersion::latest());
+        if let Ok(endpoint) = std::env::var("DYNAMODB_ENDPOINT") {
+            sdk_config = sdk_config.endpoint_url(endpoint);
+        }
+        let sdk_config = sdk_config.load().await;
+
+        Ok(Self::new(Client::new(&sdk_config), table_name)
+            .with_renewal_rules(RenewalRules::from_env()?))
+    }
+
+    async fn create_session(
+        &self,
+        user: User,
+        org_id: &str,
+        device: Device,
+    ) -> Result<Tokens, DataAccessError> {
+        let (session, refresh) = RefreshToken::start_family(org_id);
+        let put_device = Put::builder()
+            .table_name(&self.table_name)
+            .set_item(Some(
+                DeviceSession::new(device, &session, &refresh).to_item(&user.username),
+            ))
+            .build()
+            .unwrap();
+        let put_session = Put::builder()
+            .table_name(&self.table_name)
+            .set_item(Some(session.to_item(&user.username)))
+            .build()
+            .unwrap();
+        let put_refresh = Put::builder()
             .table_name(&self.table_name)
-            .set_item(Some(session_item.clone()))
-            // .return_values(aws_sdk_dynamodb::types::ReturnValue::AllNew)
+            .set_item(Some(refresh.to_item(&user.username)))
+            .build()
+            .unwrap();
+
+        self.client
+            .transact_write_items()
+            .transact_items(TransactWriteItem::builder().put(put_session).build())
+            .transact_items(TransactWriteItem::builder().put(put_refresh).build())
+            .transact_items(TransactWriteItem::builder().put(put_device).build())
             .send()
             .await
-            .and_then(|output| {
+            .map(|output| {
                 tracing::info!("OUTPUT: {:#?}", output);
-                let item = output.attributes;
-                tracing::info!("ITEM: {:?}", item);
-                Ok(Session {
-                    session_id: session_item.get("SK").unwrap().as_s().unwrap().as_str()[8..]
-                        .to_string(),
-                    created_at: session_item
-                        .get("created_at")
-                        .unwrap()
-                        .as_s()
-                        .unwrap()
-                        .to_string(),
-                    expired_at: session_item
-                        .get("expired_at")
-                        .unwrap()
-                        .as_s()
-                        .unwrap()
-                        .to_string(),
-                })
+                Tokens::new(&session, &refresh)
             })
-            .or_else(|err| Err(err.into()))
+            .map_err(|err| err.into())
+    }
+
+    /// Deletes the sessions, refresh tokens and device items of the
+    /// `logins` of `username`.
+    async fn delete_tokens(
+        &self,
+        username: &str,
+        logins: Logins<'_>,
+    ) -> Result<(), DataAccessError> {
+        let mut query = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .key_condition_expression("#pk = :username")
+            .expression_attribute_names("#pk", "PK")
+            .expression_attribute_names("#sk", "SK")
+            .expression_attribute_values(":username", user_key(username))
+            .expression_attribute_values(":session", AttributeValue::S("SESSION#".to_string()))
+            .expression_attribute_values(":refresh", AttributeValue::S("REFRESH#".to_string()))
+            .expression_attribute_values(":device", AttributeValue::S("DEVICE#".to_string()));
+        let tokens = "(begins_with(#sk, :session) or begins_with(#sk, :refresh) or begins_with(#sk, :device))";
+        let filter = match logins {
+            Logins::All => tokens.to_string(),
+            Logins::Only(family_id) => {
+                query = query.expression_attribute_values(
+                    ":family_id",
+                    AttributeValue::S(family_id.to_string()),
+                );
+                format!("{} and family_id = :family_id", tokens)
+            }
+            Logins::AllBut(family_id) => {
+                query = query.expression_attribute_values(
+                    ":family_id",
+                    AttributeValue::S(family_id.to_string()),
+                );
+                format!(
+                    "{} and (attribute_not_exists(family_id) or family_id <> :family_id)",
+                    tokens
+                )
+            }
+        };
+
+        let items = query
+            .filter_expression(filter)
+            .send()
+            .await?
+            .items
+            .unwrap_or_default();
+
+        for item in items {
+            self.client
+                .delete_item()
+                .table_name(&self.table_name)
+                .key("PK", user_key(username))
+                .key("SK", item.get("SK").unwrap().to_owned())
+                .send()
+                .await
+                .map_err(|err| {
+                    tracing::error!("{:#?}", err);
+                    DataAccessError::from(err)
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Moves the `last_seen_at` of a session and its device item to now. A
+    /// failure only costs accuracy, so it is logged and not returned.
+    async fn touch_session(&self, session: &HashMap<String, AttributeValue>) {
+        let now = AttributeValue::S(Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true));
+        let family_id = session.get("family_id").unwrap().as_s().unwrap();
+        for (sk, condition) in [
+            (session.get("SK").unwrap().to_owned(), None),
+            (device_key(family_id), Some("attribute_exists(PK)")),
+        ] {
+            let output = self
+                .client
+                .update_item()
+                .table_name(&self.table_name)
+                .key("PK", session.get("PK").unwrap().to_owned())
+                .key("SK", sk)
+                .update_expression("SET last_seen_at = :now")
+                .set_condition_expression(condition.map(str::to_string))
+                .expression_attribute_values(":now", now.clone())
+                .send()
+                .await;
+            if let Err(err) = output {
+                tracing::warn!(%err, "last_seen_at is not updated");
+            }
+        }
     }
-    async fn is_login_successful(&self, username: &str, password: &str) -> bool {
+
+    /// Returns the stored user when `password` matches.
+    async fn login_user(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> Result<Option<User>, DataAccessError> {
         let item = self
             .client
             .get_item()
@@ -162,21 +670,25 @@ impl DBDataAccess {
             .key("PK", user_key(username))
             .key("SK", user_key(username))
             .send()
-            .await
-            .unwrap()
+            .await?
             .item;
 
         match item {
             Some(item) => {
                 let user: User = from_item(&item);
-                tracing::info!("*****User Info****{:?}", user);
-                user.varify(password)
+                Ok(user.varify(password).then_some(user))
             }
-            None => false,
+            None => Ok(None),
         }
     }
 
-    async fn get_fees_info(&self, index_type: &str, days: u32) -> Result<Vec<Vehicle>, Error> {
+    async fn get_fees_info(
+        &self,
+        org_id: &str,
+        index_type: &str,
+        days: u32,
+        page: PageRequest,
+    ) -> Result<Page<Vehicle>, DataAccessError> {
         let query = self
             .client
             .query()
@@ -184,48 +696,74 @@ impl DBDataAccess {
             .index_name("GSI2")
             .key_condition_expression("#feesPK = :feesPK")
             .expression_attribute_names("#feesPK", "GSI2PK")
-            .expression_attribute_values(":feesPK", AttributeValue::S(format!("VEHICLE")));
-
-        let vehicle_items = match days {
-            0 => query
-                .filter_expression("#date < :date")
-                .expression_attribute_names("#date", format!("{}_date", index_type.to_lowercase()))
-                .expression_attribute_values(
-                    ":date",
-                    AttributeValue::S(Local::now().format("%Y-%m-%d").to_string()),
-                )
-                .send()
-                .await
-                .unwrap()
-                .items
-                .unwrap(),
-            _ => query
-                .filter_expression("#date between :sdate and :edate")
-                .expression_attribute_names("#date", format!("{}_date", index_type.to_lowercase()))
-                .expression_attribute_values(
-                    ":sdate",
-                    AttributeValue::S(Local::now().format("%Y-%m-%d").to_string()),
-                )
-                .expression_attribute_values(
-                    ":edate",
-                    AttributeValue::S(
-                        (Local::now() + Duration::days(days as i64))
-                            .format("%Y-%m-%d")
-                            .to_string(),
-                    ),
-                )
-                .send()
-                .await
-                .unwrap()
-                .items
-                .unwrap(),
+            .expression_attribute_values(":feesPK", vehicle_partition_key(org_id))
+            .set_limit(page.limit)
+            .set_exclusive_start_key(page.exclusive_start_key(
+                &["PK", "SK", "GSI2PK"],
+                ("GSI2PK", &vehicle_partition_key(org_id)),
+            )?);
+
+        let output = match days {
+            0 => {
+                query
+                    .filter_expression("#date < :date")
+                    .expression_attribute_names(
+                        "#date",
+                        format!("{}_date", index_type.to_lowercase()),
+                    )
+                    .expression_attribute_values(
+                        ":date",
+                        AttributeValue::S(Local::now().format("%Y-%m-%d").to_string()),
+                    )
+                    .send()
+                    .await?
+            }
+            _ => {
+                query
+                    .filter_expression("#date between :sdate and :edate")
+                    .expression_attribute_names(
+                        "#date",
+                        format!("{}_date", index_type.to_lowercase()),
+                    )
+                    .expression_attribute_values(
+                        ":sdate",
+                        AttributeValue::S(Local::now().format("%Y-%m-%d").to_string()),
+                    )
+                    .expression_attribute_values(
+                        ":edate",
+                        AttributeValue::S(
+                            (Local::now() + Duration::days(days as i64))
+                                .format("%Y-%m-%d")
+                                .to_string(),
+                        ),
+                    )
+                    .send()
+                    .await?
+            }
         };
 
-        Ok(vehicle_repo(vehicle_items))
+        Ok(Page::new(
+            vehicle_repo(output.items.unwrap_or_default()),
+            output.last_evaluated_key,
+        ))
     }
 
-    async fn get_user(&self, token: &str) -> Option<AttributeValue> {
-        let user = self
+    async fn find_user(&self, username: &str) -> Result<Option<User>, DataAccessError> {
+        Ok(self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("PK", user_key(username))
+            .key("SK", user_key(username))
+            .send()
+            .await?
+            .item
+            .map(|item| from_item(&item)))
+    }
+
+    /// Resolves a session token to the user, organization and role it belongs to.
+    async fn get_caller(&self, token: &str) -> Result<Option<Caller>, DataAccessError> {
+        let session = self
             .client
             .query()
             .table_name(&self.table_name)
@@ -234,25 +772,47 @@ impl DBDataAccess {
             .expression_attribute_names("#session_id", "GSI1PK")
             .expression_attribute_values(":token", session_key(token))
             .send()
-            .await
-            .unwrap()
-            .items?;
+            .await?
+            .items
+            .unwrap_or_default();
 
-        tracing::info!("USER: {:#?}", user);
+        // GSI1 only projects the keys, the organization lives on the session item.
+        let session = match session.first() {
+            Some(session) => session,
+            None => return Ok(None),
+        };
 
-        let user = user
-            .iter()
-            .next()
-            .map(|user| user.get("GSI1SK").unwrap().to_owned());
+        let session = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("PK", session.get("GSI1SK").unwrap().to_owned())
+            .key("SK", session.get("GSI1PK").unwrap().to_owned())
+            .send()
+            .await?
+            .item;
 
-        user
-    }
+        let session = match session {
+            Some(session) => session,
+            None => return Ok(None),
+        };
+        check_expiry(&session)?;
+        if last_seen_is_stale(&session) {
+            self.touch_session(&session).await;
+        }
 
-    async fn is_session_vaild(&self, token: &str) -> bool {
-        self.get_user(token).await.is_some()
+        let username = &session.get("PK").unwrap().as_s().unwrap()[5..];
+        Ok(self
+            .find_user(username)
+            .await?
+            .and_then(|user| caller_from_item(&session, &user)))
     }
 
-    async fn update_vehicle(&self, vehicle: &UpdateVehicle) -> Result<TransactWriteItem, &str> {
+    async fn update_vehicle(
+        &self,
+        org_id: &str,
+        vehicle: &UpdateVehicle,
+    ) -> Result<TransactWriteItem, DataAccessError> {
         let expression: String = vehicle
             .iter()
             .map(|(fee, date)| {
@@ -262,12 +822,32 @@ impl DBDataAccess {
                     format!("{} = {}", fee.replace(":", ""), fee)
                 }
             })
-            .filter(|value| value != "")
+            .filter(|value| !value.is_empty())
             .collect::<Vec<String>>()
             .join(", ");
 
+        let expression = [
+            expression,
+            vehicle
+                .owner
+                .as_ref()
+                .map(|_| "#owner = :owner".to_string())
+                .unwrap_or_default(),
+            vehicle
+                .vehicle_class
+                .as_ref()
+                .map(|_| "vehicle_class = :vehicle_class".to_string())
+                .unwrap_or_default(),
+        ]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<String>>()
+        .join(", ");
+
         if expression.trim().is_empty() {
-            return Err("No updated fee date is provided");
+            return Err(DataAccessError::Validation(
+                "No updated fee date, owner or vehicle class is provided".to_string(),
+            ));
         }
         let expression = format!("SET {}, updated_at = :updated_at", expression);
 
@@ -275,52 +855,69 @@ impl DBDataAccess {
             .client
             .get_item()
             .table_name(&self.table_name)
-            .key("PK", AttributeValue::S("SEARCH".to_string()))
-            .key("SK", vehicle_search_key(&vehicle.vehicle_no))
+            .key("PK", search_partition_key(org_id))
+            .key("SK", vehicle_search_key(&vehicle.vehicle_no.as_key()))
             .send()
-            .await
-            .unwrap()
+            .await?
             .item;
 
         if let Some(_car) = search_vehicle {
             let mut expression_attribute_values = vehicle
-                .iter()
-                .filter(|(_fee, date)| date.is_some())
-                .map(|(fee, date)| {
-                    (
-                        fee,
-                        AttributeValue::S(
-                            self.date_formatter(date.unwrap())
-                                .format("%Y-%m-%d")
-                                .to_string(),
-                        ),
-                    )
-                })
+                .fee_dates()?
+                .into_iter()
+                .map(|(fee, date)| (fee, AttributeValue::S(date)))
                 .collect::<HashMap<String, AttributeValue>>();
 
             expression_attribute_values.insert(
                 String::from(":updated_at"),
                 AttributeValue::S(Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)),
             );
+            expression_attribute_values.insert(String::from(":sold"), AttributeValue::Bool(true));
+
+            if let Some(vehicle_class) = &vehicle.vehicle_class {
+                expression_attribute_values.insert(
+                    String::from(":vehicle_class"),
+                    AttributeValue::S(vehicle_class.to_string()),
+                );
+            }
+
+            // `owner` is a DynamoDB reserved word.
+            let expression_attribute_names = vehicle.owner.as_ref().map(|owner| {
+                expression_attribute_values
+                    .insert(String::from(":owner"), AttributeValue::S(owner.to_string()));
+                HashMap::from([("#owner".to_string(), "owner".to_string())])
+            });
 
             let update = Update::builder()
                 .table_name(&self.table_name)
-                .key("PK", vehicle_key(&vehicle.vehicle_no))
-                .key("SK", vehicle_key(&vehicle.vehicle_no))
+                .key("PK", vehicle_key(org_id, &vehicle.vehicle_no))
+                .key("SK", vehicle_key(org_id, &vehicle.vehicle_no))
                 .update_expression(expression)
+                // A sold vehicle keeps its buyer as owner and its fee dates.
+                .condition_expression("attribute_exists(PK) AND NOT Sold = :sold")
+                .return_values_on_condition_check_failure(
+                    ReturnValuesOnConditionCheckFailure::AllOld,
+                )
+                .set_expression_attribute_names(expression_attribute_names)
                 .set_expression_attribute_values(Some(expression_attribute_values))
                 .build()
                 .unwrap();
 
             Ok(TransactWriteItem::builder().update(update).build())
         } else {
-            Err("The car is not in the record!!")
+            Err(DataAccessError::NotFound(
+                "The car is not in the record!!".to_string(),
+            ))
         }
     }
-    async fn add_history(&self, transaction_history: TransactionHistory) -> TransactWriteItem {
+    async fn add_history(
+        &self,
+        org_id: &str,
+        transaction_history: TransactionHistory,
+    ) -> TransactWriteItem {
         let put_transaction = Put::builder()
             .table_name(&self.table_name)
-            .set_item(Some(transaction_history.to_item()))
+            .set_item(Some(transaction_history.to_item(org_id)))
             .build()
             .unwrap();
         TransactWriteItem::builder().put(put_transaction).build()
@@ -329,129 +926,361 @@ impl DBDataAccess {
 
 #[async_trait]
 impl DataAccess for DBDataAccess {
-    async fn create_user(&self, user: User) -> Result<(), Error> {
-        tracing::warn!("User:====>{:#?}", user);
+    async fn create_user(&self, user: User) -> Result<(), DataAccessError> {
         tracing::info!("Table Name: {}", &self.table_name);
-        self.client
-            .put_item()
+        let organization = Organization::new(
+            user.organization
+                .clone()
+                .unwrap_or_else(|| user.username.clone()),
+        );
+        let user = User {
+            orgs: vec![organization.org_id.clone()],
+            roles: HashMap::from([(organization.org_id.clone(), Role::Admin)]),
+            ..user
+        };
+
+        let put_user = Put::builder()
             .table_name(&self.table_name)
             .set_item(Some(user.to_item()))
             .condition_expression("attribute_not_exists(PK) and attribute_not_exists(SK)")
+            .build()
+            .unwrap();
+        let put_org = Put::builder()
+            .table_name(&self.table_name)
+            .set_item(Some(organization.to_item()))
+            .build()
+            .unwrap();
+        let put_member = Put::builder()
+            .table_name(&self.table_name)
+            .set_item(Some(member_item(&organization.org_id, &user.username)))
+            .build()
+            .unwrap();
+
+        self.client
+            .transact_write_items()
+            .transact_items(TransactWriteItem::builder().put(put_user).build())
+            .transact_items(TransactWriteItem::builder().put(put_org).build())
+            .transact_items(TransactWriteItem::builder().put(put_member).build())
             .send()
             .await
-            .and_then(|_output| {
+            .map(|_output| {
                 // tracing::info!("Item Output {:#?}", output);
-                Ok(())
             })
-            .or_else(|err| {
+            .map_err(|err| {
                 tracing::error!("User create Fail Error: {:#?}", err);
-                Err(err.into())
+                match err
+                    .as_service_error()
+                    .map(|err| err.is_transaction_canceled_exception())
+                {
+                    Some(true) => DataAccessError::Conflict("Username already exist!!".to_string()),
+                    _ => err.into(),
+                }
             })
     }
 
-    async fn get_session(&self, user: User) -> Result<Session, Error> {
-        if self
-            .is_login_successful(&user.username, &user.password)
-            .await
-        {
-            self.create_session(user).await
+    async fn get_session(&self, user: User, device: Device) -> Result<Tokens, DataAccessError> {
+        if let Some(stored) = self.login_user(&user.username, &user.password).await? {
+            let stored = match stored.orgs.is_empty() {
+                true => self.create_personal_organization(stored).await?,
+                false => stored,
+            };
+            let org_id = session_org(user.org_id.as_deref(), &stored.orgs)?;
+            self.create_session(user, &org_id, device).await
         } else {
-            Err("{\"message\": \"Login fail!!\"}".into())
+            Err(DataAccessError::Unauthorized("Login fail!!".to_string()))
         }
     }
 
-    async fn delete_session(&self, session_id: &str) -> Result<String, Error> {
-        let user = self
-            .get_user(session_id)
-            .await
-            .ok_or(0)
-            .map_err(|_| "Session Expired!!")?;
+    async fn refresh_session(
+        &self,
+        refresh_token: &str,
+        device: Device,
+    ) -> Result<Tokens, DataAccessError> {
+        let invalid =
+            || DataAccessError::Unauthorized("The refresh token is not valid!!".to_string());
+        let reused = || {
+            DataAccessError::Unauthorized(
+                "The refresh token is already used, the login is revoked!!".to_string(),
+            )
+        };
 
-        let sessions = self
+        // GSI1 only projects the keys, like for sessions.
+        let key = self
             .client
             .query()
             .table_name(&self.table_name)
-            .key_condition_expression("#username = :username and begins_with(#session_id, :token)")
-            .expression_attribute_names("#username", "PK")
-            .expression_attribute_names("#session_id", "SK")
-            .expression_attribute_values(":username", user.clone())
-            .expression_attribute_values(":token", AttributeValue::S("SESSION#".to_string()))
+            .index_name("GSI1")
+            .key_condition_expression("#token = :token")
+            .expression_attribute_names("#token", "GSI1PK")
+            .expression_attribute_values(":token", refresh_key(refresh_token))
             .send()
-            .await
-            .unwrap()
+            .await?
             .items
-            .unwrap()
+            .unwrap_or_default()
             .into_iter()
-            .map(|item| item.get("SK").unwrap().to_owned())
-            .collect::<Vec<AttributeValue>>();
-
-        for session in sessions {
-            self.client
-                .delete_item()
-                .table_name(&self.table_name)
-                .key("PK", user.clone())
-                .key("SK", session)
-                .send()
-                .await
-                .and_then(|output| {
-                    tracing::info!("Item Output: {:#?}", output);
-                    Ok(())
-                })
-                .or_else(|err| {
-                    tracing::error!("{:#?}", err);
-                    Err::<(), Error>(err.into())
-                })?;
-        }
-        let user = user.as_s().unwrap()[5..].to_string();
-        Ok(user)
-    }
-
-    async fn change_pass(&self, token: &str, old_pass: &str, new_pass: &str) -> Result<(), Error> {
-        let user = self
-            .get_user(token)
-            .await
-            .ok_or("Session Expired!! login Again.")?;
+            .next()
+            .ok_or_else(invalid)?;
 
-        let user = self
+        let item = self
             .client
             .get_item()
             .table_name(&self.table_name)
-            .key("PK", user.clone())
-            .key("SK", user)
+            .key("PK", key.get("GSI1SK").unwrap().to_owned())
+            .key("SK", key.get("GSI1PK").unwrap().to_owned())
             .send()
-            .await
-            .unwrap()
+            .await?
             .item
-            .map(|output| from_item(&output))
-            .unwrap();
+            .ok_or_else(invalid)?;
+        check_expiry(&item)?;
 
-        if user.varify(old_pass) {
-            let pass = bcrypt::hash(new_pass).unwrap();
-            self.client
-                .update_item()
-                .table_name(&self.table_name)
-                .key("PK", user.get_key())
-                .key("SK", user.get_key())
-                .update_expression("SET password = :password")
-                .expression_attribute_values(":password", AttributeValue::S(pass))
-                // .return_values(aws_sdk_dynamodb::types::ReturnValue::UpdatedNew)
-                .send()
-                .await
-                .and_then(|_output| {
+        let username = owner_of(&item);
+        let stored = refresh_from_item(&item);
+        if stored.used {
+            tracing::warn!(
+                username,
+                family_id = stored.family_id,
+                "Refresh token reuse"
+            );
+            self.delete_tokens(&username, Logins::Only(&stored.family_id))
+                .await?;
+            return Err(reused());
+        }
+
+        let user = self.find_user(&username).await?.ok_or_else(invalid)?;
+        session_org(Some(&stored.org_id), &user.orgs)?;
+
+        // Marking the token used in the same transaction lets only one of two
+        // exchanges racing with it through.
+        let (session, refresh) = stored.rotate();
+        let mark_used = Update::builder()
+            .table_name(&self.table_name)
+            .key("PK", user_key(&username))
+            .key("SK", refresh_key(refresh_token))
+            .update_expression("SET #used = :used")
+            .condition_expression("#used = :unused")
+            .expression_attribute_names("#used", "used")
+            .expression_attribute_values(":used", AttributeValue::Bool(true))
+            .expression_attribute_values(":unused", AttributeValue::Bool(false))
+            .build()
+            .unwrap();
+        let put_session = Put::builder()
+            .table_name(&self.table_name)
+            .set_item(Some(session.to_item(&username)))
+            .build()
+            .unwrap();
+        let put_refresh = Put::builder()
+            .table_name(&self.table_name)
+            .set_item(Some(refresh.to_item(&username)))
+            .build()
+            .unwrap();
+        // Written field by field so a login from before device items existed
+        // gets one, without the name it was never given.
+        let mut expression = "SET last_seen_at = :now, expired_at = :expired_at, #ttl = :ttl, \
+             family_id = :family_id, org_id = :org_id, \
+             created_at = if_not_exists(created_at, :now)"
+            .to_string();
+        let mut update_device = Update::builder()
+            .table_name(&self.table_name)
+            .key("PK", user_key(&username))
+            .key("SK", device_key(&stored.family_id))
+            .expression_attribute_names("#ttl", "TTL")
+            .expression_attribute_values(":now", AttributeValue::S(session.created_at.clone()))
+            .expression_attribute_values(
+                ":expired_at",
+                AttributeValue::S(refresh.expired_at.clone()),
+            )
+            .expression_attribute_values(":ttl", ttl_value(&refresh.expired_at))
+            .expression_attribute_values(":family_id", AttributeValue::S(stored.family_id.clone()))
+            .expression_attribute_values(":org_id", AttributeValue::S(stored.org_id.clone()));
+        for (name, value) in [
+            ("user_agent", device.user_agent),
+            ("source_ip", device.source_ip),
+        ] {
+            if let Some(value) = value {
+                expression.push_str(&format!(", {name} = :{name}"));
+                update_device = update_device
+                    .expression_attribute_values(format!(":{name}"), AttributeValue::S(value));
+            }
+        }
+        let update_device = update_device.update_expression(expression).build().unwrap();
+
+        let output = self
+            .client
+            .transact_write_items()
+            .transact_items(TransactWriteItem::builder().update(mark_used).build())
+            .transact_items(TransactWriteItem::builder().put(put_session).build())
+            .transact_items(TransactWriteItem::builder().put(put_refresh).build())
+            .transact_items(TransactWriteItem::builder().update(update_device).build())
+            .send()
+            .await;
+
+        match output {
+            Ok(_) => Ok(Tokens::new(&session, &refresh)),
+            Err(err)
+                if err
+                    .as_service_error()
+                    .is_some_and(|err| err.is_transaction_canceled_exception()) =>
+            {
+                tracing::warn!(
+                    username,
+                    family_id = stored.family_id,
+                    "Refresh token reuse"
+                );
+                self.delete_tokens(&username, Logins::Only(&stored.family_id))
+                    .await?;
+                Err(reused())
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn delete_session(&self, session_id: &str) -> Result<String, DataAccessError> {
+        let caller = self
+            .get_caller(session_id)
+            .await?
+            .ok_or(DataAccessError::Unauthorized(
+                "Session Expired!!".to_string(),
+            ))?;
+
+        match &caller.family_id {
+            Some(family_id) => {
+                self.delete_tokens(&caller.username, Logins::Only(family_id))
+                    .await?
+            }
+            // A session from before logins were tracked is its own login.
+            None => {
+                self.client
+                    .delete_item()
+                    .table_name(&self.table_name)
+                    .key("PK", user_key(&caller.username))
+                    .key("SK", session_key(session_id))
+                    .send()
+                    .await?;
+            }
+        }
+        Ok(caller.username)
+    }
+
+    async fn list_sessions(&self, token: &str) -> Result<Vec<DeviceSession>, DataAccessError> {
+        let caller = self.get_caller(token).await?.ok_or_else(|| {
+            DataAccessError::Unauthorized("Session Expired!! login Again.".to_string())
+        })?;
+
+        let mut sessions = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .key_condition_expression("#pk = :username and begins_with(#sk, :device)")
+            .expression_attribute_names("#pk", "PK")
+            .expression_attribute_names("#sk", "SK")
+            .expression_attribute_values(":username", user_key(&caller.username))
+            .expression_attribute_values(":device", AttributeValue::S("DEVICE#".to_string()))
+            .send()
+            .await?
+            .items
+            .unwrap_or_default()
+            .iter()
+            .filter(|item| check_expiry(item).is_ok())
+            .map(|item| device_from_item(item, caller.family_id.as_deref()))
+            .collect::<Vec<DeviceSession>>();
+        sessions.sort_by(|a, b| b.last_seen_at.cmp(&a.last_seen_at));
+        Ok(sessions)
+    }
+
+    async fn revoke_session(&self, token: &str, session_id: &str) -> Result<(), DataAccessError> {
+        let caller = self.get_caller(token).await?.ok_or_else(|| {
+            DataAccessError::Unauthorized("Session Expired!! login Again.".to_string())
+        })?;
+
+        self.client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("PK", user_key(&caller.username))
+            .key("SK", device_key(session_id))
+            .send()
+            .await?
+            .item
+            .ok_or_else(|| DataAccessError::NotFound("The session is not found!!".to_string()))?;
+
+        self.delete_tokens(&caller.username, Logins::Only(session_id))
+            .await
+    }
+
+    async fn revoke_other_sessions(&self, token: &str) -> Result<(), DataAccessError> {
+        let caller = self.get_caller(token).await?.ok_or_else(|| {
+            DataAccessError::Unauthorized("Session Expired!! login Again.".to_string())
+        })?;
+        let family_id = caller.family_id.ok_or_else(|| {
+            DataAccessError::SessionExpired("Session Expired!! login Again.".to_string())
+        })?;
+
+        self.delete_tokens(&caller.username, Logins::AllBut(&family_id))
+            .await
+    }
+
+    async fn change_pass(
+        &self,
+        token: &str,
+        old_pass: &str,
+        new_pass: &str,
+        revoke_others: bool,
+    ) -> Result<(), DataAccessError> {
+        let caller = self.get_caller(token).await?.ok_or_else(|| {
+            DataAccessError::Unauthorized("Session Expired!! login Again.".to_string())
+        })?;
+        let user = user_key(&caller.username);
+
+        let user = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("PK", user.clone())
+            .key("SK", user)
+            .send()
+            .await?
+            .item
+            .map(|output| from_item(&output))
+            .ok_or_else(|| DataAccessError::NotFound("User is not found!!".to_string()))?;
+
+        if user.varify(old_pass) {
+            let pass = bcrypt::hash(new_pass).unwrap();
+            self.client
+                .update_item()
+                .table_name(&self.table_name)
+                .key("PK", user.get_key())
+                .key("SK", user.get_key())
+                .update_expression("SET password = :password")
+                .expression_attribute_values(":password", AttributeValue::S(pass))
+                // .return_values(aws_sdk_dynamodb::types::ReturnValue::UpdatedNew)
+                .send()
+                .await
+                .map(|_output| {
                     // tracing::info!("updated user: {:#?}", output.attributes);
-                    Ok(())
-                })
-                .or_else(|err| Err(err.into()))
+                })?;
+
+            match (revoke_others, caller.family_id) {
+                (true, Some(family_id)) => {
+                    self.delete_tokens(&user.username, Logins::AllBut(&family_id))
+                        .await
+                }
+                // A session from before logins were tracked can't be told
+                // apart from the others, so every session goes.
+                (true, None) => self.delete_tokens(&user.username, Logins::All).await,
+                (false, _) => Ok(()),
+            }
         } else {
-            Err("Password is not valid!!!".into())
+            Err(DataAccessError::Validation(
+                "Password is not valid!!!".to_string(),
+            ))
         }
     }
 
-    async fn add_vehicle(&self, token: &str, car: Vehicle) -> Result<(), Error> {
-        if self.is_session_vaild(token).await {
+    async fn add_vehicle(&self, token: &str, car: Vehicle) -> Result<(), DataAccessError> {
+        if let Some(caller) = self.get_caller(token).await? {
+            caller.require(Role::Operator)?;
             let put_search = Put::builder()
                 .table_name(&self.table_name)
-                .set_item(Some(car.to_search_item()))
+                .set_item(Some(car.to_search_item(&caller.org_id)))
                 .condition_expression("attribute_not_exists(PK) and attribute_not_exists(SK)")
                 .build()
                 .unwrap();
@@ -460,7 +1289,7 @@ impl DataAccess for DBDataAccess {
 
             let put_vehicle = Put::builder()
                 .table_name(&self.table_name)
-                .set_item(Some(car.to_item()))
+                .set_item(Some(car.to_item(&caller.org_id)))
                 .condition_expression("attribute_not_exists(PK) and attribute_not_exists(SK)")
                 .build()
                 .unwrap();
@@ -473,41 +1302,158 @@ impl DataAccess for DBDataAccess {
                 .transact_items(add_search)
                 .send()
                 .await
-                .and_then(|output| {
+                .map(|output| {
                     tracing::info!("New Vehicle Details:  {:#?}", output);
-                    Ok(())
                 })
-                .or_else(|err| {
+                .map_err(|err| {
                     tracing::error!(%err, "Error Message");
-                    Err("Vehicle already exists".into())
+                    match err
+                        .as_service_error()
+                        .map(|err| err.is_transaction_canceled_exception())
+                    {
+                        Some(true) => {
+                            DataAccessError::Conflict("Vehicle already exists".to_string())
+                        }
+                        _ => err.into(),
+                    }
                 })
         } else {
-            Err("You don't have access!!".into())
+            Err(DataAccessError::Unauthorized(
+                "You don't have access!!".to_string(),
+            ))
         }
     }
 
-    async fn get_all_vehicle(&self, token: &str) -> Result<Vec<Vehicle>, Error> {
-        if self.is_session_vaild(token).await {
-            let vehicle_items = self
+    async fn import_vehicles(
+        &self,
+        token: &str,
+        rows: Vec<ImportRow>,
+        mode: ImportMode,
+    ) -> Result<ImportReport, DataAccessError> {
+        let caller = self.get_caller(token).await?.ok_or_else(|| {
+            DataAccessError::Unauthorized("You don't have valid access!!".to_string())
+        })?;
+        caller.require(Role::Operator)?;
+
+        let keys = rows
+            .iter()
+            .map(|row| {
+                let key = row.vehicle.get_key(&caller.org_id);
+                HashMap::from([("PK".to_string(), key.clone()), ("SK".to_string(), key)])
+            })
+            .collect();
+        let stored = self
+            .batch_get(keys)
+            .await?
+            .into_iter()
+            .map(|item| (item.get("PK").unwrap().as_s().unwrap().to_string(), item))
+            .collect::<HashMap<String, HashMap<String, AttributeValue>>>();
+
+        let mut report = ImportReport::default();
+        let mut writes = Vec::new();
+        for ImportRow { row, vehicle } in rows {
+            let vehicle_no = vehicle.vehicle_no.to_string();
+            let update = match stored.get(vehicle.get_key(&caller.org_id).as_s().unwrap()) {
+                None => false,
+                Some(item) if is_sold(item) => {
+                    report.error(row, Some(vehicle_no), "The car is sold!!");
+                    continue;
+                }
+                Some(_) if mode == ImportMode::Skip => {
+                    report.skipped += 1;
+                    continue;
+                }
+                Some(_) => true,
+            };
+            let items = self.import_items(&caller.org_id, vehicle, update)?;
+            writes.push((row, vehicle_no, update, items));
+        }
+
+        // 50 rows fill the 100 items of a transaction. A row that changed
+        // since it was read cancels its whole chunk, whose rows are then
+        // written one by one so only that row fails, with the reason
+        // DynamoDB gave for it.
+        for chunk in writes.chunks(50) {
+            let output = self
+                .client
+                .transact_write_items()
+                .set_transact_items(Some(
+                    chunk.iter().flat_map(|(.., items)| items.clone()).collect(),
+                ))
+                .send()
+                .await;
+
+            match output {
+                Ok(_) => chunk
+                    .iter()
+                    .for_each(|(_, _, update, _)| report.written(*update)),
+                Err(err)
+                    if err
+                        .as_service_error()
+                        .is_some_and(|err| err.is_transaction_canceled_exception()) =>
+                {
+                    for (row, vehicle_no, update, items) in chunk {
+                        match self
+                            .client
+                            .transact_write_items()
+                            .set_transact_items(Some(items.clone()))
+                            .send()
+                            .await
+                        {
+                            Ok(_) => report.written(*update),
+                            Err(err) => match import_failure(&err, *update) {
+                                Some(message) => {
+                                    report.error(*row, Some(vehicle_no.clone()), &message)
+                                }
+                                None => return Err(err.into()),
+                            },
+                        }
+                    }
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        tracing::info!(
+            added = report.added,
+            updated = report.updated,
+            skipped = report.skipped,
+            failed = report.errors.len(),
+            "Vehicles are imported"
+        );
+        Ok(report)
+    }
+
+    async fn get_all_vehicle(
+        &self,
+        token: &str,
+        page: PageRequest,
+    ) -> Result<Page<Vehicle>, DataAccessError> {
+        if let Some(caller) = self.get_caller(token).await? {
+            let output = self
                 .client
                 .query()
                 .table_name(&self.table_name)
                 .index_name("GSI2")
                 .key_condition_expression("#vehicle = :vehicle_key")
                 .expression_attribute_names("#vehicle", "GSI2PK")
-                .expression_attribute_values(
-                    ":vehicle_key",
-                    AttributeValue::S("VEHICLE".to_string()),
-                )
+                .expression_attribute_values(":vehicle_key", vehicle_partition_key(&caller.org_id))
+                .set_limit(page.limit)
+                .set_exclusive_start_key(page.exclusive_start_key(
+                    &["PK", "SK", "GSI2PK"],
+                    ("GSI2PK", &vehicle_partition_key(&caller.org_id)),
+                )?)
                 .send()
-                .await
-                .unwrap()
-                .items
-                .unwrap();
+                .await?;
 
-            Ok(vehicle_repo(vehicle_items))
+            Ok(Page::new(
+                vehicle_repo(output.items.unwrap_or_default()),
+                output.last_evaluated_key,
+            ))
         } else {
-            Err("You don't have access!!".into())
+            Err(DataAccessError::Unauthorized(
+                "You don't have access!!".to_string(),
+            ))
         }
     }
 
@@ -516,67 +1462,147 @@ impl DataAccess for DBDataAccess {
         token: &str,
         fee_type: &str,
         days: u32,
-    ) -> Result<Vec<Vehicle>, Error> {
-        if self.is_session_vaild(token).await {
+        page: PageRequest,
+    ) -> Result<Page<Vehicle>, DataAccessError> {
+        if let Some(caller) = self.get_caller(token).await? {
+            let org_id = &caller.org_id;
             match fee_type {
-                "fitness" => self.get_fees_info("fitness", days).await,
-                "insurance" => self.get_fees_info("insurance", days).await,
-                "route" => self.get_fees_info("route", days).await,
-                _ => self.get_fees_info("tax", days).await,
+                "fitness" => self.get_fees_info(org_id, "fitness", days, page).await,
+                "insurance" => self.get_fees_info(org_id, "insurance", days, page).await,
+                "route" => self.get_fees_info(org_id, "route", days, page).await,
+                _ => self.get_fees_info(org_id, "tax", days, page).await,
             }
         } else {
-            Err("You don't have access!!".into())
+            Err(DataAccessError::Unauthorized(
+                "You don't have access!!".to_string(),
+            ))
         }
     }
 
+    async fn get_vehicle(
+        &self,
+        token: &str,
+        vehicle_no: &VehicleNumber,
+    ) -> Result<VehicleDetails, DataAccessError> {
+        let caller = self
+            .get_caller(token)
+            .await?
+            .ok_or_else(|| DataAccessError::Unauthorized("You don't have access!!".to_string()))?;
+
+        self.client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("PK", vehicle_key(&caller.org_id, vehicle_no))
+            .key("SK", vehicle_key(&caller.org_id, vehicle_no))
+            .send()
+            .await?
+            .item
+            .map(|item| vehicle_details_from_item(&item))
+            .ok_or_else(|| DataAccessError::NotFound("The car is not in the record!!".to_string()))
+    }
+
+    async fn search_vehicles(
+        &self,
+        token: &str,
+        query: &str,
+    ) -> Result<Vec<Vehicle>, DataAccessError> {
+        let caller = self
+            .get_caller(token)
+            .await?
+            .ok_or_else(|| DataAccessError::Unauthorized("You don't have access!!".to_string()))?;
+        let (query, last_digits) = search_query(query)?;
+
+        let search = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .key_condition_expression("PK = :pk AND begins_with(#search, :query)")
+            .expression_attribute_values(":pk", search_partition_key(&caller.org_id))
+            .expression_attribute_values(":query", vehicle_search_key(&query));
+
+        // LSI1 only projects the keys, SK still carries the full plate.
+        let search = match last_digits {
+            true => search
+                .index_name("LSI1")
+                .expression_attribute_names("#search", "LSI1SK"),
+            false => search.expression_attribute_names("#search", "SK"),
+        };
+
+        let keys = search
+            .send()
+            .await?
+            .items
+            .unwrap_or_default()
+            .iter()
+            .map(|item| {
+                let vehicle_no = vehicle_no_from_key(item.get("SK").unwrap());
+                let key = vehicle_key(&caller.org_id, &vehicle_no);
+                HashMap::from([("PK".to_string(), key.clone()), ("SK".to_string(), key)])
+            })
+            .collect::<Vec<HashMap<String, AttributeValue>>>();
+
+        let mut vehicles = vehicle_repo(self.batch_get(keys).await?);
+        vehicles.sort_by(|a, b| a.vehicle_no.cmp(&b.vehicle_no));
+        Ok(vehicles)
+    }
+
     async fn pay_fee(
         &self,
         token: &str,
         fee_type: &str,
-        update_vehicle: UpdateVehicle,
-    ) -> Result<(), Error> {
-        let user = self
-            .get_user(token)
-            .await
-            .ok_or("You don't have valid access!!")?;
+        mut update_vehicle: UpdateVehicle,
+    ) -> Result<(), DataAccessError> {
+        let caller = self.get_caller(token).await?.ok_or_else(|| {
+            DataAccessError::Unauthorized("You don't have valid access!!".to_string())
+        })?;
+        caller.require(Role::Operator)?;
 
         let old_vhicle = self
             .client
             .get_item()
             .table_name(&self.table_name)
-            .key("PK", vehicle_key(&update_vehicle.vehicle_no))
-            .key("SK", vehicle_key(&update_vehicle.vehicle_no))
+            .key(
+                "PK",
+                vehicle_key(&caller.org_id, &update_vehicle.vehicle_no),
+            )
+            .key(
+                "SK",
+                vehicle_key(&caller.org_id, &update_vehicle.vehicle_no),
+            )
             .send()
-            .await
-            .unwrap()
+            .await?
             .item
-            .unwrap();
+            .ok_or_else(|| {
+                DataAccessError::NotFound("The car is not in the record!!".to_string())
+            })?;
 
-        let update_vehicle_write_item = self.update_vehicle(&update_vehicle).await?;
-
-        // let date = update_vehicle
-        //     .iter()
-        //     .find(|value| value.0 == format!("{}_date", fee_type))
-        //     .unwrap()
-        //     .1
-        //     .unwrap()
-        //     .to_string();
+        if is_sold(&old_vhicle) {
+            return Err(DataAccessError::Conflict("The car is sold!!".to_string()));
+        }
 
         let exp_date = old_vhicle
             .get(&*[fee_type, "_date"].join(""))
-            .unwrap()
-            .as_s()
-            .unwrap()
+            .and_then(|date| date.as_s().ok())
+            .ok_or_else(|| DataAccessError::Validation("The fee type is not valid!!".to_string()))?
             .to_string();
 
+        update_vehicle.renew(&self.renewal_rules, fee_type, &old_vhicle, &exp_date);
+        let update_vehicle_write_item =
+            self.update_vehicle(&caller.org_id, &update_vehicle).await?;
+
         let transaction_history = TransactionHistory::new(
-            update_vehicle.vehicle_no,
+            vehicle_no_from_item(&old_vhicle, "SK"),
             exp_date,
             fee_type.to_string(),
-            user.as_s().unwrap()[5..].to_string(),
+            caller.username.clone(),
+        )
+        .with_payment(
+            old_vhicle.get("owner").unwrap().as_s().unwrap().to_string(),
+            update_vehicle.payment,
         );
 
-        let transaction_history_write_item = self.add_history(transaction_history).await;
+        let transaction_history_write_item =
+            self.add_history(&caller.org_id, transaction_history).await;
 
         self.client
             .transact_write_items()
@@ -584,50 +1610,51 @@ impl DataAccess for DBDataAccess {
             .transact_items(update_vehicle_write_item)
             .send()
             .await
-            .and_then(|output| {
+            .map(|output| {
                 tracing::info!(
                     "Vehicle {} updated and transaction is added:  {:#?}",
                     fee_type,
                     output
                 );
-                Ok(())
-            })
-            .or_else(|err| {
-                tracing::error!(%err, "Error Message");
-                Err(err.into())
             })
+            .map_err(update_failure)
     }
 
     async fn update_vehicle(
         &self,
         token: &str,
         update_vehicle: UpdateVehicle,
-    ) -> Result<(), Error> {
-        if self.is_session_vaild(token).await {
+    ) -> Result<(), DataAccessError> {
+        if let Some(caller) = self.get_caller(token).await? {
+            caller.require(Role::Operator)?;
             self.client
                 .transact_write_items()
-                .transact_items(self.update_vehicle(&update_vehicle).await?)
+                .transact_items(self.update_vehicle(&caller.org_id, &update_vehicle).await?)
                 .send()
                 .await
-                .and_then(|_output| Ok(()))
-                .or_else(|err| {
-                    tracing::error!(%err, "Error Message");
-                    Err(err.into())
-                })
+                .map(|_output| ())
+                .map_err(update_failure)
         } else {
-            Err("You don't have valid access!!".into())
+            Err(DataAccessError::Unauthorized(
+                "You don't have valid access!!".to_string(),
+            ))
         }
     }
-    async fn view_history(&self, token: &str, days: u32) -> Result<Vec<TransactionHistory>, Error> {
-        if self.is_session_vaild(token).await {
-            let historys = self
+    async fn view_history(
+        &self,
+        token: &str,
+        days: u32,
+        page: PageRequest,
+    ) -> Result<Page<TransactionHistory>, DataAccessError> {
+        if let Some(caller) = self.get_caller(token).await? {
+            let output = self
                 .client
                 .query()
                 .table_name(&self.table_name)
                 .index_name("GSI3")
                 .key_condition_expression("GSI3PK = :pk AND GSI3SK between :sdate and :edate")
                 .set_expression_attribute_values(Some(HashMap::from([
-                    (":pk".to_string(), AttributeValue::S("HISTORY".to_string())),
+                    (":pk".to_string(), history_partition_key(&caller.org_id)),
                     (
                         ":edate".to_string(),
                         history_key(&Local::now().format("%Y-%m-%d").to_string()),
@@ -641,28 +1668,231 @@ impl DataAccess for DBDataAccess {
                         ),
                     ),
                 ])))
+                .scan_index_forward(false)
+                .set_limit(page.limit)
+                .set_exclusive_start_key(page.exclusive_start_key(
+                    &["PK", "SK", "GSI3PK", "GSI3SK"],
+                    ("GSI3PK", &history_partition_key(&caller.org_id)),
+                )?)
                 .send()
-                .await
-                .unwrap()
-                .items
-                .unwrap()
-                .into_iter()
-                .rev()
-                .collect();
+                .await?;
+
+            Ok(Page::new(
+                history_repo(output.items.unwrap_or_default()),
+                output.last_evaluated_key,
+            ))
+        } else {
+            Err(DataAccessError::Unauthorized(
+                "Your Session is invalid!!".to_string(),
+            ))
+        }
+    }
+    async fn sell_vehicle(&self, token: &str, sale: SellVehicle) -> Result<(), DataAccessError> {
+        let caller = self.get_caller(token).await?.ok_or_else(|| {
+            DataAccessError::Unauthorized("You don't have valid access!!".to_string())
+        })?;
+        caller.require(Role::Operator)?;
+
+        let key = vehicle_key(&caller.org_id, &sale.vehicle_no);
+        let vehicle = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("PK", key.clone())
+            .key("SK", key.clone())
+            .send()
+            .await?
+            .item
+            .ok_or_else(|| {
+                DataAccessError::NotFound("The car is not in the record!!".to_string())
+            })?;
+
+        if is_sold(&vehicle) {
+            return Err(DataAccessError::Conflict(
+                "The car is already sold!!".to_string(),
+            ));
+        }
+
+        let sale_date = match &sale.sale_date {
+            Some(date) => validation::iso_date("sale_date", date)?,
+            None => Local::now().date_naive().format("%Y-%m-%d").to_string(),
+        };
+
+        let transaction_history = TransactionHistory::ownership(
+            vehicle_no_from_item(&vehicle, "SK"),
+            sale_date.clone(),
+            vehicle.get("owner").unwrap().as_s().unwrap().to_string(),
+            sale.buyer.clone(),
+            caller.username.clone(),
+        );
+
+        let update = Update::builder()
+            .table_name(&self.table_name)
+            .key("PK", key.clone())
+            .key("SK", key)
+            .update_expression(
+                "SET Sold = :sold, buyer = :buyer, sold_at = :sold_at, #owner = :buyer, \
+                 updated_at = :updated_at, GSI2PK = :sold_vehicles",
+            )
+            .condition_expression("NOT Sold = :sold")
+            .expression_attribute_names("#owner", "owner")
+            .expression_attribute_values(":sold", AttributeValue::Bool(true))
+            .expression_attribute_values(":buyer", AttributeValue::S(sale.buyer))
+            .expression_attribute_values(":sold_at", AttributeValue::S(sale_date))
+            .expression_attribute_values(
+                ":updated_at",
+                AttributeValue::S(Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)),
+            )
+            .expression_attribute_values(
+                ":sold_vehicles",
+                sold_vehicle_partition_key(&caller.org_id),
+            )
+            .build()
+            .unwrap();
+
+        self.client
+            .transact_write_items()
+            .transact_items(TransactWriteItem::builder().update(update).build())
+            .transact_items(self.add_history(&caller.org_id, transaction_history).await)
+            .send()
+            .await
+            .map(|_output| ())
+            .map_err(|err| {
+                tracing::error!(%err, "Error Message");
+                match err
+                    .as_service_error()
+                    .map(|err| err.is_transaction_canceled_exception())
+                {
+                    Some(true) => {
+                        DataAccessError::Conflict("The car is already sold!!".to_string())
+                    }
+                    _ => err.into(),
+                }
+            })
+    }
 
-            Ok(history_repo(historys))
+    async fn get_vehicle_history(
+        &self,
+        token: &str,
+        vehicle_no: &VehicleNumber,
+        filter: HistoryFilter,
+        page: PageRequest,
+    ) -> Result<Page<TransactionHistory>, DataAccessError> {
+        if let Some(caller) = self.get_caller(token).await? {
+            let (from, to) = filter.date_range();
+            let query = self
+                .client
+                .query()
+                .table_name(&self.table_name)
+                .expression_attribute_values(":pk", vehicle_key(&caller.org_id, vehicle_no))
+                .scan_index_forward(false)
+                .set_limit(page.limit)
+                .set_exclusive_start_key(page.exclusive_start_key(
+                    &["PK", "SK"],
+                    ("PK", &vehicle_key(&caller.org_id, vehicle_no)),
+                )?);
+
+            // SK is TRANSACTION#<type>#<date>, so a known type turns the date
+            // range into a key condition. Otherwise filter on GSI3SK.
+            let query = match &filter.transaction_type {
+                Some(transaction_type) => query
+                    .key_condition_expression("PK = :pk AND SK BETWEEN :from AND :to")
+                    .expression_attribute_values(
+                        ":from",
+                        AttributeValue::S(format!("TRANSACTION#{}#{}", transaction_type, from)),
+                    )
+                    .expression_attribute_values(
+                        ":to",
+                        AttributeValue::S(format!("TRANSACTION#{}#{}", transaction_type, to)),
+                    ),
+                None => query
+                    .key_condition_expression("PK = :pk AND begins_with(SK, :transaction)")
+                    .filter_expression("GSI3SK BETWEEN :from AND :to")
+                    .expression_attribute_values(
+                        ":transaction",
+                        AttributeValue::S("TRANSACTION#".to_string()),
+                    )
+                    .expression_attribute_values(":from", history_key(&from))
+                    .expression_attribute_values(":to", history_key(&to)),
+            };
+
+            let output = query.send().await?;
+
+            Ok(Page::new(
+                history_repo(output.items.unwrap_or_default()),
+                output.last_evaluated_key,
+            ))
         } else {
-            Err("Your Session is invalid!!".into())
+            Err(DataAccessError::Unauthorized(
+                "Your Session is invalid!!".to_string(),
+            ))
         }
     }
-    async fn undo_history(&self, token: &str, delete_history: DeleteHistory) -> Result<(), Error> {
-        if self.is_session_vaild(token).await {
+
+    async fn spending_report(
+        &self,
+        token: &str,
+        filter: HistoryFilter,
+    ) -> Result<SpendingReport, DataAccessError> {
+        let caller = self.get_caller(token).await?.ok_or_else(|| {
+            DataAccessError::Unauthorized("Your Session is invalid!!".to_string())
+        })?;
+
+        let (from, to) = filter.date_range();
+        let prefix = match &filter.transaction_type {
+            Some(transaction_type) => format!("TRANSACTION#{}#", transaction_type),
+            None => "TRANSACTION#".to_string(),
+        };
+
+        let mut historys = Vec::new();
+        let mut exclusive_start_key = None;
+        loop {
+            let output = self
+                .client
+                .query()
+                .table_name(&self.table_name)
+                .index_name("GSI3")
+                .key_condition_expression("GSI3PK = :pk AND GSI3SK BETWEEN :from AND :to")
+                .filter_expression("begins_with(SK, :prefix)")
+                .expression_attribute_values(":pk", history_partition_key(&caller.org_id))
+                .expression_attribute_values(":from", history_key(&from))
+                .expression_attribute_values(":to", history_key(&to))
+                .expression_attribute_values(":prefix", AttributeValue::S(prefix.clone()))
+                .set_exclusive_start_key(exclusive_start_key)
+                .send()
+                .await?;
+
+            historys.extend(history_repo(output.items.unwrap_or_default()));
+            exclusive_start_key = output.last_evaluated_key;
+            if exclusive_start_key.is_none() {
+                break;
+            }
+        }
+
+        Ok(SpendingReport::new(filter.from, filter.to, &historys))
+    }
+
+    async fn undo_history(
+        &self,
+        token: &str,
+        delete_history: DeleteHistory,
+    ) -> Result<(), DataAccessError> {
+        if let Some(caller) = self.get_caller(token).await? {
+            caller.require(Role::Admin)?;
+            if delete_history.transaction_type == OWNERSHIP_TRANSACTION {
+                return Err(DataAccessError::Validation(
+                    "An ownership change can't be undone!!".to_string(),
+                ));
+            }
             let current_history = self
                 .client
                 .get_item()
                 .table_name(&self.table_name)
                 .set_key(Some(HashMap::from([
-                    ("PK".to_string(), vehicle_key(&delete_history.vehicle_no)),
+                    (
+                        "PK".to_string(),
+                        vehicle_key(&caller.org_id, &delete_history.vehicle_no),
+                    ),
                     (
                         "SK".to_string(),
                         AttributeValue::S(format!(
@@ -672,46 +1902,660 @@ impl DataAccess for DBDataAccess {
                     ),
                 ])))
                 .send()
-                .await
-                .unwrap()
+                .await?
                 .item
                 .map(|history| history_from_item(&history))
-                .ok_or_else(|| "No record is available")?;
+                .ok_or_else(|| DataAccessError::NotFound("No record is available".to_string()))?;
 
-            let vehicle = format!(
-                "{{ \"vehicle_no\": \"{}\", \"{}_date\": \"{}\" }}",
+            let update_vehicle = UpdateVehicle::fee_date(
                 current_history.vehicle_no,
-                delete_history.transaction_type,
-                delete_history.created_at
-            );
-
-            let update_vehicle = serde_json::from_str::<UpdateVehicle>(&vehicle).unwrap();
+                &delete_history.transaction_type,
+                delete_history.created_at.clone(),
+            )
+            .ok_or_else(|| {
+                DataAccessError::Validation("The transaction has no fee date!!".to_string())
+            })?;
 
             self.client
                 .transact_write_items()
-                .transact_items(self.update_vehicle(&update_vehicle).await?)
-                .transact_items(self.delete_history(delete_history).await?)
+                .transact_items(self.update_vehicle(&caller.org_id, &update_vehicle).await?)
+                .transact_items(self.delete_history(&caller.org_id, delete_history).await?)
                 .send()
                 .await
-                .and_then(|_output| Ok(()))
-                .or_else(|err| {
-                    tracing::error!(%err, "Error Message");
-                    Err(err.into())
-                })
+                .map(|_output| ())
+                .map_err(update_failure)
         } else {
-            return Err("Your Session is invalid!!".into());
+            return Err(DataAccessError::Unauthorized(
+                "Your Session is invalid!!".to_string(),
+            ));
         }
     }
+
+    async fn create_organization(
+        &self,
+        token: &str,
+        name: &str,
+    ) -> Result<Organization, DataAccessError> {
+        let caller = self.get_caller(token).await?.ok_or_else(|| {
+            DataAccessError::Unauthorized("You don't have valid access!!".to_string())
+        })?;
+
+        let user = self
+            .find_user(&caller.username)
+            .await?
+            .ok_or_else(|| DataAccessError::NotFound("User is not found!!".to_string()))?;
+
+        let organization = Organization::new(name.to_string());
+
+        let put_org = Put::builder()
+            .table_name(&self.table_name)
+            .set_item(Some(organization.to_item()))
+            .build()
+            .unwrap();
+
+        let mut transact_items = vec![TransactWriteItem::builder().put(put_org).build()];
+        transact_items.extend(self.join_organization(&organization.org_id, &user, Role::Admin));
+
+        self.client
+            .transact_write_items()
+            .set_transact_items(Some(transact_items))
+            .send()
+            .await
+            .map(|output| {
+                tracing::info!("New Organization: {:#?}", output);
+            })
+            .map_err(|err| {
+                tracing::error!(%err, "Error Message");
+                DataAccessError::from(err)
+            })?;
+
+        Ok(organization)
+    }
+
+    async fn add_member(
+        &self,
+        token: &str,
+        username: &str,
+        role: Role,
+    ) -> Result<(), DataAccessError> {
+        let caller = self.get_caller(token).await?.ok_or_else(|| {
+            DataAccessError::Unauthorized("You don't have valid access!!".to_string())
+        })?;
+        caller.require(Role::Admin)?;
+
+        let member = self
+            .find_user(username)
+            .await?
+            .ok_or_else(|| DataAccessError::NotFound("User is not found!!".to_string()))?;
+
+        if member.orgs.contains(&caller.org_id) {
+            return Err(DataAccessError::Conflict(
+                "User is already a member!!".to_string(),
+            ));
+        }
+
+        self.client
+            .transact_write_items()
+            .set_transact_items(Some(self.join_organization(&caller.org_id, &member, role)))
+            .send()
+            .await
+            .map(|_output| ())
+            .map_err(|err| {
+                tracing::error!(%err, "Error Message");
+                err.into()
+            })
+    }
+
+    async fn set_role(
+        &self,
+        token: &str,
+        username: &str,
+        role: Role,
+    ) -> Result<(), DataAccessError> {
+        let caller = self.get_caller(token).await?.ok_or_else(|| {
+            DataAccessError::Unauthorized("You don't have valid access!!".to_string())
+        })?;
+        caller.require(Role::Admin)?;
+
+        if caller.username == username {
+            return Err(DataAccessError::Validation(
+                "You can't change your own role!!".to_string(),
+            ));
+        }
+
+        let member = self
+            .find_user(username)
+            .await?
+            .filter(|member| member.orgs.contains(&caller.org_id))
+            .ok_or_else(|| DataAccessError::NotFound("User is not a member!!".to_string()))?;
+
+        self.client
+            .update_item()
+            .table_name(&self.table_name)
+            .key("PK", member.get_key())
+            .key("SK", member.get_key())
+            .update_expression("SET #roles = :roles")
+            .expression_attribute_names("#roles", "roles")
+            .expression_attribute_values(
+                ":roles",
+                roles_value(&member.roles_with(&caller.org_id, role)),
+            )
+            .send()
+            .await
+            .map(|_output| ())
+            .map_err(|err| {
+                tracing::error!(%err, "Error Message");
+                err.into()
+            })
+    }
+
+    async fn get_organizations(&self, token: &str) -> Result<Vec<Organization>, DataAccessError> {
+        let caller = self.get_caller(token).await?.ok_or_else(|| {
+            DataAccessError::Unauthorized("You don't have valid access!!".to_string())
+        })?;
+
+        let user = self
+            .find_user(&caller.username)
+            .await?
+            .ok_or_else(|| DataAccessError::NotFound("User is not found!!".to_string()))?;
+
+        let mut organizations = Vec::new();
+        for org_id in user.orgs {
+            let organization = self
+                .client
+                .get_item()
+                .table_name(&self.table_name)
+                .key("PK", org_key(&org_id))
+                .key("SK", org_key(&org_id))
+                .send()
+                .await?
+                .item;
+
+            if let Some(organization) = organization {
+                organizations.push(org_from_item(&organization));
+            }
+        }
+        Ok(organizations)
+    }
+
+    async fn due_reminders(&self, days: u32) -> Result<Vec<Reminder>, DataAccessError> {
+        let mut reminders = Vec::new();
+
+        for org_id in self.org_ids().await? {
+            let recipients = self.reminder_recipients(&org_id).await?;
+            let claimed = self.claimed_reminders(&org_id).await?;
+
+            for fee_type in FEE_TYPES {
+                for (window, overdue) in [(days, false), (0, true)] {
+                    if window == 0 && !overdue {
+                        continue;
+                    }
+
+                    let mut cursor = None;
+                    loop {
+                        let vehicles = self
+                            .get_fees_info(
+                                &org_id,
+                                fee_type,
+                                window,
+                                PageRequest::new(None, cursor),
+                            )
+                            .await?;
+
+                        for vehicle in &vehicles.items {
+                            let reminder = Reminder::new(&org_id, vehicle, fee_type, overdue);
+                            if let Some(reminder) = reminder.unclaimed(&recipients, &claimed) {
+                                reminders.push(reminder);
+                            }
+                        }
+
+                        cursor = vehicles.next_cursor;
+                        if cursor.is_none() {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(reminders)
+    }
+
+    async fn claim_reminder(
+        &self,
+        reminder: &Reminder,
+        recipient: &Recipient,
+    ) -> Result<bool, DataAccessError> {
+        let result = self
+            .client
+            .put_item()
+            .table_name(&self.table_name)
+            .set_item(Some(reminder.to_item(recipient)))
+            .condition_expression("attribute_not_exists(PK) and attribute_not_exists(SK)")
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(err)
+                if err
+                    .as_service_error()
+                    .is_some_and(|err| err.is_conditional_check_failed_exception()) =>
+            {
+                Ok(false)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn release_reminder(
+        &self,
+        reminder: &Reminder,
+        recipient: &Recipient,
+    ) -> Result<(), DataAccessError> {
+        self.client
+            .delete_item()
+            .table_name(&self.table_name)
+            .key("PK", reminder_partition_key(&reminder.org_id))
+            .key("SK", reminder.get_key(recipient))
+            .send()
+            .await
+            .map(|_output| ())
+            .map_err(|err| err.into())
+    }
+
+    async fn create_calendar_feed(&self, token: &str) -> Result<CalendarFeed, DataAccessError> {
+        let caller = self
+            .get_caller(token)
+            .await?
+            .ok_or_else(|| DataAccessError::Unauthorized("You don't have access!!".to_string()))?;
+
+        let feed = CalendarFeed::new(&caller.org_id);
+        self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .set_item(Some(feed.to_item(&caller.username)))
+            .send()
+            .await?;
+        Ok(feed)
+    }
+
+    async fn calendar_vehicles(
+        &self,
+        feed_token: &str,
+    ) -> Result<(CalendarFeed, Vec<Vehicle>), DataAccessError> {
+        let invalid =
+            || DataAccessError::Unauthorized("The calendar feed is not valid!!".to_string());
+
+        // GSI1 only projects the keys, the organization lives on the feed item.
+        let keys = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .index_name("GSI1")
+            .key_condition_expression("GSI1PK = :feed")
+            .expression_attribute_values(":feed", feed_key(feed_token))
+            .send()
+            .await?
+            .items
+            .unwrap_or_default()
+            .into_iter()
+            .next()
+            .ok_or_else(invalid)?;
+        let item = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("PK", keys.get("PK").unwrap().clone())
+            .key("SK", keys.get("SK").unwrap().clone())
+            .send()
+            .await?
+            .item
+            .ok_or_else(invalid)?;
+
+        let feed = calendar_feed_from_item(&item);
+        let username = &item.get("PK").unwrap().as_s().unwrap()[5..];
+        match self.find_user(username).await? {
+            Some(user) if user.orgs.contains(&feed.org_id) => (),
+            _ => return Err(invalid()),
+        }
+
+        let vehicles = self.org_vehicles(&feed.org_id).await?;
+        Ok((feed, vehicles))
+    }
 }
 
 impl DBDataAccess {
+    /// Reads the items of `keys` with `BatchGetItem`, 100 keys per call and
+    /// retrying the unprocessed ones.
+    async fn batch_get(
+        &self,
+        keys: Vec<HashMap<String, AttributeValue>>,
+    ) -> Result<Vec<HashMap<String, AttributeValue>>, DataAccessError> {
+        let mut items = Vec::new();
+        for keys in keys.chunks(100) {
+            let mut request_keys = Some(
+                KeysAndAttributes::builder()
+                    .set_keys(Some(keys.to_vec()))
+                    .build()
+                    .map_err(|err| DataAccessError::Storage(err.to_string()))?,
+            );
+
+            while let Some(keys) = request_keys {
+                let output = self
+                    .client
+                    .batch_get_item()
+                    .request_items(&self.table_name, keys)
+                    .send()
+                    .await?;
+
+                items.extend(
+                    output
+                        .responses
+                        .and_then(|mut responses| responses.remove(&self.table_name))
+                        .unwrap_or_default(),
+                );
+                request_keys = output
+                    .unprocessed_keys
+                    .and_then(|mut unprocessed| unprocessed.remove(&self.table_name));
+            }
+        }
+        Ok(items)
+    }
+
+    /// The vehicle and SEARCH items of an imported row. A new vehicle is only
+    /// put if it is still missing, an existing one is updated in place as
+    /// long as it isn't sold.
+    fn import_items(
+        &self,
+        org_id: &str,
+        vehicle: Vehicle,
+        update: bool,
+    ) -> Result<Vec<TransactWriteItem>, DataAccessError> {
+        let search_item = vehicle.to_search_item(org_id);
+        let vehicle_write = match update {
+            true => {
+                let mut expression = "SET #owner = :owner, tax_date = :tax_date, \
+                                      fitness_date = :fitness_date, \
+                                      insurance_date = :insurance_date, \
+                                      route_date = :route_date, updated_at = :updated_at"
+                    .to_string();
+                let update = Update::builder()
+                    .table_name(&self.table_name)
+                    .key("PK", vehicle.get_key(org_id))
+                    .key("SK", vehicle.get_key(org_id))
+                    .condition_expression("attribute_exists(PK) AND NOT Sold = :sold")
+                    .return_values_on_condition_check_failure(
+                        ReturnValuesOnConditionCheckFailure::AllOld,
+                    )
+                    .expression_attribute_names("#owner", "owner")
+                    .expression_attribute_values(":sold", AttributeValue::Bool(true))
+                    .expression_attribute_values(":owner", AttributeValue::S(vehicle.owner))
+                    .expression_attribute_values(":tax_date", AttributeValue::S(vehicle.tax_date))
+                    .expression_attribute_values(
+                        ":fitness_date",
+                        AttributeValue::S(vehicle.fitness_date),
+                    )
+                    .expression_attribute_values(
+                        ":insurance_date",
+                        AttributeValue::S(vehicle.insurance_date),
+                    )
+                    .expression_attribute_values(
+                        ":route_date",
+                        AttributeValue::S(vehicle.route_date),
+                    )
+                    .expression_attribute_values(
+                        ":updated_at",
+                        AttributeValue::S(Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)),
+                    );
+                let update = match vehicle.vehicle_class {
+                    Some(vehicle_class) => {
+                        expression.push_str(", vehicle_class = :vehicle_class");
+                        update.expression_attribute_values(
+                            ":vehicle_class",
+                            AttributeValue::S(vehicle_class),
+                        )
+                    }
+                    None => update,
+                };
+                let update = update
+                    .update_expression(expression)
+                    .build()
+                    .map_err(|err| DataAccessError::Storage(err.to_string()))?;
+                TransactWriteItem::builder().update(update).build()
+            }
+            false => {
+                let put = Put::builder()
+                    .table_name(&self.table_name)
+                    .set_item(Some(vehicle.to_item(org_id)))
+                    .condition_expression("attribute_not_exists(PK) and attribute_not_exists(SK)")
+                    .build()
+                    .map_err(|err| DataAccessError::Storage(err.to_string()))?;
+                TransactWriteItem::builder().put(put).build()
+            }
+        };
+
+        let put_search = Put::builder()
+            .table_name(&self.table_name)
+            .set_item(Some(search_item))
+            .build()
+            .map_err(|err| DataAccessError::Storage(err.to_string()))?;
+
+        Ok(vec![
+            vehicle_write,
+            TransactWriteItem::builder().put(put_search).build(),
+        ])
+    }
+
+    /// Every unsold vehicle of `org_id`, all pages of `GSI2`.
+    async fn org_vehicles(&self, org_id: &str) -> Result<Vec<Vehicle>, DataAccessError> {
+        let mut vehicles = Vec::new();
+        let mut exclusive_start_key = None;
+        loop {
+            let output = self
+                .client
+                .query()
+                .table_name(&self.table_name)
+                .index_name("GSI2")
+                .key_condition_expression("GSI2PK = :vehicle_key")
+                .expression_attribute_values(":vehicle_key", vehicle_partition_key(org_id))
+                .set_exclusive_start_key(exclusive_start_key)
+                .send()
+                .await?;
+
+            vehicles.extend(vehicle_repo(output.items.unwrap_or_default()));
+            exclusive_start_key = output.last_evaluated_key;
+            if exclusive_start_key.is_none() {
+                return Ok(vehicles);
+            }
+        }
+    }
+
+    /// Ids of every organization. Only the scheduled jobs need them all, so a
+    /// scan is good enough.
+    async fn org_ids(&self) -> Result<Vec<String>, DataAccessError> {
+        let mut org_ids = Vec::new();
+        let mut exclusive_start_key = None;
+        loop {
+            let output = self
+                .client
+                .scan()
+                .table_name(&self.table_name)
+                .filter_expression("PK = SK AND begins_with(PK, :org) AND attribute_exists(#name)")
+                .expression_attribute_names("#name", "name")
+                .expression_attribute_values(":org", AttributeValue::S("ORG#".to_string()))
+                .set_exclusive_start_key(exclusive_start_key)
+                .send()
+                .await?;
+
+            org_ids.extend(
+                output
+                    .items
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|item| org_from_item(item).org_id),
+            );
+            exclusive_start_key = output.last_evaluated_key;
+            if exclusive_start_key.is_none() {
+                break;
+            }
+        }
+        Ok(org_ids)
+    }
+
+    async fn reminder_recipients(&self, org_id: &str) -> Result<Vec<Recipient>, DataAccessError> {
+        let mut usernames = Vec::new();
+        let mut exclusive_start_key = None;
+        loop {
+            let output = self
+                .client
+                .query()
+                .table_name(&self.table_name)
+                .key_condition_expression("PK = :org AND begins_with(SK, :user)")
+                .expression_attribute_values(":org", org_key(org_id))
+                .expression_attribute_values(":user", AttributeValue::S("USER#".to_string()))
+                .set_exclusive_start_key(exclusive_start_key)
+                .send()
+                .await?;
+
+            usernames.extend(
+                output
+                    .items
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|item| item.get("SK").unwrap().as_s().unwrap()[5..].to_string()),
+            );
+            exclusive_start_key = output.last_evaluated_key;
+            if exclusive_start_key.is_none() {
+                break;
+            }
+        }
+
+        let mut recipients = Vec::new();
+        for username in usernames {
+            if let Some(user) = self.find_user(&username).await? {
+                recipients.extend(Recipient::new(org_id, &user));
+            }
+        }
+        Ok(recipients)
+    }
+
+    /// The `SK` of every reminder claimed in the organization.
+    async fn claimed_reminders(&self, org_id: &str) -> Result<HashSet<String>, DataAccessError> {
+        let mut claimed = HashSet::new();
+        let mut exclusive_start_key = None;
+        loop {
+            let output = self
+                .client
+                .query()
+                .table_name(&self.table_name)
+                .key_condition_expression("PK = :reminders")
+                .expression_attribute_values(":reminders", reminder_partition_key(org_id))
+                .projection_expression("SK")
+                .set_exclusive_start_key(exclusive_start_key)
+                .send()
+                .await?;
+
+            claimed.extend(
+                output
+                    .items
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|item| item.get("SK")?.as_s().ok().cloned()),
+            );
+            exclusive_start_key = output.last_evaluated_key;
+            if exclusive_start_key.is_none() {
+                break;
+            }
+        }
+        Ok(claimed)
+    }
+
+    /// Gives a user that signed up before organizations existed the
+    /// organization signup creates now, named after them, with the user as
+    /// admin. Returns the user as stored afterwards.
+    async fn create_personal_organization(&self, user: User) -> Result<User, DataAccessError> {
+        let organization = Organization::new(user.username.clone());
+        let put_org = Put::builder()
+            .table_name(&self.table_name)
+            .set_item(Some(organization.to_item()))
+            .build()
+            .unwrap();
+
+        let mut transact_items = vec![TransactWriteItem::builder().put(put_org).build()];
+        transact_items.extend(self.join_organization(&organization.org_id, &user, Role::Admin));
+
+        self.client
+            .transact_write_items()
+            .set_transact_items(Some(transact_items))
+            .send()
+            .await
+            .map_err(|err| {
+                tracing::error!(%err, "Personal organization is not created");
+                DataAccessError::from(err)
+            })?;
+
+        tracing::info!(
+            username = user.username,
+            org_id = organization.org_id,
+            "Created the organization of a user from before organizations"
+        );
+        Ok(User {
+            roles: user.roles_with(&organization.org_id, Role::Admin),
+            orgs: [user.orgs, vec![organization.org_id]].concat(),
+            ..user
+        })
+    }
+
+    /// Writes the membership item, appends the organization to the user's
+    /// `orgs` list and gives the user `role` there. The user item must exist.
+    ///
+    /// The user item is what access is checked against, the membership items
+    /// only list the members of an organization. Both are written in this one
+    /// transaction, which fails when either already has the membership.
+    fn join_organization(&self, org_id: &str, user: &User, role: Role) -> Vec<TransactWriteItem> {
+        let put_member = Put::builder()
+            .table_name(&self.table_name)
+            .set_item(Some(member_item(org_id, &user.username)))
+            .condition_expression("attribute_not_exists(PK) and attribute_not_exists(SK)")
+            .build()
+            .unwrap();
+
+        let update_user = Update::builder()
+            .table_name(&self.table_name)
+            .key("PK", user.get_key())
+            .key("SK", user.get_key())
+            .update_expression(
+                "SET orgs = list_append(if_not_exists(orgs, :empty), :org_ids), #roles = :roles",
+            )
+            .condition_expression(
+                "attribute_exists(PK) AND (attribute_not_exists(orgs) OR NOT contains(orgs, :org_id))",
+            )
+            .expression_attribute_names("#roles", "roles")
+            .expression_attribute_values(":org_id", AttributeValue::S(org_id.to_string()))
+            .expression_attribute_values(":empty", AttributeValue::L(Vec::new()))
+            .expression_attribute_values(
+                ":org_ids",
+                AttributeValue::L(vec![AttributeValue::S(org_id.to_string())]),
+            )
+            .expression_attribute_values(":roles", roles_value(&user.roles_with(org_id, role)))
+            .build()
+            .unwrap();
+
+        vec![
+            TransactWriteItem::builder().put(put_member).build(),
+            TransactWriteItem::builder().update(update_user).build(),
+        ]
+    }
+
     async fn delete_history(
         &self,
+        org_id: &str,
         delete_history: DeleteHistory,
-    ) -> Result<TransactWriteItem, Error> {
+    ) -> Result<TransactWriteItem, DataAccessError> {
         let history_delete = Delete::builder()
             .table_name(&self.table_name)
-            .key("PK", vehicle_key(&delete_history.vehicle_no))
+            .key("PK", vehicle_key(org_id, &delete_history.vehicle_no))
             .key(
                 "SK",
                 AttributeValue::S(format!(
@@ -719,8 +2563,126 @@ impl DBDataAccess {
                     delete_history.transaction_type, delete_history.created_at
                 )),
             )
-            .build()?;
+            .build()
+            .map_err(|err| DataAccessError::Storage(err.to_string()))?;
 
         Ok(TransactWriteItem::builder().delete(history_delete).build())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use aws_sdk_dynamodb::types::{error::TransactionCanceledException, CancellationReason};
+
+    use super::*;
+
+    fn canceled(reasons: Vec<CancellationReason>) -> SdkError<TransactWriteItemsError, ()> {
+        SdkError::service_error(
+            TransactWriteItemsError::TransactionCanceledException(
+                TransactionCanceledException::builder()
+                    .set_cancellation_reasons(Some(reasons))
+                    .build(),
+            ),
+            (),
+        )
+    }
+
+    fn reason(code: &str) -> CancellationReason {
+        CancellationReason::builder().code(code).build()
+    }
+
+    #[test]
+    fn import_failure_reports_the_reason_of_the_failed_item() {
+        let sold = CancellationReason::builder()
+            .code("ConditionalCheckFailed")
+            .item("Sold", AttributeValue::Bool(true))
+            .build();
+        let cases = [
+            (vec![reason("None"), sold], true, "The car is sold!!"),
+            (
+                vec![reason("ConditionalCheckFailed"), reason("None")],
+                true,
+                "The car is not in the record!!",
+            ),
+            (
+                vec![reason("ConditionalCheckFailed"), reason("None")],
+                false,
+                "Vehicle already exists",
+            ),
+            (
+                vec![reason("None"), reason("TransactionConflict")],
+                false,
+                "The vehicle is being changed by another request, try again",
+            ),
+            (
+                vec![reason("ThrottlingError"), reason("None")],
+                true,
+                "The table is busy, try again",
+            ),
+            (
+                vec![reason("ValidationError")],
+                false,
+                "The vehicle is not written: ValidationError",
+            ),
+        ];
+
+        for (reasons, update, message) in cases {
+            assert_eq!(
+                import_failure(&canceled(reasons), update).as_deref(),
+                Some(message)
+            );
+        }
+    }
+
+    #[test]
+    fn fee_date_sets_only_the_date_of_its_fee() {
+        let vehicle_no = "DHA-KA-11-1234".parse::<VehicleNumber>().unwrap();
+        let update_vehicle =
+            UpdateVehicle::fee_date(vehicle_no.clone(), "fitness", "2026-01-02".to_string())
+                .unwrap();
+
+        assert_eq!(
+            update_vehicle
+                .iter()
+                .collect::<Vec<(String, Option<&String>)>>(),
+            [
+                (":tax_date".to_string(), None),
+                (":insurance_date".to_string(), None),
+                (":fitness_date".to_string(), Some(&"2026-01-02".to_string())),
+                (":route_date".to_string(), None),
+            ]
+        );
+        assert!(update_vehicle.owner.is_none());
+        assert!(
+            UpdateVehicle::fee_date(vehicle_no, "ownership", "2026-01-02".to_string()).is_none()
+        );
+    }
+
+    #[test]
+    fn update_failure_tells_sold_from_missing_vehicles() {
+        let sold = CancellationReason::builder()
+            .code("ConditionalCheckFailed")
+            .item("Sold", AttributeValue::Bool(true))
+            .build();
+
+        assert!(matches!(
+            update_failure(canceled(vec![reason("None"), sold])),
+            DataAccessError::Conflict(message) if message == "The car is sold!!"
+        ));
+        assert!(matches!(
+            update_failure(canceled(vec![reason("ConditionalCheckFailed")])),
+            DataAccessError::NotFound(_)
+        ));
+        assert!(matches!(
+            update_failure(canceled(vec![reason("TransactionConflict")])),
+            DataAccessError::Storage(_)
+        ));
+    }
+
+    #[test]
+    fn import_failure_ignores_other_errors() {
+        let err: SdkError<TransactWriteItemsError, ()> =
+            SdkError::timeout_error("the request timed out");
+        assert_eq!(import_failure(&err, false), None);
+    }
+}
@@ -0,0 +1,1359 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Mutex,
+};
+
+use async_trait::async_trait;
+use aws_sdk_dynamodb::types::AttributeValue;
+use chrono::{Duration, Local, SecondsFormat, Utc};
+use lambda_http::tracing;
+use pwhash::bcrypt;
+
+use crate::{
+    error::DataAccessError,
+    handler::FEE_TYPES,
+    is_sold,
+    model::{
+        calendar::{calendar_feed_from_item, feed_key, CalendarFeed},
+        history::{
+            history_from_item, history_key, history_partition_key, history_repo,
+            TransactionHistory, OWNERSHIP_TRANSACTION,
+        },
+        import::{ImportMode, ImportReport, ImportRow},
+        organization::{member_item, org_from_item, org_key, Organization},
+        reminder::{reminder_partition_key, Recipient, Reminder},
+        report::SpendingReport,
+        session::{
+            caller_from_item, check_expiry, device_from_item, device_key, last_seen_is_stale,
+            owner_of, refresh_from_item, refresh_key, session_key, ttl_value, Caller, Device,
+            DeviceSession, Logins, RefreshToken, Tokens,
+        },
+        user::{from_item, roles_value, user_key, Role, User},
+        vehicle::{
+            search_partition_key, sold_vehicle_partition_key, vehicle_details_from_item,
+            vehicle_key, vehicle_no_from_item, vehicle_no_from_key, vehicle_partition_key,
+            vehicle_repo, vehicle_search_key, Vehicle, VehicleDetails,
+        },
+        vehicle_number::VehicleNumber,
+    },
+    pagination::{Page, PageRequest},
+    renewal::RenewalRules,
+    search_query, session_org, validation, DataAccess, DeleteHistory, HistoryFilter, SellVehicle,
+    UpdateVehicle,
+};
+
+type Item = HashMap<String, AttributeValue>;
+
+/// A `DataAccess` backend that keeps every item in process memory.
+///
+/// Items are stored with the same `PK`/`SK` and index attributes that
+/// `DBDataAccess` writes to DynamoDB, so both backends share the key design
+/// and the `*_from_item` converters. Useful for tests and offline development.
+#[derive(Default)]
+pub struct InMemoryDataAccess {
+    table: Mutex<BTreeMap<(String, String), Item>>,
+    renewal_rules: RenewalRules,
+}
+
+impl InMemoryDataAccess {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_renewal_rules(self, renewal_rules: RenewalRules) -> Self {
+        Self {
+            renewal_rules,
+            ..self
+        }
+    }
+
+    fn key(pk: &AttributeValue, sk: &AttributeValue) -> (String, String) {
+        (
+            pk.as_s().unwrap().to_string(),
+            sk.as_s().unwrap().to_string(),
+        )
+    }
+
+    fn item_key(item: &Item) -> (String, String) {
+        Self::key(item.get("PK").unwrap(), item.get("SK").unwrap())
+    }
+
+    fn get_item(&self, pk: &AttributeValue, sk: &AttributeValue) -> Option<Item> {
+        self.table.lock().unwrap().get(&Self::key(pk, sk)).cloned()
+    }
+
+    fn put_item(&self, item: Item) {
+        self.table
+            .lock()
+            .unwrap()
+            .insert(Self::item_key(&item), item);
+    }
+
+    /// Returns every item whose `index_pk` attribute equals `value`, ordered by
+    /// `index_sk` the way a DynamoDB index query would be.
+    fn query_index(&self, index_pk: &str, value: &str, index_sk: Option<&str>) -> Vec<Item> {
+        let mut items: Vec<Item> = self
+            .table
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|item| {
+                item.get(index_pk).and_then(|pk| pk.as_s().ok()) == Some(&value.to_string())
+            })
+            .cloned()
+            .collect();
+
+        if let Some(index_sk) = index_sk {
+            items.sort_by(|a, b| {
+                let a = a.get(index_sk).and_then(|sk| sk.as_s().ok());
+                let b = b.get(index_sk).and_then(|sk| sk.as_s().ok());
+                a.cmp(&b)
+            });
+        }
+        items
+    }
+
+    /// Cuts one page out of `items`, which must already be in index order.
+    ///
+    /// Like a DynamoDB query, `limit` counts evaluated items before `filter`
+    /// is applied and the cursor holds the `key_attributes` of the last
+    /// evaluated item. It must come from the same `partition`.
+    fn paginate(
+        items: Vec<Item>,
+        page: &PageRequest,
+        key_attributes: &[&str],
+        partition: (&str, &AttributeValue),
+        filter: impl Fn(&Item) -> bool,
+    ) -> Result<Page<Item>, DataAccessError> {
+        let start = match page.exclusive_start_key(key_attributes, partition)? {
+            Some(start_key) => {
+                let (pk, sk) = match (start_key.get("PK"), start_key.get("SK")) {
+                    (Some(pk), Some(sk)) => Self::key(pk, sk),
+                    _ => {
+                        return Err(DataAccessError::Validation(
+                            "The cursor is not valid!!".to_string(),
+                        ))
+                    }
+                };
+                items
+                    .iter()
+                    .position(|item| Self::item_key(item) == (pk.clone(), sk.clone()))
+                    .ok_or_else(|| {
+                        DataAccessError::Validation("The cursor is not valid!!".to_string())
+                    })?
+                    + 1
+            }
+            None => 0,
+        };
+
+        let limit = page.limit.map_or(items.len(), |limit| limit as usize);
+        let evaluated = items.into_iter().skip(start).collect::<Vec<Item>>();
+        let last_evaluated_key = match evaluated.len() > limit {
+            true => Some(
+                evaluated[limit - 1]
+                    .iter()
+                    .filter(|(name, _)| key_attributes.contains(&name.as_str()))
+                    .map(|(name, value)| (name.clone(), value.clone()))
+                    .collect(),
+            ),
+            false => None,
+        };
+
+        let items = evaluated
+            .into_iter()
+            .take(limit)
+            .filter(|item| filter(item))
+            .collect();
+
+        Ok(Page::new(items, last_evaluated_key))
+    }
+
+    fn find_user(&self, username: &str) -> Option<User> {
+        self.get_item(&user_key(username), &user_key(username))
+            .map(|item| from_item(&item))
+    }
+
+    fn get_caller(&self, token: &str) -> Result<Option<Caller>, DataAccessError> {
+        let session = session_key(token);
+        let session = match self
+            .query_index("GSI1PK", session.as_s().unwrap(), Some("GSI1SK"))
+            .into_iter()
+            .next()
+        {
+            Some(session) => session,
+            None => return Ok(None),
+        };
+        check_expiry(&session)?;
+        if last_seen_is_stale(&session) {
+            self.touch_session(&session);
+        }
+        Ok(self
+            .find_user(&owner_of(&session))
+            .and_then(|user| caller_from_item(&session, &user)))
+    }
+
+    /// Moves the `last_seen_at` of a session and its device item to now.
+    fn touch_session(&self, session: &Item) {
+        let now = AttributeValue::S(Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true));
+        let pk = session.get("PK").unwrap();
+        let family_id = session.get("family_id").unwrap().as_s().unwrap();
+        let mut table = self.table.lock().unwrap();
+        for sk in [session.get("SK").unwrap(), &device_key(family_id)] {
+            if let Some(item) = table.get_mut(&Self::key(pk, sk)) {
+                item.insert("last_seen_at".to_string(), now.clone());
+            }
+        }
+    }
+
+    /// Removes the sessions, refresh tokens and device items of the `logins`
+    /// of `username`.
+    fn delete_tokens(&self, username: &str, logins: Logins) {
+        let pk = user_key(username).as_s().unwrap().to_string();
+        self.table.lock().unwrap().retain(|(item_pk, sk), item| {
+            let token = ["SESSION#", "REFRESH#", "DEVICE#"]
+                .iter()
+                .any(|prefix| sk.starts_with(prefix));
+            !(*item_pk == pk && token && logins.contains(item))
+        });
+    }
+
+    /// Writes the membership item, appends the organization to the user's
+    /// `orgs` list and gives the user `role` there, like
+    /// `DBDataAccess::join_organization`.
+    fn join_organization(&self, org_id: &str, user: &User, role: Role) {
+        let mut item = self.get_item(&user.get_key(), &user.get_key()).unwrap();
+
+        let mut orgs = match item.remove("orgs") {
+            Some(AttributeValue::L(orgs)) => orgs,
+            _ => Vec::new(),
+        };
+        orgs.push(AttributeValue::S(org_id.to_string()));
+        item.insert("orgs".to_string(), AttributeValue::L(orgs));
+        item.insert(
+            "roles".to_string(),
+            roles_value(&user.roles_with(org_id, role)),
+        );
+
+        self.put_item(item);
+        self.put_item(member_item(org_id, &user.username));
+    }
+
+    fn get_fees_info(
+        &self,
+        org_id: &str,
+        index_type: &str,
+        days: u32,
+        page: PageRequest,
+    ) -> Result<Page<Vehicle>, DataAccessError> {
+        let date_attribute = format!("{}_date", index_type.to_lowercase());
+        let today = Local::now().format("%Y-%m-%d").to_string();
+        let end_date = (Local::now() + Duration::days(days as i64))
+            .format("%Y-%m-%d")
+            .to_string();
+
+        let vehicle_partition = vehicle_partition_key(org_id);
+        let vehicle_items = Self::paginate(
+            self.query_index("GSI2PK", vehicle_partition.as_s().unwrap(), None),
+            &page,
+            &["PK", "SK", "GSI2PK"],
+            ("GSI2PK", &vehicle_partition),
+            |item| {
+                let date = match item.get(&date_attribute).and_then(|date| date.as_s().ok()) {
+                    Some(date) => date,
+                    None => return false,
+                };
+                match days {
+                    0 => *date < today,
+                    _ => *date >= today && *date <= end_date,
+                }
+            },
+        )?;
+
+        Ok(Page {
+            items: vehicle_repo(vehicle_items.items),
+            next_cursor: vehicle_items.next_cursor,
+        })
+    }
+
+    /// Applies the fee dates and owner of `vehicle` to the stored vehicle
+    /// item, mirroring the `UpdateItem` built by `DBDataAccess`.
+    fn update_vehicle(&self, org_id: &str, vehicle: &UpdateVehicle) -> Result<(), DataAccessError> {
+        if vehicle.iter().all(|(_fee, date)| date.is_none())
+            && vehicle.owner.is_none()
+            && vehicle.vehicle_class.is_none()
+        {
+            return Err(DataAccessError::Validation(
+                "No updated fee date, owner or vehicle class is provided".to_string(),
+            ));
+        }
+        let fee_dates = vehicle.fee_dates()?;
+
+        let search = search_partition_key(org_id);
+        if self
+            .get_item(&search, &vehicle_search_key(&vehicle.vehicle_no.as_key()))
+            .is_none()
+        {
+            return Err(DataAccessError::NotFound(
+                "The car is not in the record!!".to_string(),
+            ));
+        }
+
+        let key = vehicle_key(org_id, &vehicle.vehicle_no);
+        let mut table = self.table.lock().unwrap();
+        // The condition of the `Update` of `DBDataAccess::update_vehicle`.
+        let item = match table.get_mut(&Self::key(&key, &key)) {
+            Some(item) if is_sold(item) => {
+                return Err(DataAccessError::Conflict("The car is sold!!".to_string()))
+            }
+            Some(item) => item,
+            None => {
+                return Err(DataAccessError::NotFound(
+                    "The car is not in the record!!".to_string(),
+                ))
+            }
+        };
+
+        for (fee, date) in fee_dates {
+            item.insert(fee.replace(':', ""), AttributeValue::S(date));
+        }
+        if let Some(owner) = &vehicle.owner {
+            item.insert("owner".to_string(), AttributeValue::S(owner.to_string()));
+        }
+        if let Some(vehicle_class) = &vehicle.vehicle_class {
+            item.insert(
+                "vehicle_class".to_string(),
+                AttributeValue::S(vehicle_class.to_string()),
+            );
+        }
+        item.insert(
+            "updated_at".to_string(),
+            AttributeValue::S(Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)),
+        );
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DataAccess for InMemoryDataAccess {
+    async fn create_user(&self, user: User) -> Result<(), DataAccessError> {
+        if self.get_item(&user.get_key(), &user.get_key()).is_some() {
+            tracing::error!("User create Fail Error: {} already exists", user.username);
+            return Err(DataAccessError::Conflict(
+                "Username already exist!!".to_string(),
+            ));
+        }
+        let organization = Organization::new(
+            user.organization
+                .clone()
+                .unwrap_or_else(|| user.username.clone()),
+        );
+        let user = User {
+            orgs: vec![organization.org_id.clone()],
+            roles: HashMap::from([(organization.org_id.clone(), Role::Admin)]),
+            ..user
+        };
+        self.put_item(user.to_item());
+        self.put_item(member_item(&organization.org_id, &user.username));
+        self.put_item(organization.to_item());
+        Ok(())
+    }
+
+    async fn get_session(&self, user: User, device: Device) -> Result<Tokens, DataAccessError> {
+        let stored = self
+            .get_item(&user.get_key(), &user.get_key())
+            .map(|item| from_item(&item))
+            .filter(|stored| stored.varify(&user.password));
+
+        if let Some(stored) = stored {
+            let org_id = session_org(user.org_id.as_deref(), &stored.orgs)?;
+            let (session, refresh) = RefreshToken::start_family(&org_id);
+            self.put_item(session.to_item(&user.username));
+            self.put_item(refresh.to_item(&user.username));
+            self.put_item(DeviceSession::new(device, &session, &refresh).to_item(&user.username));
+            Ok(Tokens::new(&session, &refresh))
+        } else {
+            Err(DataAccessError::Unauthorized("Login fail!!".to_string()))
+        }
+    }
+
+    async fn refresh_session(
+        &self,
+        refresh_token: &str,
+        device: Device,
+    ) -> Result<Tokens, DataAccessError> {
+        let invalid =
+            || DataAccessError::Unauthorized("The refresh token is not valid!!".to_string());
+        let reused = || {
+            DataAccessError::Unauthorized(
+                "The refresh token is already used, the login is revoked!!".to_string(),
+            )
+        };
+
+        let key = refresh_key(refresh_token);
+        let item = self
+            .query_index("GSI1PK", key.as_s().unwrap(), Some("GSI1SK"))
+            .into_iter()
+            .next()
+            .ok_or_else(invalid)?;
+        check_expiry(&item)?;
+
+        let username = owner_of(&item);
+        let stored = refresh_from_item(&item);
+        let user = self.find_user(&username).ok_or_else(invalid)?;
+        session_org(Some(&stored.org_id), &user.orgs)?;
+
+        // Checked and set under one lock, like the conditional update of
+        // `DBDataAccess::refresh_session`.
+        let marked = match self
+            .table
+            .lock()
+            .unwrap()
+            .get_mut(&Self::key(&user_key(&username), &key))
+        {
+            Some(item) if !matches!(item.get("used"), Some(AttributeValue::Bool(true))) => {
+                item.insert("used".to_string(), AttributeValue::Bool(true));
+                true
+            }
+            _ => false,
+        };
+        if !marked {
+            self.delete_tokens(&username, Logins::Only(&stored.family_id));
+            return Err(reused());
+        }
+
+        let (session, refresh) = stored.rotate();
+        self.put_item(session.to_item(&username));
+        self.put_item(refresh.to_item(&username));
+
+        let device_pk = user_key(&username);
+        let device_sk = device_key(&stored.family_id);
+        let mut item = self.get_item(&device_pk, &device_sk).unwrap_or_else(|| {
+            HashMap::from([
+                ("PK".to_string(), device_pk.clone()),
+                ("SK".to_string(), device_sk.clone()),
+                (
+                    "created_at".to_string(),
+                    AttributeValue::S(session.created_at.clone()),
+                ),
+            ])
+        });
+        for (name, value) in [
+            ("last_seen_at", Some(session.created_at.clone())),
+            ("expired_at", Some(refresh.expired_at.clone())),
+            ("family_id", Some(stored.family_id.clone())),
+            ("org_id", Some(stored.org_id.clone())),
+            ("user_agent", device.user_agent),
+            ("source_ip", device.source_ip),
+        ] {
+            if let Some(value) = value {
+                item.insert(name.to_string(), AttributeValue::S(value));
+            }
+        }
+        item.insert("TTL".to_string(), ttl_value(&refresh.expired_at));
+        self.put_item(item);
+
+        Ok(Tokens::new(&session, &refresh))
+    }
+
+    async fn delete_session(&self, token: &str) -> Result<String, DataAccessError> {
+        let caller = self
+            .get_caller(token)?
+            .ok_or_else(|| DataAccessError::Unauthorized("Session Expired!!".to_string()))?;
+        match &caller.family_id {
+            Some(family_id) => self.delete_tokens(&caller.username, Logins::Only(family_id)),
+            None => {
+                self.table
+                    .lock()
+                    .unwrap()
+                    .remove(&Self::key(&user_key(&caller.username), &session_key(token)));
+            }
+        }
+
+        Ok(caller.username)
+    }
+
+    async fn list_sessions(&self, token: &str) -> Result<Vec<DeviceSession>, DataAccessError> {
+        let caller = self.get_caller(token)?.ok_or_else(|| {
+            DataAccessError::Unauthorized("Session Expired!! login Again.".to_string())
+        })?;
+        let pk = user_key(&caller.username).as_s().unwrap().to_string();
+
+        let mut sessions = self
+            .table
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|((item_pk, sk), item)| {
+                *item_pk == pk && sk.starts_with("DEVICE#") && check_expiry(item).is_ok()
+            })
+            .map(|(_, item)| device_from_item(item, caller.family_id.as_deref()))
+            .collect::<Vec<DeviceSession>>();
+        sessions.sort_by(|a, b| b.last_seen_at.cmp(&a.last_seen_at));
+        Ok(sessions)
+    }
+
+    async fn revoke_session(&self, token: &str, session_id: &str) -> Result<(), DataAccessError> {
+        let caller = self.get_caller(token)?.ok_or_else(|| {
+            DataAccessError::Unauthorized("Session Expired!! login Again.".to_string())
+        })?;
+
+        self.get_item(&user_key(&caller.username), &device_key(session_id))
+            .ok_or_else(|| DataAccessError::NotFound("The session is not found!!".to_string()))?;
+
+        self.delete_tokens(&caller.username, Logins::Only(session_id));
+        Ok(())
+    }
+
+    async fn revoke_other_sessions(&self, token: &str) -> Result<(), DataAccessError> {
+        let caller = self.get_caller(token)?.ok_or_else(|| {
+            DataAccessError::Unauthorized("Session Expired!! login Again.".to_string())
+        })?;
+        let family_id = caller.family_id.ok_or_else(|| {
+            DataAccessError::SessionExpired("Session Expired!! login Again.".to_string())
+        })?;
+
+        self.delete_tokens(&caller.username, Logins::AllBut(&family_id));
+        Ok(())
+    }
+
+    async fn change_pass(
+        &self,
+        token: &str,
+        old_pass: &str,
+        new_pass: &str,
+        revoke_others: bool,
+    ) -> Result<(), DataAccessError> {
+        let caller = self.get_caller(token)?.ok_or_else(|| {
+            DataAccessError::Unauthorized("Session Expired!! login Again.".to_string())
+        })?;
+        let user = user_key(&caller.username);
+
+        let mut item = self
+            .get_item(&user, &user)
+            .ok_or_else(|| DataAccessError::NotFound("User is not found!!".to_string()))?;
+
+        if from_item(&item).varify(old_pass) {
+            item.insert(
+                "password".to_string(),
+                AttributeValue::S(bcrypt::hash(new_pass).unwrap()),
+            );
+            self.put_item(item);
+
+            match (revoke_others, caller.family_id) {
+                (true, Some(family_id)) => {
+                    self.delete_tokens(&caller.username, Logins::AllBut(&family_id))
+                }
+                (true, None) => self.delete_tokens(&caller.username, Logins::All),
+                (false, _) => {}
+            }
+            Ok(())
+        } else {
+            Err(DataAccessError::Validation(
+                "Password is not valid!!!".to_string(),
+            ))
+        }
+    }
+
+    async fn add_vehicle(&self, token: &str, car: Vehicle) -> Result<(), DataAccessError> {
+        let caller = self
+            .get_caller(token)?
+            .ok_or_else(|| DataAccessError::Unauthorized("You don't have access!!".to_string()))?;
+        caller.require(Role::Operator)?;
+
+        let search_item = car.to_search_item(&caller.org_id);
+        let vehicle_item = car.to_item(&caller.org_id);
+
+        let mut table = self.table.lock().unwrap();
+        if table.contains_key(&Self::item_key(&search_item))
+            || table.contains_key(&Self::item_key(&vehicle_item))
+        {
+            return Err(DataAccessError::Conflict(
+                "Vehicle already exists".to_string(),
+            ));
+        }
+        table.insert(Self::item_key(&search_item), search_item);
+        table.insert(Self::item_key(&vehicle_item), vehicle_item);
+        Ok(())
+    }
+
+    async fn import_vehicles(
+        &self,
+        token: &str,
+        rows: Vec<ImportRow>,
+        mode: ImportMode,
+    ) -> Result<ImportReport, DataAccessError> {
+        let caller = self.get_caller(token)?.ok_or_else(|| {
+            DataAccessError::Unauthorized("You don't have valid access!!".to_string())
+        })?;
+        caller.require(Role::Operator)?;
+
+        let mut report = ImportReport::default();
+        let mut table = self.table.lock().unwrap();
+        for ImportRow { row, vehicle } in rows {
+            let search_item = vehicle.to_search_item(&caller.org_id);
+            let vehicle_item = vehicle.to_item(&caller.org_id);
+            let key = Self::item_key(&vehicle_item);
+
+            match table.get_mut(&key) {
+                None => {
+                    table.insert(Self::item_key(&search_item), search_item);
+                    table.insert(key, vehicle_item);
+                    report.written(false);
+                }
+                Some(stored) if is_sold(stored) => report.error(
+                    row,
+                    Some(vehicle_no_from_item(&vehicle_item, "SK").to_string()),
+                    "The car is sold!!",
+                ),
+                Some(_) if mode == ImportMode::Skip => report.skipped += 1,
+                Some(stored) => {
+                    // Same attributes as the `UpdateItem` of `DBDataAccess`.
+                    for name in [
+                        "owner",
+                        "tax_date",
+                        "fitness_date",
+                        "insurance_date",
+                        "route_date",
+                        "vehicle_class",
+                    ] {
+                        if let Some(value) = vehicle_item.get(name) {
+                            stored.insert(name.to_string(), value.clone());
+                        }
+                    }
+                    stored.insert(
+                        "updated_at".to_string(),
+                        AttributeValue::S(Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)),
+                    );
+                    table.insert(Self::item_key(&search_item), search_item);
+                    report.written(true);
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    async fn get_all_vehicle(
+        &self,
+        token: &str,
+        page: PageRequest,
+    ) -> Result<Page<Vehicle>, DataAccessError> {
+        if let Some(caller) = self.get_caller(token)? {
+            let vehicle_partition = vehicle_partition_key(&caller.org_id);
+            let vehicle_items = Self::paginate(
+                self.query_index("GSI2PK", vehicle_partition.as_s().unwrap(), None),
+                &page,
+                &["PK", "SK", "GSI2PK"],
+                ("GSI2PK", &vehicle_partition),
+                |_| true,
+            )?;
+
+            Ok(Page {
+                items: vehicle_repo(vehicle_items.items),
+                next_cursor: vehicle_items.next_cursor,
+            })
+        } else {
+            Err(DataAccessError::Unauthorized(
+                "You don't have access!!".to_string(),
+            ))
+        }
+    }
+
+    async fn get_vehicles_by_type(
+        &self,
+        token: &str,
+        fee_type: &str,
+        days: u32,
+        page: PageRequest,
+    ) -> Result<Page<Vehicle>, DataAccessError> {
+        if let Some(caller) = self.get_caller(token)? {
+            let org_id = &caller.org_id;
+            match fee_type {
+                "fitness" => self.get_fees_info(org_id, "fitness", days, page),
+                "insurance" => self.get_fees_info(org_id, "insurance", days, page),
+                "route" => self.get_fees_info(org_id, "route", days, page),
+                _ => self.get_fees_info(org_id, "tax", days, page),
+            }
+        } else {
+            Err(DataAccessError::Unauthorized(
+                "You don't have access!!".to_string(),
+            ))
+        }
+    }
+
+    async fn get_vehicle(
+        &self,
+        token: &str,
+        vehicle_no: &VehicleNumber,
+    ) -> Result<VehicleDetails, DataAccessError> {
+        let caller = self
+            .get_caller(token)?
+            .ok_or_else(|| DataAccessError::Unauthorized("You don't have access!!".to_string()))?;
+
+        let key = vehicle_key(&caller.org_id, vehicle_no);
+        self.get_item(&key, &key)
+            .map(|item| vehicle_details_from_item(&item))
+            .ok_or_else(|| DataAccessError::NotFound("The car is not in the record!!".to_string()))
+    }
+
+    async fn search_vehicles(
+        &self,
+        token: &str,
+        query: &str,
+    ) -> Result<Vec<Vehicle>, DataAccessError> {
+        let caller = self
+            .get_caller(token)?
+            .ok_or_else(|| DataAccessError::Unauthorized("You don't have access!!".to_string()))?;
+        let (query, last_digits) = search_query(query)?;
+
+        let search_attribute = match last_digits {
+            true => "LSI1SK",
+            false => "SK",
+        };
+        let prefix = vehicle_search_key(&query);
+        let prefix = prefix.as_s().unwrap();
+
+        let search_partition = search_partition_key(&caller.org_id);
+        let vehicles = self
+            .query_index(
+                "PK",
+                search_partition.as_s().unwrap(),
+                Some(search_attribute),
+            )
+            .iter()
+            .filter(|item| {
+                item.get(search_attribute)
+                    .and_then(|key| key.as_s().ok())
+                    .is_some_and(|key| key.starts_with(prefix))
+            })
+            .filter_map(|item| {
+                let vehicle_no = vehicle_no_from_key(item.get("SK").unwrap());
+                let key = vehicle_key(&caller.org_id, &vehicle_no);
+                self.get_item(&key, &key)
+            })
+            .collect::<Vec<Item>>();
+
+        let mut vehicles = vehicle_repo(vehicles);
+        vehicles.sort_by(|a, b| a.vehicle_no.cmp(&b.vehicle_no));
+        Ok(vehicles)
+    }
+
+    async fn pay_fee(
+        &self,
+        token: &str,
+        fee_type: &str,
+        mut update_vehicle: UpdateVehicle,
+    ) -> Result<(), DataAccessError> {
+        let caller = self.get_caller(token)?.ok_or_else(|| {
+            DataAccessError::Unauthorized("You don't have valid access!!".to_string())
+        })?;
+        caller.require(Role::Operator)?;
+
+        let key = vehicle_key(&caller.org_id, &update_vehicle.vehicle_no);
+        let old_vhicle = self.get_item(&key, &key).ok_or_else(|| {
+            DataAccessError::NotFound("The car is not in the record!!".to_string())
+        })?;
+
+        if is_sold(&old_vhicle) {
+            return Err(DataAccessError::Conflict("The car is sold!!".to_string()));
+        }
+
+        let exp_date = old_vhicle
+            .get(&*[fee_type, "_date"].join(""))
+            .and_then(|date| date.as_s().ok())
+            .ok_or_else(|| DataAccessError::Validation("The fee type is not valid!!".to_string()))?
+            .to_string();
+
+        update_vehicle.renew(&self.renewal_rules, fee_type, &old_vhicle, &exp_date);
+        self.update_vehicle(&caller.org_id, &update_vehicle)?;
+
+        let transaction_history = TransactionHistory::new(
+            vehicle_no_from_item(&old_vhicle, "SK"),
+            exp_date,
+            fee_type.to_string(),
+            caller.username.clone(),
+        )
+        .with_payment(
+            old_vhicle.get("owner").unwrap().as_s().unwrap().to_string(),
+            update_vehicle.payment,
+        );
+        self.put_item(transaction_history.to_item(&caller.org_id));
+        Ok(())
+    }
+
+    async fn update_vehicle(
+        &self,
+        token: &str,
+        update_vehicle: UpdateVehicle,
+    ) -> Result<(), DataAccessError> {
+        if let Some(caller) = self.get_caller(token)? {
+            caller.require(Role::Operator)?;
+            self.update_vehicle(&caller.org_id, &update_vehicle)
+        } else {
+            Err(DataAccessError::Unauthorized(
+                "You don't have valid access!!".to_string(),
+            ))
+        }
+    }
+
+    async fn view_history(
+        &self,
+        token: &str,
+        days: u32,
+        page: PageRequest,
+    ) -> Result<Page<TransactionHistory>, DataAccessError> {
+        let caller = self.get_caller(token)?.ok_or_else(|| {
+            DataAccessError::Unauthorized("Your Session is invalid!!".to_string())
+        })?;
+
+        let edate = history_key(&Local::now().format("%Y-%m-%d").to_string());
+        let sdate = history_key(
+            &(Local::now() - Duration::days(days as i64))
+                .format("%Y-%m-%d")
+                .to_string(),
+        );
+        let (sdate, edate) = (sdate.as_s().unwrap(), edate.as_s().unwrap());
+
+        let history_partition = history_partition_key(&caller.org_id);
+        let historys = self
+            .query_index("GSI3PK", history_partition.as_s().unwrap(), Some("GSI3SK"))
+            .into_iter()
+            .filter(|item| {
+                let date = item.get("GSI3SK").unwrap().as_s().unwrap();
+                date >= sdate && date <= edate
+            })
+            .rev()
+            .collect();
+        let historys = Self::paginate(
+            historys,
+            &page,
+            &["PK", "SK", "GSI3PK", "GSI3SK"],
+            ("GSI3PK", &history_partition),
+            |_| true,
+        )?;
+
+        Ok(Page {
+            items: history_repo(historys.items),
+            next_cursor: historys.next_cursor,
+        })
+    }
+
+    async fn sell_vehicle(&self, token: &str, sale: SellVehicle) -> Result<(), DataAccessError> {
+        let caller = self.get_caller(token)?.ok_or_else(|| {
+            DataAccessError::Unauthorized("You don't have valid access!!".to_string())
+        })?;
+        caller.require(Role::Operator)?;
+
+        let key = vehicle_key(&caller.org_id, &sale.vehicle_no);
+        let vehicle = self.get_item(&key, &key).ok_or_else(|| {
+            DataAccessError::NotFound("The car is not in the record!!".to_string())
+        })?;
+
+        if is_sold(&vehicle) {
+            return Err(DataAccessError::Conflict(
+                "The car is already sold!!".to_string(),
+            ));
+        }
+
+        let sale_date = match &sale.sale_date {
+            Some(date) => validation::iso_date("sale_date", date)?,
+            None => Local::now().date_naive().format("%Y-%m-%d").to_string(),
+        };
+
+        let transaction_history = TransactionHistory::ownership(
+            vehicle_no_from_item(&vehicle, "SK"),
+            sale_date.clone(),
+            vehicle.get("owner").unwrap().as_s().unwrap().to_string(),
+            sale.buyer.clone(),
+            caller.username.clone(),
+        );
+
+        let mut vehicle = vehicle;
+        vehicle.insert("Sold".to_string(), AttributeValue::Bool(true));
+        vehicle.insert("buyer".to_string(), AttributeValue::S(sale.buyer.clone()));
+        vehicle.insert("sold_at".to_string(), AttributeValue::S(sale_date));
+        vehicle.insert("owner".to_string(), AttributeValue::S(sale.buyer));
+        vehicle.insert(
+            "updated_at".to_string(),
+            AttributeValue::S(Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)),
+        );
+        vehicle.insert(
+            "GSI2PK".to_string(),
+            sold_vehicle_partition_key(&caller.org_id),
+        );
+        self.put_item(vehicle);
+        self.put_item(transaction_history.to_item(&caller.org_id));
+        Ok(())
+    }
+
+    async fn get_vehicle_history(
+        &self,
+        token: &str,
+        vehicle_no: &VehicleNumber,
+        filter: HistoryFilter,
+        page: PageRequest,
+    ) -> Result<Page<TransactionHistory>, DataAccessError> {
+        let caller = self.get_caller(token)?.ok_or_else(|| {
+            DataAccessError::Unauthorized("Your Session is invalid!!".to_string())
+        })?;
+
+        let (from, to) = filter.date_range();
+        let prefix = match &filter.transaction_type {
+            Some(transaction_type) => format!("TRANSACTION#{}#", transaction_type),
+            None => "TRANSACTION#".to_string(),
+        };
+
+        let in_range = |item: &Item| {
+            let date = &item.get("GSI3SK").unwrap().as_s().unwrap()["TRANSACTION#".len()..];
+            date >= from.as_str() && date <= to.as_str()
+        };
+
+        // With a type the date range is part of the key condition, without
+        // one it is a filter that does not shrink the evaluated page.
+        let vehicle = vehicle_key(&caller.org_id, vehicle_no);
+        let historys = self
+            .query_index("PK", vehicle.as_s().unwrap(), Some("SK"))
+            .into_iter()
+            .filter(|item| item.get("SK").unwrap().as_s().unwrap().starts_with(&prefix))
+            .filter(|item| filter.transaction_type.is_none() || in_range(item))
+            .rev()
+            .collect();
+        let historys = Self::paginate(historys, &page, &["PK", "SK"], ("PK", &vehicle), in_range)?;
+
+        Ok(Page {
+            items: history_repo(historys.items),
+            next_cursor: historys.next_cursor,
+        })
+    }
+
+    async fn spending_report(
+        &self,
+        token: &str,
+        filter: HistoryFilter,
+    ) -> Result<SpendingReport, DataAccessError> {
+        let caller = self.get_caller(token)?.ok_or_else(|| {
+            DataAccessError::Unauthorized("Your Session is invalid!!".to_string())
+        })?;
+
+        let (from, to) = filter.date_range();
+        let (from, to) = (history_key(&from), history_key(&to));
+        let prefix = match &filter.transaction_type {
+            Some(transaction_type) => format!("TRANSACTION#{}#", transaction_type),
+            None => "TRANSACTION#".to_string(),
+        };
+
+        let history_partition = history_partition_key(&caller.org_id);
+        let historys = self
+            .query_index("GSI3PK", history_partition.as_s().unwrap(), Some("GSI3SK"))
+            .into_iter()
+            .filter(|item| {
+                let date = item.get("GSI3SK").unwrap().as_s().unwrap();
+                date >= from.as_s().unwrap() && date <= to.as_s().unwrap()
+            })
+            .filter(|item| item.get("SK").unwrap().as_s().unwrap().starts_with(&prefix))
+            .collect::<Vec<Item>>();
+
+        Ok(SpendingReport::new(
+            filter.from,
+            filter.to,
+            &history_repo(historys),
+        ))
+    }
+
+    async fn undo_history(
+        &self,
+        token: &str,
+        delete_history: DeleteHistory,
+    ) -> Result<(), DataAccessError> {
+        let caller = self.get_caller(token)?.ok_or_else(|| {
+            DataAccessError::Unauthorized("Your Session is invalid!!".to_string())
+        })?;
+        caller.require(Role::Admin)?;
+        if delete_history.transaction_type == OWNERSHIP_TRANSACTION {
+            return Err(DataAccessError::Validation(
+                "An ownership change can't be undone!!".to_string(),
+            ));
+        }
+
+        let pk = vehicle_key(&caller.org_id, &delete_history.vehicle_no);
+        let sk = AttributeValue::S(format!(
+            "TRANSACTION#{}#{}",
+            delete_history.transaction_type, delete_history.created_at
+        ));
+        let current_history = self
+            .get_item(&pk, &sk)
+            .map(|history| history_from_item(&history))
+            .ok_or_else(|| DataAccessError::NotFound("No record is available".to_string()))?;
+
+        let update_vehicle = UpdateVehicle::fee_date(
+            current_history.vehicle_no,
+            &delete_history.transaction_type,
+            delete_history.created_at.clone(),
+        )
+        .ok_or_else(|| {
+            DataAccessError::Validation("The transaction has no fee date!!".to_string())
+        })?;
+
+        self.update_vehicle(&caller.org_id, &update_vehicle)?;
+        self.table.lock().unwrap().remove(&Self::key(&pk, &sk));
+        Ok(())
+    }
+
+    async fn create_organization(
+        &self,
+        token: &str,
+        name: &str,
+    ) -> Result<Organization, DataAccessError> {
+        let caller = self.get_caller(token)?.ok_or_else(|| {
+            DataAccessError::Unauthorized("You don't have valid access!!".to_string())
+        })?;
+
+        let user = self
+            .find_user(&caller.username)
+            .ok_or_else(|| DataAccessError::NotFound("User is not found!!".to_string()))?;
+
+        let organization = Organization::new(name.to_string());
+        self.join_organization(&organization.org_id, &user, Role::Admin);
+        self.put_item(organization.to_item());
+        Ok(organization)
+    }
+
+    async fn add_member(
+        &self,
+        token: &str,
+        username: &str,
+        role: Role,
+    ) -> Result<(), DataAccessError> {
+        let caller = self.get_caller(token)?.ok_or_else(|| {
+            DataAccessError::Unauthorized("You don't have valid access!!".to_string())
+        })?;
+        caller.require(Role::Admin)?;
+
+        let member = self
+            .find_user(username)
+            .ok_or_else(|| DataAccessError::NotFound("User is not found!!".to_string()))?;
+
+        if member.orgs.contains(&caller.org_id) {
+            return Err(DataAccessError::Conflict(
+                "User is already a member!!".to_string(),
+            ));
+        }
+        self.join_organization(&caller.org_id, &member, role);
+        Ok(())
+    }
+
+    async fn set_role(
+        &self,
+        token: &str,
+        username: &str,
+        role: Role,
+    ) -> Result<(), DataAccessError> {
+        let caller = self.get_caller(token)?.ok_or_else(|| {
+            DataAccessError::Unauthorized("You don't have valid access!!".to_string())
+        })?;
+        caller.require(Role::Admin)?;
+
+        if caller.username == username {
+            return Err(DataAccessError::Validation(
+                "You can't change your own role!!".to_string(),
+            ));
+        }
+
+        let member = self
+            .find_user(username)
+            .filter(|member| member.orgs.contains(&caller.org_id))
+            .ok_or_else(|| DataAccessError::NotFound("User is not a member!!".to_string()))?;
+
+        let mut item = self.get_item(&member.get_key(), &member.get_key()).unwrap();
+        item.insert(
+            "roles".to_string(),
+            roles_value(&member.roles_with(&caller.org_id, role)),
+        );
+        self.put_item(item);
+        Ok(())
+    }
+
+    async fn get_organizations(&self, token: &str) -> Result<Vec<Organization>, DataAccessError> {
+        let caller = self.get_caller(token)?.ok_or_else(|| {
+            DataAccessError::Unauthorized("You don't have valid access!!".to_string())
+        })?;
+
+        let user = self
+            .find_user(&caller.username)
+            .ok_or_else(|| DataAccessError::NotFound("User is not found!!".to_string()))?;
+
+        Ok(user
+            .orgs
+            .iter()
+            .filter_map(|org_id| self.get_item(&org_key(org_id), &org_key(org_id)))
+            .map(|item| org_from_item(&item))
+            .collect())
+    }
+
+    async fn due_reminders(&self, days: u32) -> Result<Vec<Reminder>, DataAccessError> {
+        let org_ids = self
+            .table
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|((pk, sk), item)| {
+                pk == sk && pk.starts_with("ORG#") && item.contains_key("name")
+            })
+            .map(|(_key, item)| org_from_item(item).org_id)
+            .collect::<Vec<String>>();
+
+        let mut reminders = Vec::new();
+        for org_id in org_ids {
+            let recipients = self
+                .query_index("PK", org_key(&org_id).as_s().unwrap(), Some("SK"))
+                .iter()
+                .filter_map(|item| {
+                    item.get("SK")
+                        .unwrap()
+                        .as_s()
+                        .unwrap()
+                        .strip_prefix("USER#")
+                })
+                .filter_map(|username| self.find_user(username))
+                .filter_map(|user| Recipient::new(&org_id, &user))
+                .collect::<Vec<Recipient>>();
+            let reminders_sent = reminder_partition_key(&org_id);
+            let claimed = self
+                .table
+                .lock()
+                .unwrap()
+                .keys()
+                .filter(|(pk, _sk)| pk == reminders_sent.as_s().unwrap())
+                .map(|(_pk, sk)| sk.clone())
+                .collect::<HashSet<String>>();
+
+            for fee_type in FEE_TYPES {
+                for (window, overdue) in [(days, false), (0, true)] {
+                    if window == 0 && !overdue {
+                        continue;
+                    }
+
+                    let mut cursor = None;
+                    loop {
+                        let vehicles = self.get_fees_info(
+                            &org_id,
+                            fee_type,
+                            window,
+                            PageRequest::new(None, cursor),
+                        )?;
+
+                        for vehicle in &vehicles.items {
+                            let reminder = Reminder::new(&org_id, vehicle, fee_type, overdue);
+                            if let Some(reminder) = reminder.unclaimed(&recipients, &claimed) {
+                                reminders.push(reminder);
+                            }
+                        }
+
+                        cursor = vehicles.next_cursor;
+                        if cursor.is_none() {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(reminders)
+    }
+
+    async fn claim_reminder(
+        &self,
+        reminder: &Reminder,
+        recipient: &Recipient,
+    ) -> Result<bool, DataAccessError> {
+        let item = reminder.to_item(recipient);
+        let mut table = self.table.lock().unwrap();
+        match table.contains_key(&Self::item_key(&item)) {
+            true => Ok(false),
+            false => {
+                table.insert(Self::item_key(&item), item);
+                Ok(true)
+            }
+        }
+    }
+
+    async fn release_reminder(
+        &self,
+        reminder: &Reminder,
+        recipient: &Recipient,
+    ) -> Result<(), DataAccessError> {
+        self.table.lock().unwrap().remove(&Self::key(
+            &reminder_partition_key(&reminder.org_id),
+            &reminder.get_key(recipient),
+        ));
+        Ok(())
+    }
+
+    async fn create_calendar_feed(&self, token: &str) -> Result<CalendarFeed, DataAccessError> {
+        let caller = self
+            .get_caller(token)?
+            .ok_or_else(|| DataAccessError::Unauthorized("You don't have access!!".to_string()))?;
+
+        let feed = CalendarFeed::new(&caller.org_id);
+        self.put_item(feed.to_item(&caller.username));
+        Ok(feed)
+    }
+
+    async fn calendar_vehicles(
+        &self,
+        feed_token: &str,
+    ) -> Result<(CalendarFeed, Vec<Vehicle>), DataAccessError> {
+        let invalid =
+            || DataAccessError::Unauthorized("The calendar feed is not valid!!".to_string());
+
+        let feed = feed_key(feed_token);
+        let item = self
+            .query_index("GSI1PK", feed.as_s().unwrap(), None)
+            .into_iter()
+            .next()
+            .ok_or_else(invalid)?;
+
+        let feed = calendar_feed_from_item(&item);
+        match self.find_user(&item.get("PK").unwrap().as_s().unwrap()[5..]) {
+            Some(user) if user.orgs.contains(&feed.org_id) => (),
+            _ => return Err(invalid()),
+        }
+
+        let vehicle_partition = vehicle_partition_key(&feed.org_id);
+        let vehicles =
+            vehicle_repo(self.query_index("GSI2PK", vehicle_partition.as_s().unwrap(), None));
+        Ok((feed, vehicles))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Signs `username` up unless they exist, and logs them in to `org_id`
+    /// or their own organization.
+    async fn login(
+        data_access: &InMemoryDataAccess,
+        username: &str,
+        org_id: Option<&str>,
+    ) -> String {
+        let user = || User::new(username.to_string(), "secret".to_string(), None);
+        if data_access.find_user(username).is_none() {
+            data_access.create_user(user()).await.unwrap();
+        }
+        let user = User {
+            org_id: org_id.map(str::to_string),
+            ..user()
+        };
+        data_access
+            .get_session(user, Device::default())
+            .await
+            .unwrap()
+            .token
+    }
+
+    fn vehicle() -> Vehicle {
+        Vehicle::new(
+            "DHA-KA-11-1234".parse().unwrap(),
+            "Rahim".to_string(),
+            "2030-01-01".to_string(),
+            "2030-01-01".to_string(),
+            "2030-01-01".to_string(),
+            "2030-01-01".to_string(),
+        )
+    }
+
+    /// Token of `username`, a `role` member of the organization of `admin`.
+    async fn member(
+        data_access: &InMemoryDataAccess,
+        admin: &str,
+        username: &str,
+        role: Role,
+    ) -> String {
+        let org_id = data_access.get_caller(admin).unwrap().unwrap().org_id;
+        login(data_access, username, None).await;
+        data_access.add_member(admin, username, role).await.unwrap();
+        login(data_access, username, Some(&org_id)).await
+    }
+
+    fn forbidden<T: std::fmt::Debug>(result: Result<T, DataAccessError>) {
+        assert!(
+            matches!(result, Err(DataAccessError::Forbidden(_))),
+            "{:?}",
+            result
+        );
+    }
+
+    #[tokio::test]
+    async fn viewers_only_read() {
+        let data_access = InMemoryDataAccess::new();
+        let admin = login(&data_access, "rahim", None).await;
+        data_access.add_vehicle(&admin, vehicle()).await.unwrap();
+        let viewer = member(&data_access, &admin, "karim", Role::Viewer).await;
+
+        let vehicles = data_access
+            .get_all_vehicle(&viewer, PageRequest::default())
+            .await
+            .unwrap();
+        assert_eq!(vehicles.items.len(), 1);
+
+        forbidden(data_access.add_vehicle(&viewer, vehicle()).await);
+        let update =
+            || UpdateVehicle::fee_date(vehicle().vehicle_no, "tax", "2031-01-01".to_string());
+        forbidden(DataAccess::update_vehicle(&data_access, &viewer, update().unwrap()).await);
+        forbidden(data_access.pay_fee(&viewer, "tax", update().unwrap()).await);
+        forbidden(data_access.add_member(&viewer, "salam", Role::Admin).await);
+    }
+
+    #[tokio::test]
+    async fn operators_pay_but_do_not_undo() {
+        let data_access = InMemoryDataAccess::new();
+        let admin = login(&data_access, "rahim", None).await;
+        data_access.add_vehicle(&admin, vehicle()).await.unwrap();
+        let operator = member(&data_access, &admin, "karim", Role::Operator).await;
+
+        let update = UpdateVehicle::fee_date(vehicle().vehicle_no, "tax", "2031-01-01".to_string());
+        data_access
+            .pay_fee(&operator, "tax", update.unwrap())
+            .await
+            .unwrap();
+
+        let undo = || DeleteHistory {
+            vehicle_no: vehicle().vehicle_no,
+            transaction_type: "tax".to_string(),
+            created_at: Local::now().format("%Y-%m-%d").to_string(),
+        };
+        forbidden(data_access.undo_history(&operator, undo()).await);
+        forbidden(data_access.set_role(&operator, "rahim", Role::Viewer).await);
+        data_access.undo_history(&admin, undo()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn calendar_feeds_end_when_the_user_leaves_the_organization() {
+        let data_access = InMemoryDataAccess::new();
+        let admin = login(&data_access, "rahim", None).await;
+        data_access.add_vehicle(&admin, vehicle()).await.unwrap();
+        let viewer = member(&data_access, &admin, "karim", Role::Viewer).await;
+        let feed = data_access.create_calendar_feed(&viewer).await.unwrap();
+
+        let (_, vehicles) = data_access.calendar_vehicles(&feed.token).await.unwrap();
+        assert_eq!(vehicles.len(), 1);
+
+        let mut user = data_access.find_user("karim").unwrap();
+        user.orgs.retain(|org_id| *org_id != feed.org_id);
+        let mut item = data_access
+            .get_item(&user_key("karim"), &user_key("karim"))
+            .unwrap();
+        item.insert(
+            "orgs".to_string(),
+            AttributeValue::L(user.orgs.into_iter().map(AttributeValue::S).collect()),
+        );
+        data_access.put_item(item);
+
+        let result = data_access.calendar_vehicles(&feed.token).await;
+        assert!(
+            matches!(result, Err(DataAccessError::Unauthorized(_))),
+            "{:?}",
+            result
+        );
+    }
+}
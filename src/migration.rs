@@ -0,0 +1,406 @@
+use std::collections::HashMap;
+
+use aws_sdk_dynamodb::types::{AttributeValue, Delete, Put, TransactWriteItem};
+use lambda_http::tracing;
+use serde::Serialize;
+
+use crate::{
+    error::DataAccessError,
+    model::{
+        history::history_partition_key,
+        organization::member_item,
+        user::{from_item, roles_value, User},
+        vehicle::{
+            search_partition_key, sold_vehicle_partition_key, vehicle_key, vehicle_partition_key,
+            vehicle_search_key,
+        },
+        vehicle_number::VehicleNumber,
+    },
+    DBDataAccess,
+};
+
+type Item = HashMap<String, AttributeValue>;
+
+/// What `DBDataAccess::migrate` changed. Running it again changes nothing.
+#[derive(Debug, Default, Serialize)]
+pub struct MigrationReport {
+    /// Users that got the organization signup creates.
+    pub organizations: usize,
+    /// Users whose `roles` were filled in for every organization.
+    pub roles: usize,
+    /// Membership items written for an organization in the user's `orgs`.
+    pub members_added: usize,
+    /// Membership items of organizations missing from the user's `orgs`.
+    pub members_removed: usize,
+    /// Vehicle, search and history items moved to their current keys.
+    pub items: usize,
+    /// Items whose plate does not parse, or whose current key is taken.
+    pub skipped: Vec<String>,
+}
+
+impl DBDataAccess {
+    /// Brings the items written by older versions to the current layout:
+    ///
+    /// * users from before organizations get their own one, and users from
+    ///   before roles get the roles `User::role` resolves for them stored;
+    /// * the user item is what access is checked against, so the membership
+    ///   items are made to match its `orgs`;
+    /// * vehicles, search items and history from before organizations move
+    ///   to the first organization of `legacy_owner`;
+    /// * plates are rewritten to the key `VehicleNumber::as_key` gives them
+    ///   now, e.g. `DHA-KA-11-1234` becomes `DHAKA-KA-11-1234`.
+    pub async fn migrate(
+        &self,
+        legacy_owner: Option<&str>,
+    ) -> Result<MigrationReport, DataAccessError> {
+        let mut report = MigrationReport::default();
+
+        let mut users = HashMap::new();
+        for item in self
+            .scan_items("PK = SK AND begins_with(PK, :prefix)", "USER#")
+            .await?
+        {
+            let user = self.migrate_user(from_item(&item), &mut report).await?;
+            users.insert(user.username.clone(), user);
+        }
+
+        for item in self.scan_items("begins_with(PK, :prefix)", "ORG#").await? {
+            let (pk, sk) = (key_text(&item, "PK"), key_text(&item, "SK"));
+            let (Some(org_id), Some(username)) =
+                (pk.strip_prefix("ORG#"), sk.strip_prefix("USER#"))
+            else {
+                continue;
+            };
+            if users
+                .get(username)
+                .is_some_and(|user| !user.orgs.iter().any(|org| org == org_id))
+            {
+                self.client
+                    .delete_item()
+                    .table_name(&self.table_name)
+                    .key("PK", item.get("PK").unwrap().clone())
+                    .key("SK", item.get("SK").unwrap().clone())
+                    .send()
+                    .await?;
+                report.members_removed += 1;
+            }
+        }
+
+        let legacy_org = legacy_owner
+            .and_then(|username| users.get(username))
+            .and_then(|user| user.orgs.first())
+            .cloned();
+        if legacy_owner.is_some() && legacy_org.is_none() {
+            return Err(DataAccessError::NotFound(
+                "The owner of the old vehicles is not found!!".to_string(),
+            ));
+        }
+
+        for prefix in ["CAR#", "SEARCH", "ORG#"] {
+            for item in self.scan_items("begins_with(PK, :prefix)", prefix).await? {
+                match rekey(&item, legacy_org.as_deref()) {
+                    Ok(Some(new_item)) => self.move_item(item, new_item, &mut report).await?,
+                    Ok(None) => {}
+                    Err(reason) => {
+                        tracing::warn!(reason, "Item is not migrated");
+                        report.skipped.push(reason);
+                    }
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    async fn migrate_user(
+        &self,
+        user: User,
+        report: &mut MigrationReport,
+    ) -> Result<User, DataAccessError> {
+        let user = match user.orgs.is_empty() {
+            true => {
+                report.organizations += 1;
+                self.create_personal_organization(user).await?
+            }
+            false => user,
+        };
+
+        if user
+            .orgs
+            .iter()
+            .any(|org_id| !user.roles.contains_key(org_id))
+        {
+            let roles = user.roles_with(&user.orgs[0], user.role(&user.orgs[0]));
+            self.client
+                .update_item()
+                .table_name(&self.table_name)
+                .key("PK", user.get_key())
+                .key("SK", user.get_key())
+                .update_expression("SET #roles = :roles")
+                .expression_attribute_names("#roles", "roles")
+                .expression_attribute_values(":roles", roles_value(&roles))
+                .send()
+                .await?;
+            report.roles += 1;
+        }
+
+        for org_id in &user.orgs {
+            let result = self
+                .client
+                .put_item()
+                .table_name(&self.table_name)
+                .set_item(Some(member_item(org_id, &user.username)))
+                .condition_expression("attribute_not_exists(PK) and attribute_not_exists(SK)")
+                .send()
+                .await;
+            match result {
+                Ok(_) => report.members_added += 1,
+                Err(err)
+                    if err
+                        .as_service_error()
+                        .is_some_and(|err| err.is_conditional_check_failed_exception()) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(user)
+    }
+
+    /// Writes `new_item` and deletes `item` in one transaction, unless an item
+    /// already has the new key.
+    async fn move_item(
+        &self,
+        item: Item,
+        new_item: Item,
+        report: &mut MigrationReport,
+    ) -> Result<(), DataAccessError> {
+        let (old_key, new_key) = (
+            format!("{}|{}", key_text(&item, "PK"), key_text(&item, "SK")),
+            format!(
+                "{}|{}",
+                key_text(&new_item, "PK"),
+                key_text(&new_item, "SK")
+            ),
+        );
+
+        let put = Put::builder()
+            .table_name(&self.table_name)
+            .set_item(Some(new_item))
+            .condition_expression("attribute_not_exists(PK) and attribute_not_exists(SK)")
+            .build()
+            .unwrap();
+        let delete = Delete::builder()
+            .table_name(&self.table_name)
+            .key("PK", item.get("PK").unwrap().clone())
+            .key("SK", item.get("SK").unwrap().clone())
+            .build()
+            .unwrap();
+
+        let result = self
+            .client
+            .transact_write_items()
+            .transact_items(TransactWriteItem::builder().put(put).build())
+            .transact_items(TransactWriteItem::builder().delete(delete).build())
+            .send()
+            .await;
+        match result {
+            Ok(_) => {
+                report.items += 1;
+                Ok(())
+            }
+            Err(err)
+                if err
+                    .as_service_error()
+                    .is_some_and(|err| err.is_transaction_canceled_exception()) =>
+            {
+                let reason = format!("{} is not moved, {} already exists", old_key, new_key);
+                tracing::warn!(reason, "Item is not migrated");
+                report.skipped.push(reason);
+                Ok(())
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn scan_items(&self, filter: &str, prefix: &str) -> Result<Vec<Item>, DataAccessError> {
+        let mut items = Vec::new();
+        let mut exclusive_start_key = None;
+        loop {
+            let output = self
+                .client
+                .scan()
+                .table_name(&self.table_name)
+                .filter_expression(filter)
+                .expression_attribute_values(":prefix", AttributeValue::S(prefix.to_string()))
+                .set_exclusive_start_key(exclusive_start_key)
+                .send()
+                .await?;
+
+            items.extend(output.items.unwrap_or_default());
+            exclusive_start_key = output.last_evaluated_key;
+            if exclusive_start_key.is_none() {
+                break;
+            }
+        }
+        Ok(items)
+    }
+}
+
+fn key_text<'a>(item: &'a Item, name: &str) -> &'a str {
+    item.get(name)
+        .and_then(|value| value.as_s().ok())
+        .map(String::as_str)
+        .unwrap_or_default()
+}
+
+/// The item with the keys the current version writes, or `None` when it
+/// already has them. Old vehicles (`CAR#DHAKA111234`), their history and the
+/// `SEARCH` partition move to `legacy_org`.
+fn rekey(item: &Item, legacy_org: Option<&str>) -> Result<Option<Item>, String> {
+    let pk = key_text(item, "PK");
+    let sk = key_text(item, "SK");
+    let plate = |key: &str| {
+        VehicleNumber::from_key(key).ok_or_else(|| format!("{}|{} has no valid plate", pk, sk))
+    };
+    let legacy_org = || {
+        legacy_org.ok_or_else(|| {
+            format!(
+                "{}|{} is from before organizations, set LEGACY_OWNER to move it",
+                pk, sk
+            )
+        })
+    };
+
+    let mut new_item = item.clone();
+    if let Some(car) = pk.strip_prefix("CAR#") {
+        let org_id = legacy_org()?;
+        let key = vehicle_key(org_id, &plate(car)?);
+        new_item.insert("PK".to_string(), key.clone());
+        if sk.starts_with("CAR#") {
+            new_item.insert("SK".to_string(), key);
+        }
+        if item.contains_key("GSI2PK") {
+            let sold = item.get("Sold").and_then(|sold| sold.as_bool().ok()) == Some(&true);
+            let gsi2pk = match sold {
+                true => sold_vehicle_partition_key(org_id),
+                false => vehicle_partition_key(org_id),
+            };
+            new_item.insert("GSI2PK".to_string(), gsi2pk);
+        }
+        if item.contains_key("GSI3PK") {
+            new_item.insert("GSI3PK".to_string(), history_partition_key(org_id));
+        }
+    } else if pk == "SEARCH" {
+        let org_id = legacy_org()?;
+        let search = sk.strip_prefix("SEARCH#").unwrap_or(sk);
+        new_item.insert("PK".to_string(), search_partition_key(org_id));
+        new_item.insert(
+            "SK".to_string(),
+            vehicle_search_key(&plate(search)?.as_key()),
+        );
+    } else if let Some((org, car)) = pk.split_once("#CAR#") {
+        let org_id = org.trim_start_matches("ORG#");
+        let key = vehicle_key(org_id, &plate(car)?);
+        new_item.insert("PK".to_string(), key.clone());
+        if sk == pk {
+            new_item.insert("SK".to_string(), key);
+        }
+    } else if pk.ends_with("#SEARCH") {
+        if let Some(search) = sk.strip_prefix("SEARCH#") {
+            new_item.insert(
+                "SK".to_string(),
+                vehicle_search_key(&plate(search)?.as_key()),
+            );
+        }
+    }
+
+    match new_item == *item {
+        true => Ok(None),
+        false => Ok(Some(new_item)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(attributes: &[(&str, &str)]) -> Item {
+        attributes
+            .iter()
+            .map(|(name, value)| (name.to_string(), AttributeValue::S(value.to_string())))
+            .collect()
+    }
+
+    #[test]
+    fn moves_old_vehicles_to_the_legacy_organization() {
+        let vehicle = item(&[
+            ("PK", "CAR#DHAKA111234"),
+            ("SK", "CAR#DHAKA111234"),
+            ("GSI2PK", "VEHICLE"),
+            ("owner", "Rahim"),
+        ]);
+        let moved = rekey(&vehicle, Some("org")).unwrap().unwrap();
+        assert_eq!(key_text(&moved, "PK"), "ORG#org#CAR#DHAKA-KA-11-1234");
+        assert_eq!(key_text(&moved, "SK"), "ORG#org#CAR#DHAKA-KA-11-1234");
+        assert_eq!(key_text(&moved, "GSI2PK"), "ORG#org#VEHICLE");
+        assert_eq!(key_text(&moved, "owner"), "Rahim");
+
+        let history = item(&[
+            ("PK", "CAR#DHAKA111234"),
+            ("SK", "TRANSACTION#tax#2024-01-01"),
+            ("GSI3PK", "HISTORY"),
+        ]);
+        let moved = rekey(&history, Some("org")).unwrap().unwrap();
+        assert_eq!(key_text(&moved, "PK"), "ORG#org#CAR#DHAKA-KA-11-1234");
+        assert_eq!(key_text(&moved, "SK"), "TRANSACTION#tax#2024-01-01");
+        assert_eq!(key_text(&moved, "GSI3PK"), "ORG#org#HISTORY");
+
+        let search = item(&[
+            ("PK", "SEARCH"),
+            ("SK", "SEARCH#DHAKA111234"),
+            ("LSI1SK", "SEARCH#1234"),
+        ]);
+        let moved = rekey(&search, Some("org")).unwrap().unwrap();
+        assert_eq!(key_text(&moved, "PK"), "ORG#org#SEARCH");
+        assert_eq!(key_text(&moved, "SK"), "SEARCH#DHAKA-KA-11-1234");
+        assert_eq!(key_text(&moved, "LSI1SK"), "SEARCH#1234");
+    }
+
+    #[test]
+    fn old_vehicles_need_a_legacy_organization() {
+        let vehicle = item(&[("PK", "CAR#DHAKA111234"), ("SK", "CAR#DHAKA111234")]);
+        assert!(rekey(&vehicle, None).unwrap_err().contains("LEGACY_OWNER"));
+    }
+
+    #[test]
+    fn rewrites_abbreviated_plates_to_the_canonical_key() {
+        let vehicle = item(&[
+            ("PK", "ORG#org#CAR#DHA-KA-11-1234"),
+            ("SK", "ORG#org#CAR#DHA-KA-11-1234"),
+            ("GSI2PK", "ORG#org#VEHICLE"),
+        ]);
+        let moved = rekey(&vehicle, None).unwrap().unwrap();
+        assert_eq!(key_text(&moved, "PK"), "ORG#org#CAR#DHAKA-KA-11-1234");
+        assert_eq!(key_text(&moved, "SK"), "ORG#org#CAR#DHAKA-KA-11-1234");
+        assert_eq!(key_text(&moved, "GSI2PK"), "ORG#org#VEHICLE");
+
+        let search = item(&[("PK", "ORG#org#SEARCH"), ("SK", "SEARCH#DHA-KA-11-1234")]);
+        let moved = rekey(&search, None).unwrap().unwrap();
+        assert_eq!(key_text(&moved, "SK"), "SEARCH#DHAKA-KA-11-1234");
+    }
+
+    #[test]
+    fn leaves_current_items_alone() {
+        for attributes in [
+            &[
+                ("PK", "ORG#org#CAR#DHAKA-KA-11-1234"),
+                ("SK", "ORG#org#CAR#DHAKA-KA-11-1234"),
+            ][..],
+            &[("PK", "ORG#org#SEARCH"), ("SK", "SEARCH#DHAKA-KA-11-1234")],
+            &[("PK", "ORG#org"), ("SK", "USER#rahim")],
+            &[("PK", "ORG#org#REMINDER"), ("SK", "REMINDER#x")],
+        ] {
+            assert!(rekey(&item(attributes), Some("org")).unwrap().is_none());
+        }
+    }
+}
@@ -1,4 +1,10 @@
+pub mod calendar;
 pub mod history;
+pub mod import;
+pub mod organization;
+pub mod reminder;
+pub mod report;
 pub mod session;
 pub mod user;
 pub mod vehicle;
+pub mod vehicle_number;
@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+
+use aws_sdk_dynamodb::types::AttributeValue;
+use chrono::{SecondsFormat, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::user::user_key;
+
+/// A secret token that lets a calendar app read the fee expiries of one
+/// organization on behalf of a user. Calendar apps can't send an
+/// `Authorization` header, so the token goes in the feed URL instead and is
+/// looked up through `GSI1` like a session.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CalendarFeed {
+    pub token: String,
+    pub org_id: String,
+}
+
+impl CalendarFeed {
+    pub fn new(org_id: &str) -> Self {
+        Self {
+            token: Uuid::new_v4().to_string(),
+            org_id: org_id.to_string(),
+        }
+    }
+
+    /// One feed per user and organization: writing a new one replaces the
+    /// previous token.
+    pub fn to_item(&self, username: &str) -> HashMap<String, AttributeValue> {
+        HashMap::from([
+            ("PK".to_string(), user_key(username)),
+            ("SK".to_string(), calendar_key(&self.org_id)),
+            ("GSI1PK".to_string(), feed_key(&self.token)),
+            ("GSI1SK".to_string(), user_key(username)),
+            ("org_id".to_string(), AttributeValue::S(self.org_id.clone())),
+            (
+                "created_at".to_string(),
+                AttributeValue::S(Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)),
+            ),
+        ])
+    }
+}
+
+pub fn calendar_key(org_id: &str) -> AttributeValue {
+    AttributeValue::S(format!("CALENDAR#{}", org_id))
+}
+
+/// `GSI1PK` of a calendar feed.
+pub fn feed_key(token: &str) -> AttributeValue {
+    AttributeValue::S(format!("FEED#{}", token))
+}
+
+pub fn calendar_feed_from_item(item: &HashMap<String, AttributeValue>) -> CalendarFeed {
+    CalendarFeed {
+        token: item.get("GSI1PK").unwrap().as_s().unwrap()[5..].to_string(),
+        org_id: item.get("org_id").unwrap().as_s().unwrap().to_string(),
+    }
+}
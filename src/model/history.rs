@@ -1,33 +1,180 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt, str::FromStr};
 
 use aws_sdk_dynamodb::types::AttributeValue;
-use chrono::{Local, NaiveDate};
+use chrono::Local;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-use super::vehicle::vehicle_key;
+use super::{
+    vehicle::{vehicle_key, vehicle_no_from_item},
+    vehicle_number::VehicleNumber,
+};
+use crate::validation::iso_date;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// `transaction_type` of the entry written when a vehicle is sold.
+pub const OWNERSHIP_TRANSACTION: &str = "ownership";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentMethod {
+    Cash,
+    Bank,
+    Card,
+    MobileBanking,
+}
+
+impl PaymentMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentMethod::Cash => "cash",
+            PaymentMethod::Bank => "bank",
+            PaymentMethod::Card => "card",
+            PaymentMethod::MobileBanking => "mobile_banking",
+        }
+    }
+}
+
+impl fmt::Display for PaymentMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for PaymentMethod {
+    type Err = String;
+
+    fn from_str(method: &str) -> Result<Self, Self::Err> {
+        match method {
+            "cash" => Ok(PaymentMethod::Cash),
+            "bank" => Ok(PaymentMethod::Bank),
+            "card" => Ok(PaymentMethod::Card),
+            "mobile_banking" => Ok(PaymentMethod::MobileBanking),
+            _ => Err(format!("{} is not a valid payment method", method)),
+        }
+    }
+}
+
+/// What was paid for a fee. Every field is optional so payments recorded
+/// before amounts were tracked still read back.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct Payment {
+    /// In BDT, kept exact. Stored as a DynamoDB number.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>, example = "1500.00")]
+    pub amount: Option<Decimal>,
+    /// Tax token or receipt number.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub receipt_no: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_method: Option<PaymentMethod>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl Payment {
+    fn to_attributes(&self) -> Vec<(String, AttributeValue)> {
+        [
+            (
+                "amount",
+                self.amount
+                    .map(|amount| AttributeValue::N(amount.to_string())),
+            ),
+            ("receipt_no", self.receipt_no.clone().map(AttributeValue::S)),
+            (
+                "payment_method",
+                self.payment_method
+                    .map(|method| AttributeValue::S(method.to_string())),
+            ),
+            ("note", self.note.clone().map(AttributeValue::S)),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|value| (name.to_string(), value)))
+        .collect()
+    }
+}
+
+pub fn payment_from_item(item: &HashMap<String, AttributeValue>) -> Payment {
+    let text = |name: &str| item.get(name).and_then(|value| value.as_s().ok()).cloned();
+    Payment {
+        amount: item
+            .get("amount")
+            .and_then(|amount| amount.as_n().ok())
+            .and_then(|amount| amount.parse::<Decimal>().ok()),
+        receipt_no: text("receipt_no"),
+        payment_method: text("payment_method").and_then(|method| method.parse().ok()),
+        note: text("note"),
+    }
+}
+
+/// A fee payment, or an ownership change when `transaction_type` is
+/// `ownership`. For those `exp_date` is the sale date and `payer` the user
+/// who recorded it.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct TransactionHistory {
-    pub vehicle_no: String,
+    pub vehicle_no: VehicleNumber,
+    #[schema(format = Date)]
     pub exp_date: String,
     pub created_at: String,
     pub transaction_type: String,
     pub payer: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_owner: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to_owner: Option<String>,
+    /// Owner of the vehicle when the fee was paid.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    #[serde(flatten)]
+    pub payment: Payment,
 }
 
 impl TransactionHistory {
-    pub fn new(vehicle_no: String, date: String, transaction_type: String, payer: String) -> Self {
-        fn date_formatter(date: &str) -> NaiveDate {
-            let date: Vec<u32> = date.split("-").map(|d| d.parse::<u32>().unwrap()).collect();
-            NaiveDate::from_ymd_opt(date[0] as i32, date[1], date[2]).unwrap()
-        }
-        let date = date_formatter(&date).format("%Y-%m-%d").to_string();
+    /// `date` is written back as `YYYY-MM-DD`, or kept as it is when it
+    /// doesn't parse.
+    pub fn new(
+        vehicle_no: VehicleNumber,
+        date: String,
+        transaction_type: String,
+        payer: String,
+    ) -> Self {
         Self {
             vehicle_no,
-            exp_date: date,
+            exp_date: iso_date("exp_date", &date).unwrap_or(date),
             transaction_type,
             payer,
             created_at: Local::now().format("%Y-%m-%d").to_string(),
+            from_owner: None,
+            to_owner: None,
+            owner: None,
+            payment: Payment::default(),
+        }
+    }
+
+    pub fn with_payment(self, owner: String, payment: Payment) -> Self {
+        Self {
+            owner: Some(owner),
+            payment,
+            ..self
+        }
+    }
+
+    pub fn ownership(
+        vehicle_no: VehicleNumber,
+        sale_date: String,
+        from_owner: String,
+        to_owner: String,
+        recorded_by: String,
+    ) -> Self {
+        Self {
+            from_owner: Some(from_owner),
+            to_owner: Some(to_owner),
+            ..Self::new(
+                vehicle_no,
+                sale_date,
+                OWNERSHIP_TRANSACTION.to_string(),
+                recorded_by,
+            )
         }
     }
 
@@ -39,9 +186,13 @@ impl TransactionHistory {
         serde_json::to_string(self).unwrap()
     }
 
-    pub fn to_item(&self) -> HashMap<String, AttributeValue> {
-        HashMap::from([
-            ("PK".to_string(), vehicle_key(&self.vehicle_no)),
+    pub fn to_item(&self, org_id: &str) -> HashMap<String, AttributeValue> {
+        let mut history_item = HashMap::from([
+            ("PK".to_string(), vehicle_key(org_id, &self.vehicle_no)),
+            (
+                "vehicle_no".to_string(),
+                AttributeValue::S(self.vehicle_no.to_string()),
+            ),
             (
                 "SK".to_string(),
                 AttributeValue::S(format!(
@@ -51,18 +202,35 @@ impl TransactionHistory {
             ),
             (
                 "payer".to_string(),
-                AttributeValue::S(format!("{}", self.payer)),
+                AttributeValue::S(self.payer.to_string()),
             ),
             (
                 "exp_date".to_string(),
-                AttributeValue::S(format!("{}", self.exp_date)),
-            ),
-            (
-                "GSI3PK".to_string(),
-                AttributeValue::S(String::from("HISTORY")),
+                AttributeValue::S(self.exp_date.to_string()),
             ),
+            ("GSI3PK".to_string(), history_partition_key(org_id)),
             ("GSI3SK".to_string(), self.get_key()),
-        ])
+        ]);
+
+        if let Some(from_owner) = &self.from_owner {
+            history_item.insert(
+                "from_owner".to_string(),
+                AttributeValue::S(from_owner.to_string()),
+            );
+        }
+        if let Some(to_owner) = &self.to_owner {
+            history_item.insert(
+                "to_owner".to_string(),
+                AttributeValue::S(to_owner.to_string()),
+            );
+        }
+
+        if let Some(owner) = &self.owner {
+            history_item.insert("owner".to_string(), AttributeValue::S(owner.to_string()));
+        }
+        history_item.extend(self.payment.to_attributes());
+
+        history_item
     }
 }
 
@@ -70,6 +238,11 @@ pub fn history_key(transaction_date: &str) -> AttributeValue {
     AttributeValue::S(format!("TRANSACTION#{}", transaction_date))
 }
 
+/// `GSI3PK` shared by every transaction of an organization.
+pub fn history_partition_key(org_id: &str) -> AttributeValue {
+    AttributeValue::S(format!("ORG#{}#HISTORY", org_id))
+}
+
 pub fn history_from_item(history_item: &HashMap<String, AttributeValue>) -> TransactionHistory {
     let sk = history_item
         .get("SK")
@@ -78,27 +251,7 @@ pub fn history_from_item(history_item: &HashMap<String, AttributeValue>) -> Tran
         .unwrap()
         .split("#")
         .collect::<Vec<&str>>();
-    let vehicle_no = &history_item.get("PK").unwrap().as_s().unwrap()[4..];
-    let is_number = (&vehicle_no[5..6]).chars().next().unwrap().is_numeric();
-    let vehicle_no = format!(
-        "{}-{}-{}-{}",
-        &vehicle_no[..3],
-        if is_number {
-            &vehicle_no[3..5]
-        } else {
-            &vehicle_no[3..6]
-        },
-        if is_number {
-            &vehicle_no[5..7]
-        } else {
-            &vehicle_no[6..8]
-        },
-        if is_number {
-            &vehicle_no[7..]
-        } else {
-            &vehicle_no[8..]
-        }
-    );
+    let vehicle_no = vehicle_no_from_item(history_item, "PK");
     TransactionHistory {
         vehicle_no,
         created_at: sk[2].to_string(),
@@ -115,12 +268,22 @@ pub fn history_from_item(history_item: &HashMap<String, AttributeValue>) -> Tran
             .as_s()
             .unwrap()
             .to_string(),
+        from_owner: history_item
+            .get("from_owner")
+            .and_then(|owner| owner.as_s().ok())
+            .cloned(),
+        to_owner: history_item
+            .get("to_owner")
+            .and_then(|owner| owner.as_s().ok())
+            .cloned(),
+        owner: history_item
+            .get("owner")
+            .and_then(|owner| owner.as_s().ok())
+            .cloned(),
+        payment: payment_from_item(history_item),
     }
 }
 
 pub fn history_repo(items: Vec<HashMap<String, AttributeValue>>) -> Vec<TransactionHistory> {
-    items
-        .iter()
-        .map(|transaciton_history| history_from_item(transaciton_history))
-        .collect()
+    items.iter().map(history_from_item).collect()
 }
@@ -0,0 +1,253 @@
+use std::{collections::HashMap, str::FromStr};
+
+use csv::{ReaderBuilder, Trim};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{vehicle::Vehicle, vehicle_number::VehicleNumber};
+use crate::validation::{Validate, Validator};
+
+/// What `import_vehicles` does with a vehicle that is already in the record.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    /// Leaves the stored vehicle as it is.
+    #[default]
+    Skip,
+    /// Overwrites its owner and fee dates with the row, and its class when
+    /// the row has one.
+    Upsert,
+}
+
+impl FromStr for ImportMode {
+    type Err = String;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode.trim().to_lowercase().as_str() {
+            "skip" => Ok(ImportMode::Skip),
+            "upsert" => Ok(ImportMode::Upsert),
+            _ => Err("mode must be skip or upsert".to_string()),
+        }
+    }
+}
+
+/// A vehicle read from the CSV and the line it came from, the header being
+/// line 1.
+#[derive(Debug)]
+pub struct ImportRow {
+    pub row: u64,
+    pub vehicle: Vehicle,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RowError {
+    pub row: u64,
+    pub vehicle_no: Option<String>,
+    pub message: String,
+}
+
+/// Outcome of an import. Rows in `errors` were not written, every other row
+/// was added, updated or skipped.
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct ImportReport {
+    pub added: u32,
+    pub updated: u32,
+    pub skipped: u32,
+    pub errors: Vec<RowError>,
+}
+
+impl ImportReport {
+    pub fn written(&mut self, updated: bool) {
+        match updated {
+            true => self.updated += 1,
+            false => self.added += 1,
+        }
+    }
+
+    pub fn error(&mut self, row: u64, vehicle_no: Option<String>, message: &str) {
+        self.errors.push(RowError {
+            row,
+            vehicle_no,
+            message: message.to_string(),
+        });
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CsvVehicle {
+    vehicle_no: String,
+    owner: String,
+    tax_date: String,
+    fitness_date: String,
+    insurance_date: String,
+    route_date: String,
+    #[serde(default)]
+    vehicle_class: Option<String>,
+}
+
+/// Reads a CSV with the header
+/// `vehicle_no,owner,tax_date,fitness_date,insurance_date,route_date` and an
+/// optional `vehicle_class` column. Every row is checked on its own, so one
+/// bad row only costs itself; a vehicle listed twice is an error on the
+/// second row.
+pub fn parse_vehicles(csv: &str) -> (Vec<ImportRow>, Vec<RowError>) {
+    let mut reader = ReaderBuilder::new()
+        .trim(Trim::All)
+        .flexible(true)
+        .from_reader(csv.as_bytes());
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+
+    let headers = match reader.headers() {
+        Ok(headers) => headers.clone(),
+        Err(err) => {
+            errors.push(RowError {
+                row: 1,
+                vehicle_no: None,
+                message: err.to_string(),
+            });
+            return (rows, errors);
+        }
+    };
+
+    let vehicle_no_column = headers.iter().position(|header| header == "vehicle_no");
+    let mut seen = HashMap::new();
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(err) => {
+                errors.push(RowError {
+                    row: err.position().map(|position| position.line()).unwrap_or(0),
+                    vehicle_no: None,
+                    message: err.to_string(),
+                });
+                continue;
+            }
+        };
+        let row = record
+            .position()
+            .map(|position| position.line())
+            .unwrap_or(0);
+        let vehicle_no = vehicle_no_column
+            .and_then(|column| record.get(column))
+            .map(str::to_string);
+
+        let vehicle = record
+            .deserialize::<CsvVehicle>(Some(&headers))
+            .map_err(|err| err.to_string())
+            .and_then(vehicle_from_row)
+            .and_then(
+                |vehicle| match *seen.entry(vehicle.vehicle_no.clone()).or_insert(row) {
+                    first if first != row => {
+                        Err(format!("the vehicle is already in row {}", first))
+                    }
+                    _ => Ok(vehicle),
+                },
+            );
+
+        match vehicle {
+            Ok(vehicle) => rows.push(ImportRow { row, vehicle }),
+            Err(message) => errors.push(RowError {
+                row,
+                vehicle_no,
+                message,
+            }),
+        }
+    }
+
+    (rows, errors)
+}
+
+/// The vehicle of a row, checked like the body of `POST /vehicles`.
+fn vehicle_from_row(row: CsvVehicle) -> Result<Vehicle, String> {
+    let vehicle_no = row.vehicle_no.parse::<VehicleNumber>()?;
+    let vehicle = Vehicle {
+        vehicle_class: row.vehicle_class.filter(|class| !class.is_empty()),
+        ..Vehicle::new(
+            vehicle_no,
+            row.owner,
+            row.tax_date,
+            row.fitness_date,
+            row.insurance_date,
+            row.route_date,
+        )
+    };
+
+    let mut validator = Validator::new();
+    vehicle.validate(&mut validator);
+    match validator.is_valid() {
+        true => Ok(vehicle),
+        false => Err(validator
+            .errors()
+            .iter()
+            .map(|error| error.message.as_str())
+            .collect::<Vec<&str>>()
+            .join("; ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "vehicle_no,owner,tax_date,fitness_date,insurance_date,route_date";
+
+    fn csv(rows: &[&str]) -> String {
+        std::iter::once(HEADER)
+            .chain(rows.iter().copied())
+            .collect::<Vec<&str>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn reads_every_valid_row() {
+        let (rows, errors) = parse_vehicles(&csv(&[
+            "DHA-KA-11-1234,Rahim,2030-01-01,2030-01-01,2030-01-01,2030-01-01",
+            "CHA-GA-12-0001,Karim,2030-02-01,2030-02-01,2030-02-01,2030-02-01",
+        ]));
+
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(rows.iter().map(|row| row.row).collect::<Vec<u64>>(), [2, 3]);
+        assert_eq!(rows[1].vehicle.owner, "Karim");
+    }
+
+    #[test]
+    fn duplicates_point_to_the_first_row() {
+        let (rows, errors) = parse_vehicles(&csv(&[
+            "DHA-KA-11-1234,Rahim,2030-01-01,2030-01-01,2030-01-01,2030-01-01",
+            "DHAKA KA 11-1234,Karim,2030-01-01,2030-01-01,2030-01-01,2030-01-01",
+            "ঢাকা-ক-১১-১২৩৪,Salam,2030-01-01,2030-01-01,2030-01-01,2030-01-01",
+        ]));
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].row, 2);
+        assert_eq!(
+            errors
+                .iter()
+                .map(|error| (error.row, error.message.as_str()))
+                .collect::<Vec<(u64, &str)>>(),
+            [
+                (3, "the vehicle is already in row 2"),
+                (4, "the vehicle is already in row 2"),
+            ]
+        );
+    }
+
+    #[test]
+    fn bad_rows_only_cost_themselves() {
+        let (rows, errors) = parse_vehicles(&csv(&[
+            "not a plate,Rahim,2030-01-01,2030-01-01,2030-01-01,2030-01-01",
+            "DHA-KA-11-1234,Rahim,2030-13-01,2030-01-01,2030-01-01,2030-01-01",
+            "DHA-KA-11-1235,Rahim",
+            "DHA-KA-11-1236,Rahim,2030-01-01,2030-01-01,2030-01-01,2030-01-01",
+        ]));
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].row, 5);
+        assert_eq!(
+            errors.iter().map(|error| error.row).collect::<Vec<u64>>(),
+            [2, 3, 4]
+        );
+        assert_eq!(errors[1].vehicle_no.as_deref(), Some("DHA-KA-11-1234"));
+    }
+}
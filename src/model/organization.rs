@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+
+use aws_sdk_dynamodb::types::AttributeValue;
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::user::user_key;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Organization {
+    pub org_id: String,
+    pub name: String,
+    pub created_at: String,
+}
+
+impl Organization {
+    pub fn new(name: String) -> Self {
+        Organization {
+            org_id: Uuid::new_v4().to_string(),
+            name,
+            created_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        }
+    }
+
+    pub fn get_key(&self) -> AttributeValue {
+        org_key(&self.org_id)
+    }
+
+    pub fn to_item(&self) -> HashMap<String, AttributeValue> {
+        HashMap::from([
+            ("PK".to_string(), self.get_key()),
+            ("SK".to_string(), self.get_key()),
+            ("name".to_string(), AttributeValue::S(self.name.clone())),
+            (
+                "created_at".to_string(),
+                AttributeValue::S(self.created_at.clone()),
+            ),
+        ])
+    }
+}
+
+pub fn org_key(org_id: &str) -> AttributeValue {
+    AttributeValue::S(format!("ORG#{}", org_id))
+}
+
+/// The item that lists `username` as a member of the organization.
+pub fn member_item(org_id: &str, username: &str) -> HashMap<String, AttributeValue> {
+    HashMap::from([
+        ("PK".to_string(), org_key(org_id)),
+        ("SK".to_string(), user_key(username)),
+        (
+            "joined_at".to_string(),
+            AttributeValue::S(Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)),
+        ),
+    ])
+}
+
+pub fn org_from_item(item: &HashMap<String, AttributeValue>) -> Organization {
+    Organization {
+        org_id: item.get("PK").unwrap().as_s().unwrap()[4..].to_string(),
+        name: item.get("name").unwrap().as_s().unwrap().to_string(),
+        created_at: item.get("created_at").unwrap().as_s().unwrap().to_string(),
+    }
+}
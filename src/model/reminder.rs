@@ -0,0 +1,254 @@
+use std::collections::{HashMap, HashSet};
+
+use aws_sdk_dynamodb::types::AttributeValue;
+use chrono::{Days, NaiveDate, SecondsFormat, Utc};
+use serde::Serialize;
+
+use super::{
+    user::{Role, User},
+    vehicle::Vehicle,
+    vehicle_number::VehicleNumber,
+};
+
+/// How long the record of a sent reminder is kept after the fee's due date.
+/// A fee still unpaid by then is reminded of again.
+pub const REMINDER_KEEP_DAYS: u64 = 365;
+
+/// A fee that expires soon, or already expired, and the members of the
+/// organization to tell about it.
+#[derive(Debug, Clone, Serialize)]
+pub struct Reminder {
+    pub org_id: String,
+    pub vehicle_no: VehicleNumber,
+    pub owner: String,
+    pub fee_type: String,
+    pub due_date: String,
+    pub overdue: bool,
+    pub recipients: Vec<Recipient>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Recipient {
+    pub username: String,
+    pub phone: String,
+}
+
+impl Recipient {
+    /// Reminders go to the operators and admins of the organization, the
+    /// members that can pay the fee, as long as they have a phone number.
+    pub fn new(org_id: &str, user: &User) -> Option<Self> {
+        let phone = user.phone.as_deref().map(str::trim).unwrap_or_default();
+        match user.role(org_id) >= Role::Operator && !phone.is_empty() {
+            true => Some(Self {
+                username: user.username.clone(),
+                phone: phone.to_string(),
+            }),
+            false => None,
+        }
+    }
+}
+
+impl Reminder {
+    pub fn new(org_id: &str, vehicle: &Vehicle, fee_type: &str, overdue: bool) -> Self {
+        Self {
+            org_id: org_id.to_string(),
+            vehicle_no: vehicle.vehicle_no.clone(),
+            owner: vehicle.owner.clone(),
+            fee_type: fee_type.to_string(),
+            due_date: vehicle.fee_date(fee_type).to_string(),
+            overdue,
+            recipients: Vec::new(),
+        }
+    }
+
+    /// The reminder for the `recipients` whose claim is not in `claimed`, or
+    /// `None` when every one of them already has it. A reminder nobody can
+    /// get is kept, so the job can tell about it.
+    pub fn unclaimed(self, recipients: &[Recipient], claimed: &HashSet<String>) -> Option<Self> {
+        let unclaimed = recipients
+            .iter()
+            .filter(|recipient| !claimed.contains(self.get_key(recipient).as_s().unwrap().as_str()))
+            .cloned()
+            .collect::<Vec<Recipient>>();
+
+        match recipients.is_empty() || !unclaimed.is_empty() {
+            true => Some(Self {
+                recipients: unclaimed,
+                ..self
+            }),
+            false => None,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self.overdue {
+            true => format!(
+                "{} ({}): the {} expired on {}. Please renew it.",
+                self.vehicle_no, self.owner, self.fee_type, self.due_date
+            ),
+            false => format!(
+                "{} ({}): the {} expires on {}.",
+                self.vehicle_no, self.owner, self.fee_type, self.due_date
+            ),
+        }
+    }
+
+    pub fn get_key(&self, recipient: &Recipient) -> AttributeValue {
+        reminder_key(
+            &self.vehicle_no,
+            &self.fee_type,
+            &self.due_date,
+            self.overdue,
+            &recipient.username,
+        )
+    }
+
+    /// The claim on sending the reminder to one recipient, written before
+    /// the message goes out. One reminder goes out before a fee expires and
+    /// one after, until a payment moves its date.
+    pub fn to_item(&self, recipient: &Recipient) -> HashMap<String, AttributeValue> {
+        let keep_until = NaiveDate::parse_from_str(&self.due_date, "%Y-%m-%d")
+            .unwrap_or_else(|_| Utc::now().date_naive())
+            .checked_add_days(Days::new(REMINDER_KEEP_DAYS))
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_utc();
+
+        HashMap::from([
+            ("PK".to_string(), reminder_partition_key(&self.org_id)),
+            ("SK".to_string(), self.get_key(recipient)),
+            (
+                "sent_at".to_string(),
+                AttributeValue::S(Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)),
+            ),
+            (
+                "TTL".to_string(),
+                AttributeValue::N(keep_until.timestamp().to_string()),
+            ),
+        ])
+    }
+}
+
+pub fn reminder_partition_key(org_id: &str) -> AttributeValue {
+    AttributeValue::S(format!("ORG#{}#REMINDER", org_id))
+}
+
+pub fn reminder_key(
+    vehicle_no: &VehicleNumber,
+    fee_type: &str,
+    due_date: &str,
+    overdue: bool,
+    username: &str,
+) -> AttributeValue {
+    AttributeValue::S(format!(
+        "REMINDER#{}#{}#{}#{}#{}",
+        vehicle_no.as_key(),
+        fee_type,
+        due_date,
+        if overdue { "OVERDUE" } else { "DUE" },
+        username
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reminder(overdue: bool) -> Reminder {
+        let vehicle = Vehicle::new(
+            "DHA-KA-11-1234".parse().unwrap(),
+            "Rahim".to_string(),
+            "2030-01-31".to_string(),
+            "2030-01-31".to_string(),
+            "2030-01-31".to_string(),
+            "2030-01-31".to_string(),
+        );
+        Reminder::new("org", &vehicle, "tax", overdue)
+    }
+
+    fn recipient(username: &str) -> Recipient {
+        Recipient {
+            username: username.to_string(),
+            phone: "+8801700000000".to_string(),
+        }
+    }
+
+    fn user(role: Role, phone: Option<&str>) -> User {
+        User {
+            orgs: vec!["org".to_string()],
+            roles: HashMap::from([("org".to_string(), role)]),
+            ..User::new(
+                "rahim".to_string(),
+                "secret".to_string(),
+                phone.map(str::to_string),
+            )
+        }
+    }
+
+    #[test]
+    fn only_members_that_can_pay_and_have_a_phone_are_reminded() {
+        assert!(Recipient::new("org", &user(Role::Operator, Some(" +8801700000000 "))).is_some());
+        assert!(Recipient::new("org", &user(Role::Admin, Some("+8801700000000"))).is_some());
+        assert!(Recipient::new("org", &user(Role::Viewer, Some("+8801700000000"))).is_none());
+        assert!(Recipient::new("org", &user(Role::Operator, Some("  "))).is_none());
+        assert!(Recipient::new("org", &user(Role::Operator, None)).is_none());
+    }
+
+    #[test]
+    fn each_recipient_and_side_of_the_due_date_has_its_own_claim() {
+        let (karim, salam) = (recipient("karim"), recipient("salam"));
+        let keys = [
+            reminder(false).get_key(&karim),
+            reminder(false).get_key(&salam),
+            reminder(true).get_key(&karim),
+        ];
+
+        assert_eq!(
+            keys[0].as_s().unwrap(),
+            "REMINDER#DHAKA-KA-11-1234#tax#2030-01-31#DUE#karim"
+        );
+        assert_ne!(keys[0], keys[1]);
+        assert_ne!(keys[0], keys[2]);
+    }
+
+    #[test]
+    fn unclaimed_leaves_out_the_recipients_already_claimed() {
+        let recipients = [recipient("karim"), recipient("salam")];
+        let claimed = HashSet::from([reminder(false)
+            .get_key(&recipients[0])
+            .as_s()
+            .unwrap()
+            .clone()]);
+
+        let left = reminder(false).unclaimed(&recipients, &claimed).unwrap();
+        assert_eq!(left.recipients.len(), 1);
+        assert_eq!(left.recipients[0].username, "salam");
+
+        let claimed = recipients
+            .iter()
+            .map(|recipient| reminder(false).get_key(recipient).as_s().unwrap().clone())
+            .collect::<HashSet<String>>();
+        assert!(reminder(false).unclaimed(&recipients, &claimed).is_none());
+        assert!(reminder(false).unclaimed(&[], &claimed).is_some());
+    }
+
+    #[test]
+    fn claims_expire_a_while_after_the_due_date() {
+        let item = reminder(false).to_item(&recipient("karim"));
+        let keep_until = NaiveDate::from_ymd_opt(2030, 1, 31)
+            .unwrap()
+            .checked_add_days(Days::new(REMINDER_KEEP_DAYS))
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_utc()
+            .timestamp();
+
+        assert_eq!(
+            item.get("TTL"),
+            Some(&AttributeValue::N(keep_until.to_string()))
+        );
+        assert_eq!(item.get("PK"), Some(&reminder_partition_key("org")));
+    }
+}
@@ -0,0 +1,265 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::{
+    history::{TransactionHistory, OWNERSHIP_TRANSACTION},
+    vehicle::Vehicle,
+    vehicle_number::VehicleNumber,
+};
+
+/// Total, number and average of the payments that carry an amount.
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct SpendingTotal {
+    #[schema(value_type = String, example = "4500.00")]
+    pub total: Decimal,
+    pub count: u32,
+    #[schema(value_type = String, example = "1500.00")]
+    pub average: Decimal,
+}
+
+impl SpendingTotal {
+    fn add(&mut self, amount: Decimal) {
+        self.total += amount;
+        self.count += 1;
+        self.average = (self.total / Decimal::from(self.count)).round_dp(2);
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SpendingGroup {
+    pub key: String,
+    #[serde(flatten)]
+    pub spending: SpendingTotal,
+}
+
+/// Fee payments of a date range summed up per vehicle, fee type, owner at the
+/// time of payment and calendar month (`YYYY-MM`). Ownership changes are left
+/// out, and payments recorded without an amount are only counted in
+/// `unpriced`.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SpendingReport {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    #[serde(flatten)]
+    pub spending: SpendingTotal,
+    pub unpriced: u32,
+    pub by_vehicle: Vec<SpendingGroup>,
+    pub by_fee_type: Vec<SpendingGroup>,
+    pub by_owner: Vec<SpendingGroup>,
+    pub by_month: Vec<SpendingGroup>,
+}
+
+impl SpendingReport {
+    pub fn new(from: Option<String>, to: Option<String>, historys: &[TransactionHistory]) -> Self {
+        let mut spending = SpendingTotal::default();
+        let mut unpriced = 0;
+        let mut by_vehicle = BTreeMap::new();
+        let mut by_fee_type = BTreeMap::new();
+        let mut by_owner = BTreeMap::new();
+        let mut by_month = BTreeMap::new();
+
+        for history in historys
+            .iter()
+            .filter(|history| history.transaction_type != OWNERSHIP_TRANSACTION)
+        {
+            let amount = match history.payment.amount {
+                Some(amount) => amount,
+                None => {
+                    unpriced += 1;
+                    continue;
+                }
+            };
+
+            spending.add(amount);
+            let vehicle_no = history.vehicle_no.to_string();
+            for (groups, key) in [
+                (&mut by_vehicle, vehicle_no.as_str()),
+                (&mut by_fee_type, history.transaction_type.as_str()),
+                (&mut by_owner, history.owner.as_deref().unwrap_or("unknown")),
+                (&mut by_month, &history.created_at[..7]),
+            ] {
+                groups
+                    .entry(key.to_string())
+                    .or_insert_with(SpendingTotal::default)
+                    .add(amount);
+            }
+        }
+
+        Self {
+            from,
+            to,
+            spending,
+            unpriced,
+            by_vehicle: spending_groups(by_vehicle),
+            by_fee_type: spending_groups(by_fee_type),
+            by_owner: spending_groups(by_owner),
+            by_month: spending_groups(by_month),
+        }
+    }
+}
+
+fn spending_groups(groups: BTreeMap<String, SpendingTotal>) -> Vec<SpendingGroup> {
+    groups
+        .into_iter()
+        .map(|(key, spending)| SpendingGroup { key, spending })
+        .collect()
+}
+
+#[derive(Debug, Serialize)]
+pub struct DueVehicle {
+    pub vehicle_no: VehicleNumber,
+    pub due_date: String,
+    /// Negative once the fee is overdue.
+    pub days_remaining: i64,
+}
+
+impl DueVehicle {
+    pub fn overdue(&self) -> bool {
+        self.days_remaining < 0
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DueOwner {
+    pub owner: String,
+    pub vehicles: Vec<DueVehicle>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DueFee {
+    pub fee_type: String,
+    pub owners: Vec<DueOwner>,
+}
+
+/// Fees that are overdue or expire by `until`, per fee type and then per
+/// owner in alphabetical order, the most urgent vehicle first.
+#[derive(Debug, Serialize)]
+pub struct DueReport {
+    pub generated_on: String,
+    pub until: String,
+    pub fees: Vec<DueFee>,
+}
+
+impl DueReport {
+    /// `due` holds the vehicles of every fee type as the due queries return
+    /// them, overdue and due ones together.
+    pub fn new(today: NaiveDate, until: NaiveDate, due: Vec<(&str, Vec<Vehicle>)>) -> Self {
+        let fees = due
+            .into_iter()
+            .map(|(fee_type, vehicles)| {
+                let mut owners = BTreeMap::<String, Vec<DueVehicle>>::new();
+                for vehicle in vehicles {
+                    let due_date = vehicle.fee_date(fee_type).to_string();
+                    let days_remaining = match NaiveDate::parse_from_str(&due_date, "%Y-%m-%d") {
+                        Ok(date) => (date - today).num_days(),
+                        Err(_) => continue,
+                    };
+                    owners.entry(vehicle.owner).or_default().push(DueVehicle {
+                        vehicle_no: vehicle.vehicle_no,
+                        due_date,
+                        days_remaining,
+                    });
+                }
+
+                DueFee {
+                    fee_type: fee_type.to_string(),
+                    owners: owners
+                        .into_iter()
+                        .map(|(owner, mut vehicles)| {
+                            vehicles.sort_by(|a, b| {
+                                (&a.due_date, &a.vehicle_no).cmp(&(&b.due_date, &b.vehicle_no))
+                            });
+                            DueOwner { owner, vehicles }
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+
+        Self {
+            generated_on: today.format("%Y-%m-%d").to_string(),
+            until: until.format("%Y-%m-%d").to_string(),
+            fees,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vehicle(vehicle_no: &str, owner: &str, tax_date: &str) -> Vehicle {
+        Vehicle::new(
+            vehicle_no.parse().unwrap(),
+            owner.to_string(),
+            tax_date.to_string(),
+            "2099-01-01".to_string(),
+            "2099-01-01".to_string(),
+            "2099-01-01".to_string(),
+        )
+    }
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn due_fees_are_grouped_by_fee_type_and_owner() {
+        let report = DueReport::new(
+            date("2026-01-10"),
+            date("2026-02-09"),
+            vec![
+                (
+                    "tax",
+                    vec![
+                        vehicle("DHA-KA-11-0003", "Rahim", "2026-01-20"),
+                        vehicle("DHA-KA-11-0002", "Karim", "2026-01-15"),
+                        vehicle("DHA-KA-11-0001", "Rahim", "2026-01-05"),
+                    ],
+                ),
+                ("fitness", Vec::new()),
+            ],
+        );
+
+        assert_eq!(report.generated_on, "2026-01-10");
+        assert_eq!(report.until, "2026-02-09");
+        assert_eq!(
+            report
+                .fees
+                .iter()
+                .map(|fee| fee.fee_type.as_str())
+                .collect::<Vec<&str>>(),
+            ["tax", "fitness"]
+        );
+        assert!(report.fees[1].owners.is_empty());
+
+        let owners = &report.fees[0].owners;
+        assert_eq!(
+            owners
+                .iter()
+                .map(|owner| owner.owner.as_str())
+                .collect::<Vec<&str>>(),
+            ["Karim", "Rahim"]
+        );
+        let rahim = &owners[1].vehicles;
+        assert_eq!(rahim[0].vehicle_no.to_string(), "DHA-KA-11-0001");
+        assert_eq!(rahim[0].days_remaining, -5);
+        assert!(rahim[0].overdue());
+        assert_eq!(rahim[1].days_remaining, 10);
+        assert!(!rahim[1].overdue());
+    }
+
+    #[test]
+    fn fees_without_a_date_are_left_out() {
+        let report = DueReport::new(
+            date("2026-01-10"),
+            date("2026-02-09"),
+            vec![("tax", vec![vehicle("DHA-KA-11-0001", "Rahim", "soon")])],
+        );
+        assert!(report.fees[0].owners.is_empty());
+    }
+}
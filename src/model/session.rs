@@ -2,23 +2,45 @@ use std::collections::HashMap;
 
 use aws_sdk_dynamodb::types::AttributeValue;
 use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-use super::user::user_key;
+use super::user::{user_key, Role, User};
+use crate::error::DataAccessError;
+
+/// How long an access token is accepted. Clients renew it with their
+/// refresh token rather than logging in again.
+pub const ACCESS_TOKEN_MINUTES: i64 = 15;
+
+/// How long a refresh token can be exchanged. Every exchange starts a new
+/// one, so a client that is used at least this often stays logged in.
+pub const REFRESH_TOKEN_DAYS: i64 = 30;
+
+/// How stale the `last_seen_at` of a login may get before a request writes
+/// it again, so a busy client costs one write per interval, not per request.
+pub const LAST_SEEN_MINUTES: i64 = 5;
 
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 pub struct Session {
     pub session_id: String,
     pub created_at: String,
     pub expired_at: String,
+    /// The organization every request made with this session is scoped to.
+    pub org_id: String,
+    /// The login this session descends from, shared with its refresh tokens.
+    pub family_id: String,
 }
 
 impl Session {
-    pub fn new() -> Self {
+    pub fn new(org_id: &str, family_id: &str) -> Self {
         Session {
             session_id: Uuid::new_v4().to_string(),
             created_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
-            expired_at: (Utc::now() + Duration::days(7)).to_rfc3339_opts(SecondsFormat::Secs, true),
+            expired_at: (Utc::now() + Duration::minutes(ACCESS_TOKEN_MINUTES))
+                .to_rfc3339_opts(SecondsFormat::Secs, true),
+            org_id: org_id.to_string(),
+            family_id: family_id.to_string(),
         }
     }
 
@@ -26,15 +48,7 @@ impl Session {
         HashMap::from([
             ("PK".to_string(), user_key(username)),
             ("SK".to_string(), session_key(&self.session_id)),
-            (
-                "TTL".to_string(),
-                AttributeValue::N(
-                    DateTime::parse_from_rfc3339(&self.expired_at)
-                        .unwrap()
-                        .timestamp()
-                        .to_string(),
-                ),
-            ),
+            ("TTL".to_string(), ttl_value(&self.expired_at)),
             (
                 "created_at".to_string(),
                 AttributeValue::S(self.created_at.clone().to_string()),
@@ -43,6 +57,15 @@ impl Session {
                 "expired_at".to_string(),
                 AttributeValue::S(self.expired_at.clone().to_string()),
             ),
+            (
+                "last_seen_at".to_string(),
+                AttributeValue::S(self.created_at.clone()),
+            ),
+            ("org_id".to_string(), AttributeValue::S(self.org_id.clone())),
+            (
+                "family_id".to_string(),
+                AttributeValue::S(self.family_id.clone()),
+            ),
             ("GSI1PK".to_string(), session_key(&self.session_id)),
             ("GSI1SK".to_string(), user_key(username)),
         ])
@@ -54,3 +77,400 @@ pub fn session_key(session_id: &str) -> AttributeValue {
 
     AttributeValue::S(key)
 }
+
+/// A single-use token that buys a new session and a new refresh token of the
+/// same family. A used one is kept until it expires, so presenting it again
+/// gives the theft away.
+#[derive(Debug, Clone)]
+pub struct RefreshToken {
+    pub token: String,
+    pub family_id: String,
+    pub org_id: String,
+    pub created_at: String,
+    pub expired_at: String,
+    pub used: bool,
+}
+
+impl RefreshToken {
+    pub fn new(org_id: &str, family_id: &str) -> Self {
+        RefreshToken {
+            token: Uuid::new_v4().to_string(),
+            family_id: family_id.to_string(),
+            org_id: org_id.to_string(),
+            created_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            expired_at: (Utc::now() + Duration::days(REFRESH_TOKEN_DAYS))
+                .to_rfc3339_opts(SecondsFormat::Secs, true),
+            used: false,
+        }
+    }
+
+    /// The session and refresh token of a new login, starting a family of
+    /// their own.
+    pub fn start_family(org_id: &str) -> (Session, RefreshToken) {
+        let family_id = Uuid::new_v4().to_string();
+        (
+            Session::new(org_id, &family_id),
+            RefreshToken::new(org_id, &family_id),
+        )
+    }
+
+    /// The session and refresh token this one is exchanged for.
+    pub fn rotate(&self) -> (Session, RefreshToken) {
+        (
+            Session::new(&self.org_id, &self.family_id),
+            RefreshToken::new(&self.org_id, &self.family_id),
+        )
+    }
+
+    pub fn to_item(&self, username: &str) -> HashMap<String, AttributeValue> {
+        HashMap::from([
+            ("PK".to_string(), user_key(username)),
+            ("SK".to_string(), refresh_key(&self.token)),
+            ("TTL".to_string(), ttl_value(&self.expired_at)),
+            (
+                "family_id".to_string(),
+                AttributeValue::S(self.family_id.clone()),
+            ),
+            ("org_id".to_string(), AttributeValue::S(self.org_id.clone())),
+            (
+                "created_at".to_string(),
+                AttributeValue::S(self.created_at.clone()),
+            ),
+            (
+                "expired_at".to_string(),
+                AttributeValue::S(self.expired_at.clone()),
+            ),
+            ("used".to_string(), AttributeValue::Bool(self.used)),
+            ("GSI1PK".to_string(), refresh_key(&self.token)),
+            ("GSI1SK".to_string(), user_key(username)),
+        ])
+    }
+}
+
+pub fn refresh_key(token: &str) -> AttributeValue {
+    AttributeValue::S(format!("REFRESH#{token}"))
+}
+
+pub fn refresh_from_item(item: &HashMap<String, AttributeValue>) -> RefreshToken {
+    let text = |name: &str| {
+        item.get(name)
+            .and_then(|value| value.as_s().ok())
+            .cloned()
+            .unwrap_or_default()
+    };
+    RefreshToken {
+        token: text("SK")["REFRESH#".len()..].to_string(),
+        family_id: text("family_id"),
+        org_id: text("org_id"),
+        created_at: text("created_at"),
+        expired_at: text("expired_at"),
+        used: matches!(item.get("used"), Some(AttributeValue::Bool(true))),
+    }
+}
+
+/// The username a session or refresh token item belongs to.
+pub fn owner_of(item: &HashMap<String, AttributeValue>) -> String {
+    item.get("PK").unwrap().as_s().unwrap()[5..].to_string()
+}
+
+/// Fails with `SessionExpired` once the `expired_at` of a session or refresh
+/// token item has passed. DynamoDB only removes items some time after their
+/// `TTL`, so an item that is still there is not necessarily valid.
+pub fn check_expiry(item: &HashMap<String, AttributeValue>) -> Result<(), DataAccessError> {
+    let expired_at = item
+        .get("expired_at")
+        .and_then(|value| value.as_s().ok())
+        .and_then(|expired_at| DateTime::parse_from_rfc3339(expired_at).ok());
+    match expired_at {
+        Some(expired_at) if expired_at > Utc::now() => Ok(()),
+        _ => Err(DataAccessError::SessionExpired(
+            "Session Expired!! login Again.".to_string(),
+        )),
+    }
+}
+
+/// What `login` and `refresh` hand out: a short lived access token for the
+/// `Authorization` header and the refresh token that renews it.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Tokens {
+    /// Send it as the `Authorization` header.
+    pub token: String,
+    pub expired_at: String,
+    /// Exchange it at `POST /sessions/refresh` before `refresh_expired_at`.
+    /// It works once.
+    pub refresh_token: String,
+    pub refresh_expired_at: String,
+}
+
+impl Tokens {
+    pub fn new(session: &Session, refresh: &RefreshToken) -> Self {
+        Tokens {
+            token: session.session_id.clone(),
+            expired_at: session.expired_at.clone(),
+            refresh_token: refresh.token.clone(),
+            refresh_expired_at: refresh.expired_at.clone(),
+        }
+    }
+}
+
+/// Where a login comes from, as far as the request tells.
+#[derive(Debug, Clone, Default)]
+pub struct Device {
+    /// Name the client gives itself at login, e.g. `Rahim's phone`.
+    pub name: Option<String>,
+    pub user_agent: Option<String>,
+    pub source_ip: Option<String>,
+}
+
+/// One login of a user and the device it was made from. Every session and
+/// refresh token the login hands out shares its `id`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DeviceSession {
+    /// The `id` to revoke the login by.
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    /// Address of the last login or refresh.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_ip: Option<String>,
+    pub org_id: String,
+    pub created_at: String,
+    /// Last request made with the login, accurate to `LAST_SEEN_MINUTES`.
+    pub last_seen_at: String,
+    /// When its current refresh token expires, unless refreshed again.
+    pub expired_at: String,
+    /// Whether it is the login of the request that listed it.
+    pub current: bool,
+}
+
+impl DeviceSession {
+    pub fn new(device: Device, session: &Session, refresh: &RefreshToken) -> Self {
+        DeviceSession {
+            id: session.family_id.clone(),
+            device_name: device.name,
+            user_agent: device.user_agent,
+            source_ip: device.source_ip,
+            org_id: session.org_id.clone(),
+            created_at: session.created_at.clone(),
+            last_seen_at: session.created_at.clone(),
+            expired_at: refresh.expired_at.clone(),
+            current: false,
+        }
+    }
+
+    pub fn to_item(&self, username: &str) -> HashMap<String, AttributeValue> {
+        let mut item = HashMap::from([
+            ("PK".to_string(), user_key(username)),
+            ("SK".to_string(), device_key(&self.id)),
+            ("family_id".to_string(), AttributeValue::S(self.id.clone())),
+            ("org_id".to_string(), AttributeValue::S(self.org_id.clone())),
+            (
+                "created_at".to_string(),
+                AttributeValue::S(self.created_at.clone()),
+            ),
+            (
+                "last_seen_at".to_string(),
+                AttributeValue::S(self.last_seen_at.clone()),
+            ),
+            (
+                "expired_at".to_string(),
+                AttributeValue::S(self.expired_at.clone()),
+            ),
+            ("TTL".to_string(), ttl_value(&self.expired_at)),
+        ]);
+        for (name, value) in [
+            ("device_name", &self.device_name),
+            ("user_agent", &self.user_agent),
+            ("source_ip", &self.source_ip),
+        ] {
+            if let Some(value) = value {
+                item.insert(name.to_string(), AttributeValue::S(value.clone()));
+            }
+        }
+        item
+    }
+}
+
+pub fn device_key(family_id: &str) -> AttributeValue {
+    AttributeValue::S(format!("DEVICE#{family_id}"))
+}
+
+/// Reads a `DEVICE#` item; `current_family` marks the caller's own login.
+pub fn device_from_item(
+    item: &HashMap<String, AttributeValue>,
+    current_family: Option<&str>,
+) -> DeviceSession {
+    let text = |name: &str| item.get(name).and_then(|value| value.as_s().ok()).cloned();
+    let id = text("family_id").unwrap_or_default();
+    DeviceSession {
+        current: current_family == Some(id.as_str()),
+        id,
+        device_name: text("device_name"),
+        user_agent: text("user_agent"),
+        source_ip: text("source_ip"),
+        org_id: text("org_id").unwrap_or_default(),
+        created_at: text("created_at").unwrap_or_default(),
+        last_seen_at: text("last_seen_at").unwrap_or_default(),
+        expired_at: text("expired_at").unwrap_or_default(),
+    }
+}
+
+/// `TTL` attribute of an item that expires at the RFC 3339 `expired_at`.
+pub fn ttl_value(expired_at: &str) -> AttributeValue {
+    AttributeValue::N(
+        DateTime::parse_from_rfc3339(expired_at)
+            .unwrap()
+            .timestamp()
+            .to_string(),
+    )
+}
+
+/// Whether the `last_seen_at` of a session item is older than
+/// `LAST_SEEN_MINUTES`, or missing on a session of a login that has one.
+pub fn last_seen_is_stale(session_item: &HashMap<String, AttributeValue>) -> bool {
+    if !session_item.contains_key("family_id") {
+        return false;
+    }
+    session_item
+        .get("last_seen_at")
+        .and_then(|value| value.as_s().ok())
+        .and_then(|last_seen| DateTime::parse_from_rfc3339(last_seen).ok())
+        .is_none_or(|last_seen| last_seen < Utc::now() - Duration::minutes(LAST_SEEN_MINUTES))
+}
+
+/// Which logins of a user to revoke.
+#[derive(Debug, Clone, Copy)]
+pub enum Logins<'a> {
+    All,
+    Only(&'a str),
+    AllBut(&'a str),
+}
+
+impl Logins<'_> {
+    /// Whether a session, refresh token or device item belongs to one of the
+    /// logins. Items of logins from before families existed count as other
+    /// logins.
+    pub fn contains(&self, item: &HashMap<String, AttributeValue>) -> bool {
+        let family_id = item.get("family_id").and_then(|value| value.as_s().ok());
+        match self {
+            Logins::All => true,
+            Logins::Only(id) => family_id.is_some_and(|family_id| family_id == id),
+            Logins::AllBut(id) => family_id.is_none_or(|family_id| family_id != id),
+        }
+    }
+}
+
+/// The user behind a session token, the organization the session is scoped to
+/// and the user's role there.
+#[derive(Debug, Clone)]
+pub struct Caller {
+    pub username: String,
+    pub org_id: String,
+    pub role: Role,
+    /// The login of the session, `None` for sessions from before logins were
+    /// tracked.
+    pub family_id: Option<String>,
+}
+
+impl Caller {
+    /// Fails with `Forbidden` unless the caller has at least `role`.
+    pub fn require(&self, role: Role) -> Result<(), DataAccessError> {
+        if self.role >= role {
+            Ok(())
+        } else {
+            Err(DataAccessError::Forbidden(format!(
+                "The {} role is required!!",
+                role
+            )))
+        }
+    }
+}
+
+/// Reads the caller out of a session item and its user. Sessions written
+/// before organizations existed carry no `org_id` and are scoped to the
+/// user's first organization; a user without any yields `None`.
+pub fn caller_from_item(
+    session_item: &HashMap<String, AttributeValue>,
+    user: &User,
+) -> Option<Caller> {
+    let org_id = session_item
+        .get("org_id")
+        .and_then(|org_id| org_id.as_s().ok())
+        .or(user.orgs.first())?
+        .to_string();
+    Some(Caller {
+        username: user.username.clone(),
+        role: user.role(&org_id),
+        org_id,
+        family_id: session_item
+            .get("family_id")
+            .and_then(|family_id| family_id.as_s().ok())
+            .cloned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expiring_at(expired_at: &str) -> HashMap<String, AttributeValue> {
+        HashMap::from([(
+            "expired_at".to_string(),
+            AttributeValue::S(expired_at.to_string()),
+        )])
+    }
+
+    fn caller(role: Role) -> Caller {
+        Caller {
+            username: "rahim".to_string(),
+            org_id: "org".to_string(),
+            role,
+            family_id: None,
+        }
+    }
+
+    #[test]
+    fn callers_need_at_least_the_required_role() {
+        assert!(caller(Role::Viewer).require(Role::Viewer).is_ok());
+        assert!(caller(Role::Admin).require(Role::Operator).is_ok());
+
+        for (role, required) in [
+            (Role::Viewer, Role::Operator),
+            (Role::Viewer, Role::Admin),
+            (Role::Operator, Role::Admin),
+        ] {
+            let err = caller(role).require(required).unwrap_err();
+            assert!(matches!(err, DataAccessError::Forbidden(_)));
+            assert_eq!(err.status_code(), 403);
+        }
+    }
+
+    #[test]
+    fn live_sessions_pass_the_expiry_check() {
+        let session = Session::new("org", "family");
+        assert!(check_expiry(&session.to_item("rahim")).is_ok());
+    }
+
+    #[test]
+    fn expired_or_unreadable_sessions_fail_the_expiry_check() {
+        let past = (Utc::now() - Duration::seconds(1)).to_rfc3339();
+        for item in [expiring_at(&past), expiring_at("tomorrow"), HashMap::new()] {
+            assert!(matches!(
+                check_expiry(&item),
+                Err(DataAccessError::SessionExpired(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn rotation_keeps_the_family() {
+        let (session, refresh) = RefreshToken::start_family("org");
+        let (next_session, next_refresh) = refresh.rotate();
+
+        assert_eq!(next_refresh.family_id, refresh.family_id);
+        assert_ne!(next_refresh.token, refresh.token);
+        assert_ne!(next_session.session_id, session.session_id);
+    }
+}
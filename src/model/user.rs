@@ -1,15 +1,77 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt, str::FromStr};
 
 use aws_sdk_dynamodb::types::AttributeValue;
 use chrono::{SecondsFormat, Utc};
 use pwhash::bcrypt;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// What a user may do inside one organization. Roles are ordered, each one
+/// includes everything the roles below it can do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Reads vehicles and history.
+    Viewer,
+    /// Also adds and updates vehicles and pays fees.
+    Operator,
+    /// Also undoes transactions and manages the members of the organization.
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Operator => "operator",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(role: &str) -> Result<Self, Self::Err> {
+        match role {
+            "viewer" => Ok(Role::Viewer),
+            "operator" => Ok(Role::Operator),
+            "admin" => Ok(Role::Admin),
+            _ => Err(format!("{} is not a valid role", role)),
+        }
+    }
+}
+
+/// Body of signup and login. Signup reads `phone` and `organization`, login
+/// reads `org_id` and `device_name`; `orgs` and `roles` are filled in by the
+/// store.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct User {
     pub username: String,
     pub password: String,
     pub phone: Option<String>,
+    /// Ids of the organizations the user belongs to. The first one is the
+    /// default organization of a new session.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub orgs: Vec<String>,
+    /// Role of the user per organization id.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub roles: HashMap<String, Role>,
+    /// Name of the organization created for the user at signup.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub organization: Option<String>,
+    /// Organization a login should be scoped to instead of the default one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<String>,
+    /// Name of the device a login is made from, shown in the session list.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_name: Option<String>,
 }
 
 impl User {
@@ -18,6 +80,11 @@ impl User {
             username,
             password,
             phone,
+            orgs: Vec::new(),
+            roles: HashMap::new(),
+            organization: None,
+            org_id: None,
+            device_name: None,
         }
     }
 
@@ -33,6 +100,32 @@ impl User {
         bcrypt::verify(password, &self.password)
     }
 
+    /// Role of the user in `org_id`. Users that joined organizations before
+    /// roles existed have none stored: they are admins of their first
+    /// organization, the one created for them at signup, and viewers of the
+    /// ones they were added to.
+    pub fn role(&self, org_id: &str) -> Role {
+        match self.roles.get(org_id) {
+            Some(role) => *role,
+            None if self.roles.is_empty() && self.orgs.first().is_some_and(|org| org == org_id) => {
+                Role::Admin
+            }
+            None => Role::Viewer,
+        }
+    }
+
+    /// The roles of the user once `org_id` is set to `role`. Organizations
+    /// without a stored role keep the one `role` resolves for them.
+    pub fn roles_with(&self, org_id: &str, role: Role) -> HashMap<String, Role> {
+        let mut roles = self
+            .orgs
+            .iter()
+            .map(|org| (org.clone(), self.role(org)))
+            .collect::<HashMap<String, Role>>();
+        roles.insert(org_id.to_string(), role);
+        roles
+    }
+
     pub fn to_item(&self) -> HashMap<String, aws_sdk_dynamodb::types::AttributeValue> {
         let hash_password = bcrypt::hash(&self.password).unwrap();
         let date = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
@@ -57,13 +150,28 @@ impl User {
         ]);
 
         if let Some(number) = &self.phone {
-            println!("{}", number);
             user_map.insert(
                 "phone".to_string(),
                 aws_sdk_dynamodb::types::AttributeValue::S(number.to_owned()),
             );
         }
 
+        if !self.orgs.is_empty() {
+            user_map.insert(
+                "orgs".to_string(),
+                aws_sdk_dynamodb::types::AttributeValue::L(
+                    self.orgs
+                        .iter()
+                        .map(|org_id| aws_sdk_dynamodb::types::AttributeValue::S(org_id.clone()))
+                        .collect(),
+                ),
+            );
+        }
+
+        if !self.roles.is_empty() {
+            user_map.insert("roles".to_string(), roles_value(&self.roles));
+        }
+
         user_map
     }
 }
@@ -72,9 +180,104 @@ pub fn user_key(username: &str) -> aws_sdk_dynamodb::types::AttributeValue {
     aws_sdk_dynamodb::types::AttributeValue::S(format!("USER#{}", username))
 }
 
+pub fn roles_value(roles: &HashMap<String, Role>) -> aws_sdk_dynamodb::types::AttributeValue {
+    aws_sdk_dynamodb::types::AttributeValue::M(
+        roles
+            .iter()
+            .map(|(org_id, role)| {
+                (
+                    org_id.clone(),
+                    aws_sdk_dynamodb::types::AttributeValue::S(role.to_string()),
+                )
+            })
+            .collect(),
+    )
+}
+
 pub fn from_item(item: &HashMap<String, aws_sdk_dynamodb::types::AttributeValue>) -> User {
     let username = item.get("PK").unwrap().as_s().unwrap().to_string()[5..].to_string();
     let password = item.get("password").unwrap().as_s().unwrap().to_string();
     let phone: Option<String> = item.get("phone").map(|s| s.as_s().unwrap().to_string());
-    User::new(username, password, phone)
+    let orgs = item
+        .get("orgs")
+        .and_then(|orgs| orgs.as_l().ok())
+        .map(|orgs| {
+            orgs.iter()
+                .filter_map(|org_id| org_id.as_s().ok().cloned())
+                .collect()
+        })
+        .unwrap_or_default();
+    let roles = item
+        .get("roles")
+        .and_then(|roles| roles.as_m().ok())
+        .map(|roles| {
+            roles
+                .iter()
+                .filter_map(|(org_id, role)| {
+                    let role = role.as_s().ok()?.parse::<Role>().ok()?;
+                    Some((org_id.clone(), role))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    User {
+        orgs,
+        roles,
+        ..User::new(username, password, phone)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member_of(orgs: &[&str], roles: &[(&str, Role)]) -> User {
+        User {
+            orgs: orgs.iter().map(|org| org.to_string()).collect(),
+            roles: roles
+                .iter()
+                .map(|(org, role)| (org.to_string(), *role))
+                .collect(),
+            ..User::new("rahim".to_string(), "secret".to_string(), None)
+        }
+    }
+
+    #[test]
+    fn stored_roles_win() {
+        let user = member_of(
+            &["own", "other"],
+            &[("own", Role::Operator), ("other", Role::Admin)],
+        );
+        assert_eq!(user.role("own"), Role::Operator);
+        assert_eq!(user.role("other"), Role::Admin);
+        assert_eq!(user.role("unknown"), Role::Viewer);
+    }
+
+    #[test]
+    fn users_without_roles_only_administer_their_own_organization() {
+        let user = member_of(&["own", "other"], &[]);
+        assert_eq!(user.role("own"), Role::Admin);
+        assert_eq!(user.role("other"), Role::Viewer);
+        assert_eq!(member_of(&[], &[]).role("own"), Role::Viewer);
+    }
+
+    #[test]
+    fn roles_with_keeps_the_resolved_roles() {
+        let user = member_of(&["own", "other"], &[]);
+        assert_eq!(
+            user.roles_with("new", Role::Operator),
+            HashMap::from([
+                ("own".to_string(), Role::Admin),
+                ("other".to_string(), Role::Viewer),
+                ("new".to_string(), Role::Operator),
+            ])
+        );
+    }
+
+    #[test]
+    fn roles_are_ordered_by_what_they_allow() {
+        assert!(Role::Viewer < Role::Operator && Role::Operator < Role::Admin);
+        assert_eq!("operator".parse::<Role>(), Ok(Role::Operator));
+        assert!("owner".parse::<Role>().is_err());
+    }
 }
@@ -1,78 +1,94 @@
 use std::collections::HashMap;
 
 use aws_sdk_dynamodb::types::AttributeValue;
-use chrono::{NaiveDate, SecondsFormat, Utc};
+use chrono::{Local, NaiveDate, SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Debug, Serialize, Deserialize)]
+use super::vehicle_number::VehicleNumber;
+use crate::validation::iso_date;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct Vehicle {
-    pub vehicle_no: String,
+    pub vehicle_no: VehicleNumber,
     pub owner: String,
+    #[schema(format = Date)]
     pub tax_date: String,
+    #[schema(format = Date)]
     pub fitness_date: String,
+    #[schema(format = Date)]
     pub insurance_date: String,
+    #[schema(format = Date)]
     pub route_date: String,
+    /// Picks the renewal rules of the vehicle, e.g. `private` or `commercial`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vehicle_class: Option<String>,
 }
 
 impl Vehicle {
+    /// Dates are written back as `YYYY-MM-DD`. One that doesn't parse, e.g.
+    /// missing from a stored item, is kept as it is; requests are checked by
+    /// `validation` before they get here.
     pub fn new(
-        vehicle_no: String,
+        vehicle_no: VehicleNumber,
         owner: String,
         tax_date: String,
         fitness_date: String,
         insurance_date: String,
         route_date: String,
     ) -> Self {
-        fn date_formatter(date: &str) -> NaiveDate {
-            let date: Vec<u32> = date.split("-").map(|d| d.parse::<u32>().unwrap()).collect();
-            NaiveDate::from_ymd_opt(date[0] as i32, date[1], date[2]).unwrap()
-        }
-        let tax_date = date_formatter(&tax_date).format("%Y-%m-%d").to_string();
-        let fitness_date = date_formatter(&fitness_date).format("%Y-%m-%d").to_string();
-        let insurance_date = date_formatter(&insurance_date)
-            .format("%Y-%m-%d")
-            .to_string();
-        let route_date = date_formatter(&route_date).format("%Y-%m-%d").to_string();
+        let date = |date: String| iso_date("date", &date).unwrap_or(date);
 
         Self {
             vehicle_no,
             owner,
-            tax_date,
-            fitness_date,
-            insurance_date,
-            route_date,
+            tax_date: date(tax_date),
+            fitness_date: date(fitness_date),
+            insurance_date: date(insurance_date),
+            route_date: date(route_date),
+            vehicle_class: None,
         }
     }
 
-    pub fn get_key(&self) -> AttributeValue {
-        vehicle_key(self.vehicle_no.as_ref())
+    pub fn get_key(&self, org_id: &str) -> AttributeValue {
+        vehicle_key(org_id, &self.vehicle_no)
+    }
+
+    /// Expiry date of `fee_type`, the tax date for an unknown fee.
+    pub fn fee_date(&self, fee_type: &str) -> &str {
+        match fee_type {
+            "fitness" => &self.fitness_date,
+            "insurance" => &self.insurance_date,
+            "route" => &self.route_date,
+            _ => &self.tax_date,
+        }
     }
     pub fn to_json(&self) -> String {
         serde_json::to_string(self).unwrap()
     }
 
-    pub fn to_search_item(&self) -> HashMap<String, AttributeValue> {
-        let last4_digit = self
-            .vehicle_no
-            .char_indices()
-            .rev()
-            .nth(3)
-            .map(|(i, _)| &self.vehicle_no[i..]);
-
+    pub fn to_search_item(&self, org_id: &str) -> HashMap<String, AttributeValue> {
         HashMap::from([
-            ("PK".to_string(), AttributeValue::S("SEARCH".to_owned())),
-            ("SK".to_string(), vehicle_search_key(&self.vehicle_no)),
+            ("PK".to_string(), search_partition_key(org_id)),
+            (
+                "SK".to_string(),
+                vehicle_search_key(&self.vehicle_no.as_key()),
+            ),
             (
                 "LSI1SK".to_string(),
-                vehicle_search_key(last4_digit.unwrap()),
+                vehicle_search_key(self.vehicle_no.serial()),
             ),
         ])
     }
 
-    pub fn to_item(self) -> HashMap<String, AttributeValue> {
-        HashMap::from([
-            ("PK".to_string(), self.get_key()),
-            ("SK".to_string(), self.get_key()),
+    pub fn to_item(self, org_id: &str) -> HashMap<String, AttributeValue> {
+        let mut vehicle_item = HashMap::from([
+            ("PK".to_string(), self.get_key(org_id)),
+            ("SK".to_string(), self.get_key(org_id)),
+            (
+                "vehicle_no".to_string(),
+                AttributeValue::S(self.vehicle_no.to_string()),
+            ),
             ("owner".to_string(), AttributeValue::S(self.owner)),
             (
                 "fitness_date".to_string(),
@@ -90,43 +106,67 @@ impl Vehicle {
             ),
             ("updated_at".to_string(), AttributeValue::Null(true)),
             ("Sold".to_string(), AttributeValue::Bool(false)),
-            ("GSI2PK".to_string(), AttributeValue::S(format!("VEHICLE"))),
-        ])
+            ("GSI2PK".to_string(), vehicle_partition_key(org_id)),
+        ]);
+
+        if let Some(vehicle_class) = self.vehicle_class {
+            vehicle_item.insert(
+                "vehicle_class".to_string(),
+                AttributeValue::S(vehicle_class),
+            );
+        }
+
+        vehicle_item
     }
 }
 
-pub fn vehicle_key(car_id: &str) -> AttributeValue {
-    let car_id = &*car_id.split("-").collect::<Vec<&str>>().join("");
-    AttributeValue::S(["CAR#", car_id].join(""))
+pub fn vehicle_key(org_id: &str, vehicle_no: &VehicleNumber) -> AttributeValue {
+    AttributeValue::S(format!("ORG#{}#CAR#{}", org_id, vehicle_no.as_key()))
+}
+
+/// Reads the plate back from a `CAR#` or `SEARCH#` key.
+pub fn vehicle_no_from_key(key: &AttributeValue) -> VehicleNumber {
+    let key = key.as_s().unwrap();
+    let vehicle_no = match key.split_once("#CAR#") {
+        Some((_org, vehicle_no)) => vehicle_no,
+        None => key.trim_start_matches("SEARCH#"),
+    };
+    VehicleNumber::from_key(vehicle_no).expect("keys are written from a valid VehicleNumber")
+}
+
+/// The plate as it was entered, from the `vehicle_no` attribute of a vehicle
+/// or history item, or read back from its `key` when it has none.
+pub fn vehicle_no_from_item(item: &HashMap<String, AttributeValue>, key: &str) -> VehicleNumber {
+    item.get("vehicle_no")
+        .and_then(|vehicle_no| vehicle_no.as_s().ok())
+        .and_then(|vehicle_no| vehicle_no.parse().ok())
+        .unwrap_or_else(|| vehicle_no_from_key(item.get(key).unwrap()))
+}
+
+/// `GSI2PK` shared by every vehicle of an organization.
+pub fn vehicle_partition_key(org_id: &str) -> AttributeValue {
+    AttributeValue::S(format!("ORG#{}#VEHICLE", org_id))
 }
 
+/// `GSI2PK` of the organization's sold vehicles, which keeps them out of the
+/// listings and fee-due queries on `vehicle_partition_key`.
+pub fn sold_vehicle_partition_key(org_id: &str) -> AttributeValue {
+    AttributeValue::S(format!("ORG#{}#SOLD", org_id))
+}
+
+/// `PK` of the organization's `SEARCH` items.
+pub fn search_partition_key(org_id: &str) -> AttributeValue {
+    AttributeValue::S(format!("ORG#{}#SEARCH", org_id))
+}
+
+/// `SK` of a `SEARCH` item from `VehicleNumber::as_key`, or its `LSI1SK`
+/// from the serial.
 pub fn vehicle_search_key(id: &str) -> AttributeValue {
-    let id = &*id.split("-").collect::<Vec<&str>>().join("");
     AttributeValue::S(format!("SEARCH#{}", id))
 }
 
 pub fn vehicle_from_item(vehicle_itme: &HashMap<String, AttributeValue>) -> Vehicle {
-    let vehicle_no = &vehicle_itme.get("SK").unwrap().as_s().unwrap()[4..];
-    let is_number = (&vehicle_no[5..6]).chars().next().unwrap().is_numeric();
-    let vehicle_no = format!(
-        "{}-{}-{}-{}",
-        &vehicle_no[..3],
-        if is_number {
-            &vehicle_no[3..5]
-        } else {
-            &vehicle_no[3..6]
-        },
-        if is_number {
-            &vehicle_no[5..7]
-        } else {
-            &vehicle_no[6..8]
-        },
-        if is_number {
-            &vehicle_no[7..]
-        } else {
-            &vehicle_no[8..]
-        }
-    );
+    let vehicle_no = vehicle_no_from_item(vehicle_itme, "SK");
     let owner = vehicle_itme
         .get("owner")
         .unwrap()
@@ -160,16 +200,84 @@ pub fn vehicle_from_item(vehicle_itme: &HashMap<String, AttributeValue>) -> Vehi
 
     // let tax_date = NaiveDate::parse_from_str(&tax_date, "%Y-%m-%d").unwrap();
 
-    Vehicle::new(
-        vehicle_no,
-        owner,
-        tax_date,
-        fitness_date,
-        insurance_date,
-        route_date,
-    )
+    Vehicle {
+        vehicle_class: vehicle_itme
+            .get("vehicle_class")
+            .and_then(|vehicle_class| vehicle_class.as_s().ok())
+            .cloned(),
+        ..Vehicle::new(
+            vehicle_no,
+            owner,
+            tax_date,
+            fitness_date,
+            insurance_date,
+            route_date,
+        )
+    }
+}
+
+/// A vehicle with the bookkeeping attributes `vehicle_from_item` leaves out.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct VehicleDetails {
+    #[serde(flatten)]
+    pub vehicle: Vehicle,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    pub sold: bool,
+    pub buyer: Option<String>,
+    pub sold_at: Option<String>,
+    pub days_remaining: DaysRemaining,
+}
+
+/// Days from today until each fee expires, negative once it is overdue.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DaysRemaining {
+    pub tax: Option<i64>,
+    pub fitness: Option<i64>,
+    pub insurance: Option<i64>,
+    pub route: Option<i64>,
+}
+
+impl DaysRemaining {
+    pub fn new(vehicle: &Vehicle) -> Self {
+        let today = Local::now().date_naive();
+        let days = |date: &str| {
+            NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .ok()
+                .map(|date| (date - today).num_days())
+        };
+        Self {
+            tax: days(&vehicle.tax_date),
+            fitness: days(&vehicle.fitness_date),
+            insurance: days(&vehicle.insurance_date),
+            route: days(&vehicle.route_date),
+        }
+    }
+}
+
+pub fn vehicle_details_from_item(vehicle_item: &HashMap<String, AttributeValue>) -> VehicleDetails {
+    let vehicle = vehicle_from_item(vehicle_item);
+    let text = |name: &str| {
+        vehicle_item
+            .get(name)
+            .and_then(|value| value.as_s().ok())
+            .cloned()
+    };
+    VehicleDetails {
+        created_at: text("created_at"),
+        updated_at: text("updated_at"),
+        sold: vehicle_item
+            .get("Sold")
+            .and_then(|sold| sold.as_bool().ok())
+            .copied()
+            .unwrap_or(false),
+        buyer: text("buyer"),
+        sold_at: text("sold_at"),
+        days_remaining: DaysRemaining::new(&vehicle),
+        vehicle,
+    }
 }
 
 pub fn vehicle_repo(items: Vec<HashMap<String, AttributeValue>>) -> Vec<Vehicle> {
-    items.iter().map(|item| vehicle_from_item(item)).collect()
+    items.iter().map(vehicle_from_item).collect()
 }
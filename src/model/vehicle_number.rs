@@ -0,0 +1,582 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use utoipa::{
+    openapi::{schema::Type, ObjectBuilder, RefOr, Schema},
+    PartialSchema, ToSchema,
+};
+
+/// A Bangladeshi registration plate such as `DHA-KA-11-1234` or
+/// `DHAKA METRO-GA-11-1234`: the district or metro area, the class letter,
+/// a two digit series and a four digit serial.
+///
+/// Parsing is case-insensitive and takes dashes or spaces between the parts,
+/// or the class and digits written together as in `DHA KA111234`, or a
+/// compact `DHAKA111234`. Plates in Bengali script, e.g.
+/// `ঢাকা মেট্রো-গ ১১-১২৩৪`, are transliterated part by part, and a three
+/// letter abbreviation such as `DHA` is spelled out as the district name, so
+/// `DHA-KA-11-1234`, `DHAKA KA 11 1234` and `ঢাকা-ক-১১-১২৩৪` are the same
+/// vehicle with the same key. The plate keeps the form it was entered in for
+/// display, equality only looks at the parts.
+#[derive(Debug, Clone)]
+pub struct VehicleNumber {
+    area: String,
+    class: String,
+    series: String,
+    serial: String,
+    display: String,
+}
+
+/// District and metro area names in Bengali, with the spelling used on the
+/// Latin plates.
+const AREAS: &[(&str, &str)] = &[
+    ("মেট্রো", "METRO"),
+    ("ঢাকা", "DHAKA"),
+    ("গাজীপুর", "GAZIPUR"),
+    ("নারায়ণগঞ্জ", "NARAYANGANJ"),
+    ("নরসিংদী", "NARSINGDI"),
+    ("মুন্সীগঞ্জ", "MUNSHIGANJ"),
+    ("মানিকগঞ্জ", "MANIKGANJ"),
+    ("টাঙ্গাইল", "TANGAIL"),
+    ("কিশোরগঞ্জ", "KISHOREGANJ"),
+    ("ফরিদপুর", "FARIDPUR"),
+    ("গোপালগঞ্জ", "GOPALGANJ"),
+    ("মাদারীপুর", "MADARIPUR"),
+    ("শরীয়তপুর", "SHARIATPUR"),
+    ("রাজবাড়ী", "RAJBARI"),
+    ("চট্টগ্রাম", "CHATTOGRAM"),
+    ("কক্সবাজার", "COXSBAZAR"),
+    ("কুমিল্লা", "CUMILLA"),
+    ("ফেনী", "FENI"),
+    ("নোয়াখালী", "NOAKHALI"),
+    ("লক্ষ্মীপুর", "LAKSHMIPUR"),
+    ("চাঁদপুর", "CHANDPUR"),
+    ("ব্রাহ্মণবাড়িয়া", "BRAHMANBARIA"),
+    ("রাঙ্গামাটি", "RANGAMATI"),
+    ("খাগড়াছড়ি", "KHAGRACHHARI"),
+    ("বান্দরবান", "BANDARBAN"),
+    ("রাজশাহী", "RAJSHAHI"),
+    ("নাটোর", "NATORE"),
+    ("নওগাঁ", "NAOGAON"),
+    ("চাঁপাইনবাবগঞ্জ", "CHAPAINAWABGANJ"),
+    ("পাবনা", "PABNA"),
+    ("সিরাজগঞ্জ", "SIRAJGANJ"),
+    ("বগুড়া", "BOGURA"),
+    ("জয়পুরহাট", "JOYPURHAT"),
+    ("খুলনা", "KHULNA"),
+    ("যশোর", "JASHORE"),
+    ("সাতক্ষীরা", "SATKHIRA"),
+    ("বাগেরহাট", "BAGERHAT"),
+    ("নড়াইল", "NARAIL"),
+    ("মাগুরা", "MAGURA"),
+    ("ঝিনাইদহ", "JHENAIDAH"),
+    ("কুষ্টিয়া", "KUSHTIA"),
+    ("চুয়াডাঙ্গা", "CHUADANGA"),
+    ("মেহেরপুর", "MEHERPUR"),
+    ("বরিশাল", "BARISHAL"),
+    ("পটুয়াখালী", "PATUAKHALI"),
+    ("ভোলা", "BHOLA"),
+    ("পিরোজপুর", "PIROJPUR"),
+    ("বরগুনা", "BARGUNA"),
+    ("ঝালকাঠি", "JHALOKATHI"),
+    ("সিলেট", "SYLHET"),
+    ("মৌলভীবাজার", "MOULVIBAZAR"),
+    ("হবিগঞ্জ", "HABIGANJ"),
+    ("সুনামগঞ্জ", "SUNAMGANJ"),
+    ("রংপুর", "RANGPUR"),
+    ("দিনাজপুর", "DINAJPUR"),
+    ("গাইবান্ধা", "GAIBANDHA"),
+    ("কুড়িগ্রাম", "KURIGRAM"),
+    ("লালমনিরহাট", "LALMONIRHAT"),
+    ("নীলফামারী", "NILPHAMARI"),
+    ("পঞ্চগড়", "PANCHAGARH"),
+    ("ঠাকুরগাঁও", "THAKURGAON"),
+    ("ময়মনসিংহ", "MYMENSINGH"),
+    ("জামালপুর", "JAMALPUR"),
+    ("শেরপুর", "SHERPUR"),
+    ("নেত্রকোণা", "NETROKONA"),
+];
+
+/// Abbreviations that start more than one district name, with the district
+/// they stand for: the divisional city, or the bigger district.
+const SHARED_ABBREVIATIONS: &[(&str, &str)] = &[
+    ("BAR", "BARISHAL"),
+    ("CHA", "CHATTOGRAM"),
+    ("NAR", "NARAYANGANJ"),
+    ("RAJ", "RAJSHAHI"),
+    ("RAN", "RANGPUR"),
+];
+
+/// Class letters, each with its own transliteration so no two classes
+/// share a key.
+const CLASSES: &[(&str, &str)] = &[
+    ("অ", "A"),
+    ("ই", "I"),
+    ("উ", "U"),
+    ("এ", "E"),
+    ("ক", "KA"),
+    ("খ", "KHA"),
+    ("ক্ষ", "KSHA"),
+    ("গ", "GA"),
+    ("ঘ", "GHA"),
+    ("ঙ", "NGA"),
+    ("চ", "CHA"),
+    ("ছ", "CHHA"),
+    ("জ", "JA"),
+    ("ঝ", "JHA"),
+    ("ট", "TTA"),
+    ("ঠ", "TTHA"),
+    ("ড", "DDA"),
+    ("ঢ", "DDHA"),
+    ("ত", "TA"),
+    ("থ", "THA"),
+    ("দ", "DA"),
+    ("ধ", "DHA"),
+    ("ন", "NA"),
+    ("প", "PA"),
+    ("ফ", "PHA"),
+    ("ব", "BA"),
+    ("ভ", "BHA"),
+    ("ম", "MA"),
+    ("য", "YA"),
+    ("র", "RA"),
+    ("ল", "LA"),
+    ("শ", "SHA"),
+    ("ষ", "SSA"),
+    ("স", "SA"),
+    ("হ", "HA"),
+];
+
+/// Spells out the precomposed Bengali letters (`ড়`, `য়`, `ো`, ...) and drops
+/// the joiners, so text typed on different keyboards compares equal.
+fn decompose(text: &str) -> String {
+    text.chars()
+        .filter(|c| !matches!(c, '\u{200C}' | '\u{200D}'))
+        .fold(String::new(), |mut text, c| {
+            match c {
+                '\u{09DC}' => text.push_str("\u{09A1}\u{09BC}"),
+                '\u{09DD}' => text.push_str("\u{09A2}\u{09BC}"),
+                '\u{09DF}' => text.push_str("\u{09AF}\u{09BC}"),
+                '\u{09CB}' => text.push_str("\u{09C7}\u{09BE}"),
+                '\u{09CC}' => text.push_str("\u{09C7}\u{09D7}"),
+                c => text.push(c),
+            }
+            text
+        })
+}
+
+/// One part of a plate in Latin capitals: Bengali area names and class
+/// letters come from the tables, Bengali digits become ASCII ones.
+fn transliterate(part: &str) -> String {
+    let part = decompose(part);
+    let known = |table: &[(&str, &'static str)]| {
+        table
+            .iter()
+            .find(|(bengali, _)| decompose(bengali) == part)
+            .map(|(_, latin)| latin.to_string())
+    };
+    known(AREAS).or_else(|| known(CLASSES)).unwrap_or_else(|| {
+        part.chars()
+            .map(|c| match c {
+                '\u{09E6}'..='\u{09EF}' => char::from(b'0' + (c as u32 - 0x09E6) as u8),
+                c => c,
+            })
+            .collect::<String>()
+            .to_uppercase()
+    })
+}
+
+/// The area name a part of the area stands for: a three letter abbreviation
+/// becomes the one district it starts, anything else is kept as it is.
+fn area_name(part: &str) -> String {
+    if part.len() != 3 {
+        return part.to_string();
+    }
+    let shared = SHARED_ABBREVIATIONS
+        .iter()
+        .find(|(abbreviation, _)| *abbreviation == part)
+        .map(|(_, name)| name.to_string());
+    let mut names = AREAS.iter().filter(|(_, name)| name.starts_with(part));
+    shared.unwrap_or_else(|| match (names.next(), names.next()) {
+        (Some((_, name)), None) => name.to_string(),
+        _ => part.to_string(),
+    })
+}
+
+fn is_known_area(name: &str) -> bool {
+    AREAS.iter().any(|(_, area)| *area == name)
+}
+
+fn is_known_class(class: &str) -> bool {
+    CLASSES.iter().any(|(_, latin)| *latin == class)
+}
+
+/// Splits a plate at dashes and spaces and transliterates every part.
+fn parts(plate: &str) -> Vec<String> {
+    plate
+        .split(|c: char| c == '-' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .map(transliterate)
+        .collect()
+}
+
+impl VehicleNumber {
+    pub fn area(&self) -> &str {
+        &self.area
+    }
+
+    pub fn class(&self) -> &str {
+        &self.class
+    }
+
+    pub fn series(&self) -> &str {
+        &self.series
+    }
+
+    pub fn serial(&self) -> &str {
+        &self.serial
+    }
+
+    /// The plate in Latin capitals, e.g. `DHAKA METRO-GA-11-1234`, whatever
+    /// script it was entered in.
+    pub fn latin(&self) -> String {
+        format!(
+            "{}-{}-{}-{}",
+            self.area, self.class, self.series, self.serial
+        )
+    }
+
+    /// Reads a plate back from `as_key`. Keys don't keep the entered form, so
+    /// it is shown in Latin.
+    pub fn from_key(key: &str) -> Option<Self> {
+        let mut vehicle_no = key.parse::<Self>().ok()?;
+        vehicle_no.display = vehicle_no.latin();
+        Some(vehicle_no)
+    }
+
+    /// The form used inside `CAR#` and `SEARCH#` keys, every part separated
+    /// by a dash, e.g. `DHAKA-METRO-GA-11-1234`. It parses back to the same
+    /// number.
+    pub fn as_key(&self) -> String {
+        format!(
+            "{}-{}-{}-{}",
+            self.area.replace(' ', "-"),
+            self.class,
+            self.series,
+            self.serial
+        )
+    }
+
+    /// Normalizes a partial plate the way `as_key` writes a full one, so it
+    /// can be matched against the start of the keys.
+    pub fn key_prefix(query: &str) -> String {
+        let mut parts = parts(query);
+        // The last part may still be typed, only a finished area is spelled out.
+        if parts.len() > 1 {
+            parts[0] = area_name(&parts[0]);
+        }
+        parts.join("-")
+    }
+
+    fn from_parts(area: &[String], class: &str, series: &str, serial: &str) -> Option<Self> {
+        let letters = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_uppercase());
+        let digits =
+            |part: &str, len: usize| part.len() == len && part.chars().all(|c| c.is_ascii_digit());
+
+        match !area.is_empty()
+            && area.iter().all(|part| letters(part))
+            && letters(class)
+            && class.len() <= 4
+            && digits(series, 2)
+            && digits(serial, 4)
+        {
+            true => Some(Self {
+                area: area
+                    .iter()
+                    .map(|part| area_name(part))
+                    .collect::<Vec<String>>()
+                    .join(" "),
+                class: class.to_string(),
+                series: series.to_string(),
+                serial: serial.to_string(),
+                display: String::new(),
+            }),
+            false => None,
+        }
+    }
+
+    /// `DHA KA111234` or `DHA KA 111234`: the area apart, the class and the
+    /// digits written together.
+    fn from_number(area: &[String], number: &str) -> Option<Self> {
+        let letters = number
+            .chars()
+            .take_while(|c| c.is_ascii_uppercase())
+            .count();
+        if number.len() != letters + 6 {
+            return None;
+        }
+        let (class, rest) = number.split_at(letters);
+        let (series, serial) = rest.split_at(2);
+        match (class, area) {
+            ("", [area @ .., class]) => Self::from_parts(area, class, series, serial),
+            (class, area) => Self::from_parts(area, class, series, serial),
+        }
+    }
+
+    /// `DHAKA111234` or `DHAKAKA111234`: the area, the class, then series and
+    /// serial. The area is the longest known district name or abbreviation,
+    /// optionally followed by `METRO`, that leaves a known class letter. An
+    /// unknown area is taken to be three letters long.
+    fn from_compact(plate: &str) -> Option<Self> {
+        if !plate.is_ascii() {
+            return None;
+        }
+        let letters = plate.chars().take_while(|c| c.is_ascii_uppercase()).count();
+        if letters < 4 || plate.len() != letters + 6 {
+            return None;
+        }
+        let (letters, rest) = plate.split_at(letters);
+        let (series, serial) = rest.split_at(2);
+
+        let known = (1..letters.len()).rev().find_map(|end| {
+            let (area, class) = letters.split_at(end);
+            let area = match area.strip_suffix("METRO") {
+                Some(district) if !district.is_empty() => {
+                    vec![district.to_string(), "METRO".to_string()]
+                }
+                _ => vec![area.to_string()],
+            };
+            match is_known_class(class) && is_known_area(&area_name(&area[0])) {
+                true => Self::from_parts(&area, class, series, serial),
+                false => None,
+            }
+        });
+        known.or_else(|| {
+            let (area, class) = letters.split_at(3);
+            Self::from_parts(&[area.to_string()], class, series, serial)
+        })
+    }
+}
+
+impl fmt::Display for VehicleNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.display)
+    }
+}
+
+impl PartialEq for VehicleNumber {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for VehicleNumber {}
+
+impl PartialOrd for VehicleNumber {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for VehicleNumber {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (&self.area, &self.class, &self.series, &self.serial).cmp(&(
+            &other.area,
+            &other.class,
+            &other.series,
+            &other.serial,
+        ))
+    }
+}
+
+impl Hash for VehicleNumber {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (&self.area, &self.class, &self.series, &self.serial).hash(state);
+    }
+}
+
+impl FromStr for VehicleNumber {
+    type Err = String;
+
+    fn from_str(plate: &str) -> Result<Self, Self::Err> {
+        let plate = plate.trim();
+        let vehicle_no = match parts(plate).as_slice() {
+            [compact] => Self::from_compact(compact),
+            [area @ .., class, series, serial] if !area.is_empty() => {
+                Self::from_parts(area, class, series, serial)
+            }
+            [area @ .., number] => Self::from_number(area, number),
+            _ => None,
+        };
+
+        vehicle_no
+            .map(|vehicle_no| Self {
+                display: plate.to_string(),
+                ..vehicle_no
+            })
+            .ok_or_else(|| {
+                format!(
+                    "{} is not a valid vehicle number, expected something like DHA-KA-11-1234 \
+                     or ঢাকা মেট্রো-গ-১১-১২৩৪",
+                    plate
+                )
+            })
+    }
+}
+
+impl Serialize for VehicleNumber {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for VehicleNumber {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Sent and received as the plate text, in Latin or Bengali script.
+impl PartialSchema for VehicleNumber {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(Type::String)
+            .description(Some(
+                "Registration plate, e.g. `DHA-KA-11-1234` or `ঢাকা মেট্রো-গ-১১-১২৩৪`.",
+            ))
+            .examples(["DHA-KA-11-1234"])
+            .into()
+    }
+}
+
+impl ToSchema for VehicleNumber {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plate(text: &str) -> VehicleNumber {
+        text.parse()
+            .unwrap_or_else(|err| panic!("{} should parse: {}", text, err))
+    }
+
+    #[test]
+    fn parses_dashed_spaced_and_compact_latin() {
+        for text in [
+            "DHA-KA-11-1234",
+            "dha ka 11 1234",
+            "DHA-KA 11-1234",
+            "DHAKA-KA-11-1234",
+            "DHA KA111234",
+            "DHA KA 111234",
+            "DHAKA111234",
+            "DHAKAKA111234",
+        ] {
+            let vehicle_no = plate(text);
+            assert_eq!(vehicle_no.area(), "DHAKA", "{}", text);
+            assert_eq!(vehicle_no.class(), "KA", "{}", text);
+            assert_eq!(vehicle_no.series(), "11", "{}", text);
+            assert_eq!(vehicle_no.serial(), "1234", "{}", text);
+            assert_eq!(vehicle_no.as_key(), "DHAKA-KA-11-1234", "{}", text);
+        }
+    }
+
+    #[test]
+    fn keeps_the_entered_form_for_display() {
+        assert_eq!(plate(" dha ka 11 1234 ").to_string(), "dha ka 11 1234");
+        assert_eq!(plate("ঢাকা-ক-১১-১২৩৪").to_string(), "ঢাকা-ক-১১-১২৩৪");
+    }
+
+    #[test]
+    fn transliterates_bengali_digits_and_class_letters() {
+        let vehicle_no = plate("ঢাকা-ক-১১-১২৩৪");
+        assert_eq!(vehicle_no.latin(), "DHAKA-KA-11-1234");
+
+        assert_eq!(plate("চট্টগ্রাম-খ ০৫-০০৯৯").latin(), "CHATTOGRAM-KHA-05-0099");
+        assert_eq!(plate("রাজশাহী-ক্ষ-২০-৪৫৬৭").class(), "KSHA");
+    }
+
+    #[test]
+    fn parses_multi_word_metro_areas() {
+        let latin = plate("DHAKA METRO-GA-11-1234");
+        assert_eq!(latin.area(), "DHAKA METRO");
+        assert_eq!(latin.as_key(), "DHAKA-METRO-GA-11-1234");
+
+        assert_eq!(plate("ঢাকা মেট্রো-গ ১১-১২৩৪"), latin);
+        assert_eq!(plate("DHA METRO GA 11 1234"), latin);
+        assert_eq!(plate("DHAKAMETROGA111234"), latin);
+    }
+
+    #[test]
+    fn bengali_and_latin_spellings_are_the_same_vehicle() {
+        let bengali = plate("ঢাকা-ক-১১-১২৩৪");
+        for text in ["DHA-KA-11-1234", "DHA KA111234", "DHAKAKA111234"] {
+            assert_eq!(plate(text), bengali, "{}", text);
+            assert_eq!(plate(text).as_key(), bengali.as_key(), "{}", text);
+        }
+
+        assert_eq!(plate("CHA-KA-11-1234"), plate("চট্টগ্রাম-ক-১১-১২৩৪"));
+        assert_ne!(plate("CHA-KA-11-1234"), plate("চাঁদপুর-ক-১১-১২৩৪"));
+    }
+
+    #[test]
+    fn key_round_trips() {
+        for text in [
+            "DHA-KA-11-1234",
+            "ঢাকা মেট্রো-গ ১১-১২৩৪",
+            "KHULNA-CHHA-09-0001",
+            "XYZ-KA-11-1234",
+        ] {
+            let vehicle_no = plate(text);
+            let from_key = VehicleNumber::from_key(&vehicle_no.as_key()).unwrap();
+            assert_eq!(from_key, vehicle_no, "{}", text);
+            assert_eq!(from_key.as_key(), vehicle_no.as_key(), "{}", text);
+            assert_eq!(from_key.to_string(), vehicle_no.latin(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn key_prefix_matches_the_start_of_keys() {
+        assert_eq!(VehicleNumber::key_prefix("dha-ka"), "DHAKA-KA");
+        assert_eq!(VehicleNumber::key_prefix("dhaka metro"), "DHAKA-METRO");
+        assert_eq!(VehicleNumber::key_prefix("ঢাকা মেট্রো-গ"), "DHAKA-METRO-GA");
+        assert_eq!(VehicleNumber::key_prefix("DHA"), "DHA");
+    }
+
+    #[test]
+    fn rejects_invalid_plates() {
+        for text in [
+            "",
+            "DHA",
+            "DHA-KA-1-1234",
+            "DHA-KA-11-123",
+            "DHA-KA-11-12345",
+            "DHA-11-1234",
+            "DHA-KA1-11-1234",
+            "DHA-KAKAKA-11-1234",
+            "12-KA-11-1234",
+            "DHA111234",
+            "DHAKA11123",
+            "ঢাকা-ক-১১-১২৩",
+        ] {
+            assert!(text.parse::<VehicleNumber>().is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn serde_uses_the_plate_text() {
+        let vehicle_no: VehicleNumber = serde_json::from_str("\"dha-ka-11-1234\"").unwrap();
+        assert_eq!(
+            serde_json::to_string(&vehicle_no).unwrap(),
+            "\"dha-ka-11-1234\""
+        );
+        assert!(serde_json::from_str::<VehicleNumber>("\"not a plate\"").is_err());
+    }
+}
@@ -0,0 +1,85 @@
+use std::{fs::OpenOptions, io::Write, path::PathBuf};
+
+use async_trait::async_trait;
+use aws_config::BehaviorVersion;
+use chrono::{SecondsFormat, Utc};
+use lambda_http::{tracing, Error};
+
+use crate::model::reminder::{Recipient, Reminder};
+
+/// Delivers a reminder to one of its recipients.
+#[async_trait]
+pub trait Notifier {
+    async fn notify(&self, reminder: &Reminder, recipient: &Recipient) -> Result<(), Error>;
+}
+
+/// Texts every recipient's phone through SNS.
+pub struct SmsNotifier {
+    client: aws_sdk_sns::Client,
+}
+
+impl SmsNotifier {
+    pub fn new(client: aws_sdk_sns::Client) -> Self {
+        Self { client }
+    }
+
+    pub async fn from_env() -> Self {
+        let sdk_config = aws_config::defaults(BehaviorVersion::latest()).load().await;
+        Self::new(aws_sdk_sns::Client::new(&sdk_config))
+    }
+}
+
+#[async_trait]
+impl Notifier for SmsNotifier {
+    async fn notify(&self, reminder: &Reminder, recipient: &Recipient) -> Result<(), Error> {
+        self.client
+            .publish()
+            .phone_number(&recipient.phone)
+            .message(reminder.message())
+            .send()
+            .await
+            .map(|_output| ())
+            .map_err(|err| {
+                aws_sdk_sns::error::DisplayErrorContext(err)
+                    .to_string()
+                    .into()
+            })
+    }
+}
+
+/// Writes reminders to the log, and to a file when `path` is set, instead of
+/// sending them. Meant for local runs.
+pub struct LogNotifier {
+    path: Option<PathBuf>,
+}
+
+impl LogNotifier {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self { path }
+    }
+
+    /// Appends to the file named by `REMINDER_LOG` when it is set.
+    pub fn from_env() -> Self {
+        Self::new(std::env::var("REMINDER_LOG").ok().map(PathBuf::from))
+    }
+}
+
+#[async_trait]
+impl Notifier for LogNotifier {
+    async fn notify(&self, reminder: &Reminder, recipient: &Recipient) -> Result<(), Error> {
+        let message = reminder.message();
+        tracing::info!(phone = recipient.phone, "REMINDER: {}", message);
+
+        if let Some(path) = &self.path {
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(
+                file,
+                "{}\t{}\t{}",
+                Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+                recipient.phone,
+                message
+            )?;
+        }
+        Ok(())
+    }
+}
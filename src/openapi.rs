@@ -0,0 +1,133 @@
+use utoipa::{
+    openapi::security::{ApiKey, ApiKeyValue, SecurityScheme},
+    Modify, OpenApi, ToSchema,
+};
+
+use crate::{
+    handler::{calendar, history, openapi, organization, session, user, vehicle},
+    model::import::ImportMode,
+};
+
+/// `{"message": "..."}`, the body of the writes that return nothing else.
+#[derive(ToSchema)]
+pub struct Message {
+    pub message: String,
+}
+
+/// Raw bytes of an XLSX or PDF download.
+#[derive(ToSchema)]
+#[schema(value_type = String, format = Binary)]
+pub struct Binary(pub Vec<u8>);
+
+/// The OpenAPI document of the routes `router::route` serves, built from the
+/// `#[utoipa::path]` of every handler and the schemas of the types they read
+/// and write.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Vehicle Management API",
+        description = "Fee expiries, payments and history of the vehicles of an organization.",
+        license(name = "MIT")
+    ),
+    paths(
+        user::signup,
+        user::change_password,
+        session::login,
+        session::refresh_session,
+        session::delete_session,
+        session::list_sessions,
+        session::revoke_session,
+        session::revoke_other_sessions,
+        vehicle::get_vehicles,
+        vehicle::get_vehicle,
+        vehicle::search_vehicles,
+        vehicle::export_vehicles,
+        vehicle::add_vehicle,
+        vehicle::update_vehicle,
+        vehicle::sell_vehicle,
+        vehicle::import_vehicles,
+        vehicle::pay_fee,
+        history::get_history,
+        history::export_history,
+        history::get_vehicle_history,
+        history::get_spending_report,
+        vehicle::get_due_report,
+        history::undo_history,
+        organization::create_organization,
+        organization::get_organizations,
+        organization::add_member,
+        organization::set_role,
+        calendar::create_calendar_feed,
+        calendar::get_calendar,
+        openapi::get_openapi,
+    ),
+    // Schemas only used by query parameters are not collected from the paths.
+    components(schemas(ImportMode)),
+    modifiers(&TokenAuth)
+)]
+pub struct ApiDoc;
+
+/// The session token goes in the `Authorization` header as it is, without a
+/// `Bearer` prefix.
+struct TokenAuth;
+
+impl Modify for TokenAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        openapi
+            .components
+            .get_or_insert_with(Default::default)
+            .add_security_scheme(
+                "token",
+                SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::with_description(
+                    "Authorization",
+                    "Token returned by `POST /sessions`",
+                ))),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The `("METHOD", "/path")` arms of `router::route`.
+    fn routes() -> Vec<(String, String)> {
+        include_str!("router.rs")
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("(\""))
+            .filter_map(|line| line.split_once(") =>"))
+            .map(|(route, _)| {
+                let (method, path) = route.split_once("\", \"").unwrap();
+                (
+                    method.to_lowercase(),
+                    path.trim_end_matches('"').to_string(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_routed_path_is_documented() {
+        let document = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        let routes = routes();
+        assert_eq!(routes.len(), 30);
+
+        for (method, path) in routes {
+            assert!(
+                document["paths"][&path][&method].is_object(),
+                "{} {} is not documented",
+                method,
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn writes_use_the_token_scheme() {
+        let document = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        assert_eq!(
+            document["components"]["securitySchemes"]["token"]["in"],
+            "header"
+        );
+    }
+}
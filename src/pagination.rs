@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+
+use aws_sdk_dynamodb::types::AttributeValue;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use lambda_http::aws_lambda_events::query_map::QueryMap;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::error::{DataAccessError, FieldError};
+
+/// Key attributes a cursor is allowed to carry. Anything else in a decoded
+/// cursor is rejected so clients cannot smuggle arbitrary attributes into
+/// `ExclusiveStartKey`.
+const CURSOR_ATTRIBUTES: [&str; 8] = [
+    "PK", "SK", "GSI1PK", "GSI1SK", "GSI2PK", "GSI3PK", "GSI3SK", "LSI1SK",
+];
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageRequest {
+    /// Most items to return.
+    #[param(minimum = 1)]
+    pub limit: Option<i32>,
+    /// `next_cursor` of the previous page.
+    pub cursor: Option<String>,
+}
+
+impl PageRequest {
+    pub fn new(limit: Option<i32>, cursor: Option<String>) -> Self {
+        Self { limit, cursor }
+    }
+
+    /// Reads `limit` and `cursor` from the query string of a request.
+    pub fn from_query(params: Option<&QueryMap>) -> Result<Self, FieldError> {
+        let params = match params {
+            Some(params) => params,
+            None => return Ok(Self::default()),
+        };
+
+        let limit = match params.first("limit") {
+            Some(limit) => match limit.trim().parse::<i32>() {
+                Ok(limit) if limit > 0 => Some(limit),
+                _ => return Err(FieldError::new("limit", "limit must be a positive number")),
+            },
+            None => None,
+        };
+
+        Ok(Self::new(limit, params.first("cursor").map(str::to_string)))
+    }
+
+    /// The decoded cursor for a query whose `LastEvaluatedKey` holds
+    /// `key_attributes` and whose partition is `partition`. A cursor of
+    /// another index or partition, e.g. another organization, would make
+    /// DynamoDB fail, so it is rejected as not valid.
+    pub fn exclusive_start_key(
+        &self,
+        key_attributes: &[&str],
+        partition: (&str, &AttributeValue),
+    ) -> Result<Option<HashMap<String, AttributeValue>>, DataAccessError> {
+        let key = match self.cursor.as_deref() {
+            Some(cursor) => decode_cursor(cursor)?,
+            None => return Ok(None),
+        };
+
+        let (partition_name, partition_value) = partition;
+        match key.len() == key_attributes.len()
+            && key_attributes.iter().all(|name| key.contains_key(*name))
+            && key.get(partition_name) == Some(partition_value)
+        {
+            true => Ok(Some(key)),
+            false => Err(DataAccessError::Validation(
+                "The cursor is not valid!!".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Pass as `cursor` to get the next page, `null` on the last one.
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    pub fn new(items: Vec<T>, last_evaluated_key: Option<HashMap<String, AttributeValue>>) -> Self {
+        Self {
+            items,
+            next_cursor: last_evaluated_key.as_ref().map(encode_cursor),
+        }
+    }
+}
+
+pub fn encode_cursor(key: &HashMap<String, AttributeValue>) -> String {
+    let key = key
+        .iter()
+        .filter_map(|(name, value)| value.as_s().ok().map(|value| (name, value)))
+        .collect::<HashMap<&String, &String>>();
+
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(&key).unwrap())
+}
+
+pub fn decode_cursor(cursor: &str) -> Result<HashMap<String, AttributeValue>, DataAccessError> {
+    let key = URL_SAFE_NO_PAD
+        .decode(cursor.trim())
+        .ok()
+        .and_then(|json| serde_json::from_slice::<HashMap<String, String>>(&json).ok())
+        .ok_or_else(|| DataAccessError::Validation("The cursor is not valid!!".to_string()))?;
+
+    if key.is_empty()
+        || key
+            .keys()
+            .any(|name| !CURSOR_ATTRIBUTES.contains(&name.as_str()))
+    {
+        return Err(DataAccessError::Validation(
+            "The cursor is not valid!!".to_string(),
+        ));
+    }
+
+    Ok(key
+        .into_iter()
+        .map(|(name, value)| (name, AttributeValue::S(value)))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(pairs: &[(&str, &str)]) -> HashMap<String, AttributeValue> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), AttributeValue::S(value.to_string())))
+            .collect()
+    }
+
+    fn page(key: &HashMap<String, AttributeValue>) -> PageRequest {
+        PageRequest::new(None, Some(encode_cursor(key)))
+    }
+
+    #[test]
+    fn cursor_round_trips() {
+        let last = key(&[
+            ("PK", "ORG#1#CAR#DHAKAKA111234"),
+            ("SK", "CAR#DHAKAKA111234"),
+            ("GSI2PK", "ORG#1#VEHICLE"),
+        ]);
+
+        assert_eq!(decode_cursor(&encode_cursor(&last)).unwrap(), last);
+        assert_eq!(
+            page(&last)
+                .exclusive_start_key(&["PK", "SK", "GSI2PK"], ("GSI2PK", &last["GSI2PK"]))
+                .unwrap(),
+            Some(last)
+        );
+    }
+
+    #[test]
+    fn no_cursor_starts_from_the_beginning() {
+        let partition = AttributeValue::S("ORG#1#VEHICLE".to_string());
+        assert_eq!(
+            PageRequest::default()
+                .exclusive_start_key(&["PK", "SK", "GSI2PK"], ("GSI2PK", &partition))
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_cursors() {
+        let smuggled = URL_SAFE_NO_PAD.encode(br#"{"PK":"ORG#1","owner":"Rahim"}"#);
+        for cursor in ["not a cursor", "", "e30", smuggled.as_str()] {
+            assert!(
+                matches!(decode_cursor(cursor), Err(DataAccessError::Validation(_))),
+                "{}",
+                cursor
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_cursors_of_another_partition_or_index() {
+        let last = key(&[
+            ("PK", "ORG#1#CAR#DHAKAKA111234"),
+            ("SK", "CAR#DHAKAKA111234"),
+            ("GSI2PK", "ORG#1#VEHICLE"),
+        ]);
+        let other_org = AttributeValue::S("ORG#2#VEHICLE".to_string());
+        let history = AttributeValue::S("ORG#1#HISTORY".to_string());
+
+        assert!(matches!(
+            page(&last).exclusive_start_key(&["PK", "SK", "GSI2PK"], ("GSI2PK", &other_org)),
+            Err(DataAccessError::Validation(_))
+        ));
+        assert!(matches!(
+            page(&last)
+                .exclusive_start_key(&["PK", "SK", "GSI3PK", "GSI3SK"], ("GSI3PK", &history)),
+            Err(DataAccessError::Validation(_))
+        ));
+    }
+}
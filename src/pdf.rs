@@ -0,0 +1,292 @@
+use pdf_writer::{Content, Name, Pdf, Rect, Ref, Str};
+
+use crate::model::report::{DueReport, DueVehicle};
+
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 40.0;
+const ROW_HEIGHT: f32 = 16.0;
+
+const REGULAR: Name = Name(b"F1");
+const BOLD: Name = Name(b"F2");
+
+const BLACK: (f32, f32, f32) = (0.0, 0.0, 0.0);
+const GREY: (f32, f32, f32) = (0.4, 0.4, 0.4);
+const DARK_RED: (f32, f32, f32) = (0.6, 0.0, 0.0);
+const LIGHT_RED: (f32, f32, f32) = (1.0, 0.88, 0.88);
+
+/// Left edge of the vehicle no, due date, days remaining and status columns.
+const COLUMNS: [f32; 4] = [MARGIN + 10.0, 260.0, 360.0, 470.0];
+
+/// Renders the due report as an A4 PDF: a section per fee type, a table per
+/// owner, overdue rows on a red background.
+///
+/// The built-in Helvetica font only covers Latin-1, so plates are printed in
+/// their Latin form and other characters, e.g. Bengali owner names, as `?`.
+pub fn due_report_pdf(report: &DueReport) -> Vec<u8> {
+    let mut layout = Layout::new();
+
+    layout.line(28.0);
+    layout.text(MARGIN, BOLD, 18.0, BLACK, "Fee due report");
+    layout.line(ROW_HEIGHT);
+    layout.text(
+        MARGIN,
+        REGULAR,
+        10.0,
+        GREY,
+        &format!(
+            "Generated on {}. Overdue fees and fees due by {}.",
+            report.generated_on, report.until
+        ),
+    );
+
+    for fee in &report.fees {
+        layout.keep(ROW_HEIGHT * 4.0);
+        layout.line(ROW_HEIGHT * 2.0);
+        let vehicles = fee
+            .owners
+            .iter()
+            .map(|owner| owner.vehicles.len())
+            .sum::<usize>();
+        layout.text(
+            MARGIN,
+            BOLD,
+            14.0,
+            BLACK,
+            &format!("{} ({})", capitalize(&fee.fee_type), vehicles),
+        );
+
+        if fee.owners.is_empty() {
+            layout.line(ROW_HEIGHT);
+            layout.text(MARGIN, REGULAR, 10.0, GREY, "Nothing is due.");
+        }
+
+        for owner in &fee.owners {
+            layout.keep(ROW_HEIGHT * 4.0);
+            layout.line(ROW_HEIGHT * 1.5);
+            layout.text(MARGIN, BOLD, 11.0, BLACK, &owner.owner);
+            layout.line(ROW_HEIGHT);
+            for (x, header) in
+                COLUMNS
+                    .iter()
+                    .zip(["Vehicle no", "Due date", "Days remaining", "Status"])
+            {
+                layout.text(*x, BOLD, 9.0, GREY, header);
+            }
+
+            for vehicle in &owner.vehicles {
+                layout.line(ROW_HEIGHT);
+                layout.row(vehicle);
+            }
+        }
+    }
+
+    layout.finish()
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// `text` in the WinAnsi encoding of the built-in fonts.
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c as u32 {
+            0x20..=0x7E | 0xA0..=0xFF => c as u8,
+            _ => b'?',
+        })
+        .collect()
+}
+
+/// Pages written top to bottom. `y` is the baseline of the current line.
+struct Layout {
+    pages: Vec<Content>,
+    y: f32,
+}
+
+impl Layout {
+    fn new() -> Self {
+        Self {
+            pages: vec![Content::new()],
+            y: PAGE_HEIGHT - MARGIN,
+        }
+    }
+
+    /// Starts a new page unless `height` still fits on this one.
+    fn keep(&mut self, height: f32) {
+        if self.y - height < MARGIN + ROW_HEIGHT {
+            self.pages.push(Content::new());
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+    }
+
+    /// Moves down to the next line, `height` below the current one.
+    fn line(&mut self, height: f32) {
+        self.keep(height);
+        self.y -= height;
+    }
+
+    fn text(&mut self, x: f32, font: Name, size: f32, color: (f32, f32, f32), text: &str) {
+        let y = self.y;
+        let content = self.pages.last_mut().unwrap();
+        content.set_fill_rgb(color.0, color.1, color.2);
+        content.begin_text();
+        content.set_font(font, size);
+        content.next_line(x, y);
+        content.show(Str(&win_ansi(text)));
+        content.end_text();
+    }
+
+    fn row(&mut self, vehicle: &DueVehicle) {
+        let (color, status) = match vehicle.overdue() {
+            true => {
+                let y = self.y;
+                let content = self.pages.last_mut().unwrap();
+                content.set_fill_rgb(LIGHT_RED.0, LIGHT_RED.1, LIGHT_RED.2);
+                content.rect(MARGIN, y - 4.0, PAGE_WIDTH - 2.0 * MARGIN, ROW_HEIGHT - 1.0);
+                content.fill_nonzero();
+                (DARK_RED, "OVERDUE")
+            }
+            false => (BLACK, "due"),
+        };
+        let font = match vehicle.overdue() {
+            true => BOLD,
+            false => REGULAR,
+        };
+
+        let cells = [
+            vehicle.vehicle_no.latin(),
+            vehicle.due_date.clone(),
+            vehicle.days_remaining.to_string(),
+            status.to_string(),
+        ];
+        for (x, cell) in COLUMNS.iter().zip(cells) {
+            self.text(*x, font, 10.0, color, &cell);
+        }
+    }
+
+    /// Adds the page numbers and writes the document.
+    fn finish(mut self) -> Vec<u8> {
+        let count = self.pages.len();
+        for (index, content) in self.pages.iter_mut().enumerate() {
+            content.set_fill_rgb(GREY.0, GREY.1, GREY.2);
+            content.begin_text();
+            content.set_font(REGULAR, 8.0);
+            content.next_line(PAGE_WIDTH - MARGIN - 50.0, MARGIN / 2.0);
+            content.show(Str(format!("Page {} of {}", index + 1, count).as_bytes()));
+            content.end_text();
+        }
+
+        let catalog_id = Ref::new(1);
+        let page_tree_id = Ref::new(2);
+        let regular_id = Ref::new(3);
+        let bold_id = Ref::new(4);
+        let page_ids = (0..count)
+            .map(|index| Ref::new(5 + 2 * index as i32))
+            .collect::<Vec<Ref>>();
+
+        let mut pdf = Pdf::new();
+        pdf.catalog(catalog_id).pages(page_tree_id);
+        pdf.pages(page_tree_id)
+            .kids(page_ids.iter().copied())
+            .count(count as i32);
+        pdf.type1_font(regular_id)
+            .base_font(Name(b"Helvetica"))
+            .encoding_predefined(Name(b"WinAnsiEncoding"));
+        pdf.type1_font(bold_id)
+            .base_font(Name(b"Helvetica-Bold"))
+            .encoding_predefined(Name(b"WinAnsiEncoding"));
+
+        for (page_id, content) in page_ids.into_iter().zip(self.pages) {
+            let content_id = Ref::new(page_id.get() + 1);
+            {
+                let mut page = pdf.page(page_id);
+                page.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT))
+                    .parent(page_tree_id)
+                    .contents(content_id);
+                page.resources()
+                    .fonts()
+                    .pair(REGULAR, regular_id)
+                    .pair(BOLD, bold_id);
+            }
+            pdf.stream(content_id, &content.finish());
+        }
+
+        pdf.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::model::vehicle::Vehicle;
+
+    fn report(vehicles: usize) -> DueReport {
+        let today = NaiveDate::from_ymd_opt(2026, 1, 10).unwrap();
+        let vehicles = (0..vehicles)
+            .map(|serial| {
+                Vehicle::new(
+                    format!("DHA-KA-11-{:04}", serial).parse().unwrap(),
+                    "রহিম".to_string(),
+                    // Every other vehicle is overdue.
+                    (today + chrono::Duration::days(serial as i64 % 2 * 20 - 5)).to_string(),
+                    "2099-01-01".to_string(),
+                    "2099-01-01".to_string(),
+                    "2099-01-01".to_string(),
+                )
+            })
+            .collect();
+        DueReport::new(
+            today,
+            NaiveDate::from_ymd_opt(2026, 2, 9).unwrap(),
+            vec![("tax", vehicles), ("fitness", Vec::new())],
+        )
+    }
+
+    fn contains(pdf: &[u8], text: &str) -> bool {
+        pdf.windows(text.len())
+            .any(|window| window == text.as_bytes())
+    }
+
+    #[test]
+    fn renders_sections_rows_and_overdue_highlights() {
+        let pdf = due_report_pdf(&report(2));
+
+        assert!(pdf.starts_with(b"%PDF-"));
+        for text in [
+            "(Fee due report)",
+            "(Tax (2))",
+            "(Fitness (0))",
+            "(Nothing is due.)",
+            "(DHAKA-KA-11-0000)",
+            "(2026-01-05)",
+            "(-5)",
+            "(OVERDUE)",
+            "(15)",
+            "(due)",
+            "(Page 1 of 1)",
+        ] {
+            assert!(contains(&pdf, text), "{}", text);
+        }
+    }
+
+    #[test]
+    fn long_reports_go_on_to_more_pages() {
+        let pdf = due_report_pdf(&report(80));
+        assert!(contains(&pdf, "/Count 2"));
+        assert!(contains(&pdf, "(Page 2 of 2)"));
+    }
+
+    #[test]
+    fn text_outside_latin_1_is_replaced() {
+        assert_eq!(win_ansi("Rahim é রহিম"), b"Rahim \xe9 ????");
+        assert_eq!(capitalize("insurance"), "Insurance");
+        assert_eq!(capitalize(""), "");
+    }
+}
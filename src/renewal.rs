@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+
+use chrono::{Months, NaiveDate};
+use lambda_http::tracing;
+use serde::Deserialize;
+
+/// Months each fee stays valid after it is paid.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ValidityPeriods {
+    pub tax: u32,
+    pub fitness: u32,
+    pub insurance: u32,
+    pub route: u32,
+}
+
+impl ValidityPeriods {
+    pub fn months(&self, fee_type: &str) -> Option<u32> {
+        match fee_type {
+            "tax" => Some(self.tax),
+            "fitness" => Some(self.fitness),
+            "insurance" => Some(self.insurance),
+            "route" => Some(self.route),
+            _ => None,
+        }
+    }
+}
+
+/// Validity periods used by `pay_fee` to work out the next expiry when the
+/// client does not send one. `classes` overrides `default` for vehicles
+/// with a matching `vehicle_class`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RenewalRules {
+    pub default: ValidityPeriods,
+    #[serde(default)]
+    pub classes: HashMap<String, ValidityPeriods>,
+}
+
+impl Default for RenewalRules {
+    /// Every fee for a year, except a two year fitness for private cars.
+    fn default() -> Self {
+        let default = ValidityPeriods {
+            tax: 12,
+            fitness: 12,
+            insurance: 12,
+            route: 12,
+        };
+        Self {
+            default,
+            classes: HashMap::from([(
+                "private".to_string(),
+                ValidityPeriods {
+                    fitness: 24,
+                    ..default
+                },
+            )]),
+        }
+    }
+}
+
+impl RenewalRules {
+    /// Reads the rules as JSON from `RENEWAL_RULES`, falling back to the
+    /// defaults when it is unset, e.g.
+    /// `{"default": {"tax": 12, "fitness": 12, "insurance": 12, "route": 36}}`.
+    /// Rules that don't parse are logged and returned as an error, so the
+    /// function fails to start instead of computing wrong dates.
+    pub fn from_env() -> Result<Self, String> {
+        match std::env::var("RENEWAL_RULES") {
+            Ok(rules) => serde_json::from_str(&rules).map_err(|err| {
+                tracing::error!(%err, "Configuration error: RENEWAL_RULES is not valid");
+                format!("RENEWAL_RULES is not valid: {}", err)
+            }),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn periods(&self, vehicle_class: Option<&str>) -> &ValidityPeriods {
+        vehicle_class
+            .and_then(|class| self.classes.get(class))
+            .unwrap_or(&self.default)
+    }
+
+    /// The expiry after paying `fee_type` on `paid_on`. An early payment
+    /// extends the current expiry, a late one starts from the payment date.
+    pub fn next_expiry(
+        &self,
+        fee_type: &str,
+        vehicle_class: Option<&str>,
+        expiry: NaiveDate,
+        paid_on: NaiveDate,
+    ) -> Option<NaiveDate> {
+        let months = self.periods(vehicle_class).months(fee_type)?;
+        expiry.max(paid_on).checked_add_months(Months::new(months))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn early_payment_extends_the_current_expiry() {
+        let rules = RenewalRules::default();
+        assert_eq!(
+            rules.next_expiry("tax", None, date("2025-03-01"), date("2025-01-15")),
+            Some(date("2026-03-01"))
+        );
+    }
+
+    #[test]
+    fn late_payment_starts_from_the_payment_date() {
+        let rules = RenewalRules::default();
+        assert_eq!(
+            rules.next_expiry("insurance", None, date("2024-12-01"), date("2025-01-15")),
+            Some(date("2026-01-15"))
+        );
+    }
+
+    #[test]
+    fn vehicle_class_overrides_the_default_periods() {
+        let rules = RenewalRules::default();
+        let (expiry, paid_on) = (date("2025-03-01"), date("2025-01-15"));
+
+        assert_eq!(
+            rules.next_expiry("fitness", Some("private"), expiry, paid_on),
+            Some(date("2027-03-01"))
+        );
+        assert_eq!(
+            rules.next_expiry("fitness", Some("commercial"), expiry, paid_on),
+            Some(date("2026-03-01"))
+        );
+        assert_eq!(
+            rules.next_expiry("tax", Some("private"), expiry, paid_on),
+            Some(date("2026-03-01"))
+        );
+    }
+
+    #[test]
+    fn month_end_is_clamped() {
+        let rules: RenewalRules = serde_json::from_str(
+            r#"{"default": {"tax": 1, "fitness": 12, "insurance": 12, "route": 36}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            rules.next_expiry("tax", None, date("2025-01-31"), date("2025-01-01")),
+            Some(date("2025-02-28"))
+        );
+        assert_eq!(
+            rules.next_expiry("fitness", None, date("2024-02-29"), date("2024-01-01")),
+            Some(date("2025-02-28"))
+        );
+        assert_eq!(
+            rules.next_expiry("route", None, date("2025-01-10"), date("2025-01-01")),
+            Some(date("2028-01-10"))
+        );
+    }
+
+    #[test]
+    fn unknown_fee_has_no_expiry() {
+        let rules = RenewalRules::default();
+        assert_eq!(
+            rules.next_expiry("parking", None, date("2025-01-01"), date("2025-01-01")),
+            None
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use lambda_http::{Body, Error, Request, RequestExt, Response};
+
+use crate::{
+    error::{error_response, validation_response},
+    handler::{calendar, history, openapi, organization, session, user, vehicle},
+    validation, DataAccess,
+};
+
+/// Dispatches a request to its handler by method and path.
+///
+/// | Method   | Path                        | Handler                             |
+/// |----------|-----------------------------|-------------------------------------|
+/// | `POST`   | `/users`                    | `user::signup`                      |
+/// | `PUT`    | `/users/password`           | `user::change_password`             |
+/// | `POST`   | `/sessions`                 | `session::login`                    |
+/// | `POST`   | `/sessions/refresh`         | `session::refresh_session`          |
+/// | `DELETE` | `/sessions`                 | `session::delete_session`           |
+/// | `GET`    | `/sessions`                 | `session::list_sessions`            |
+/// | `DELETE` | `/sessions/device?id=...`   | `session::revoke_session`           |
+/// | `DELETE` | `/sessions/others`          | `session::revoke_other_sessions`    |
+/// | `GET`    | `/vehicles`                 | `vehicle::get_vehicles`             |
+/// | `GET`    | `/vehicles?due=tax&days=30` | `vehicle::get_vehicles_by_type`     |
+/// | `GET`    | `/vehicles/details`         | `vehicle::get_vehicle`              |
+/// | `GET`    | `/vehicles/search?q=1234`   | `vehicle::search_vehicles`          |
+/// | `GET`    | `/vehicles/export`          | `vehicle::export_vehicles`          |
+/// | `POST`   | `/vehicles`                 | `vehicle::add_vehicle`              |
+/// | `PUT`    | `/vehicles`                 | `vehicle::update_vehicle`           |
+/// | `POST`   | `/vehicles/sale`            | `vehicle::sell_vehicle`             |
+/// | `POST`   | `/vehicles/import`          | `vehicle::import_vehicles`          |
+/// | `POST`   | `/payments?type=tax`        | `vehicle::pay_fee`                  |
+/// | `GET`    | `/history`                  | `history::get_history`              |
+/// | `GET`    | `/history/export`           | `history::export_history`           |
+/// | `GET`    | `/vehicles/history`         | `history::get_vehicle_history`      |
+/// | `GET`    | `/reports/spending`         | `history::get_spending_report`      |
+/// | `GET`    | `/reports/due?days=7`       | `vehicle::get_due_report`           |
+/// | `DELETE` | `/history`                  | `history::undo_history`             |
+/// | `POST`   | `/organizations`            | `organization::create_organization` |
+/// | `GET`    | `/organizations`            | `organization::get_organizations`   |
+/// | `POST`   | `/organizations/members`    | `organization::add_member`          |
+/// | `PUT`    | `/organizations/members`    | `organization::set_role`            |
+/// | `POST`   | `/calendar/feed`            | `calendar::create_calendar_feed`    |
+/// | `GET`    | `/calendar.ics?token=...`   | `calendar::get_calendar`            |
+/// | `GET`    | `/openapi.json`             | `openapi::get_openapi`              |
+pub async fn route(data_access: &impl DataAccess, req: Request) -> Result<Response<Body>, Error> {
+    let path = match req.raw_http_path() {
+        "" => req.uri().path().to_string(),
+        path => path.to_string(),
+    };
+    let path = match path.trim_end_matches('/') {
+        "" => "/",
+        path => path,
+    };
+
+    match (req.method().as_str(), path) {
+        ("POST", "/users") => user::signup(data_access, req).await,
+        ("PUT", "/users/password") => user::change_password(data_access, req).await,
+        ("POST", "/sessions") => session::login(data_access, req).await,
+        ("POST", "/sessions/refresh") => session::refresh_session(data_access, req).await,
+        ("DELETE", "/sessions") => session::delete_session(data_access, req).await,
+        ("GET", "/sessions") => session::list_sessions(data_access, req).await,
+        ("DELETE", "/sessions/device") => session::revoke_session(data_access, req).await,
+        ("DELETE", "/sessions/others") => session::revoke_other_sessions(data_access, req).await,
+        ("GET", "/vehicles") => {
+            let due = req
+                .query_string_parameters_ref()
+                .and_then(|params| params.first("due"))
+                .map(|due| validation::fee_type("due", due));
+
+            match due {
+                Some(Ok(due)) => vehicle::get_vehicles_by_type(data_access, req, due).await,
+                Some(Err(err)) => Ok(validation_response(&[err])),
+                None => vehicle::get_vehicles(data_access, req).await,
+            }
+        }
+        ("GET", "/vehicles/details") => vehicle::get_vehicle(data_access, req).await,
+        ("GET", "/vehicles/search") => vehicle::search_vehicles(data_access, req).await,
+        ("GET", "/vehicles/export") => vehicle::export_vehicles(data_access, req).await,
+        ("GET", "/vehicles/history") => history::get_vehicle_history(data_access, req).await,
+        ("POST", "/vehicles") => vehicle::add_vehicle(data_access, req).await,
+        ("PUT", "/vehicles") => vehicle::update_vehicle(data_access, req).await,
+        ("POST", "/vehicles/sale") => vehicle::sell_vehicle(data_access, req).await,
+        ("POST", "/vehicles/import") => vehicle::import_vehicles(data_access, req).await,
+        ("POST", "/payments") => vehicle::pay_fee(data_access, req).await,
+        ("GET", "/history") => history::get_history(data_access, req).await,
+        ("GET", "/history/export") => history::export_history(data_access, req).await,
+        ("GET", "/reports/spending") => history::get_spending_report(data_access, req).await,
+        ("GET", "/reports/due") => vehicle::get_due_report(data_access, req).await,
+        ("DELETE", "/history") => history::undo_history(data_access, req).await,
+        ("POST", "/organizations") => organization::create_organization(data_access, req).await,
+        ("GET", "/organizations") => organization::get_organizations(data_access, req).await,
+        ("POST", "/organizations/members") => organization::add_member(data_access, req).await,
+        ("PUT", "/organizations/members") => organization::set_role(data_access, req).await,
+        ("POST", "/calendar/feed") => calendar::create_calendar_feed(data_access, req).await,
+        ("GET", "/calendar.ics") => calendar::get_calendar(data_access, req).await,
+        ("GET", "/openapi.json") => openapi::get_openapi(req).await,
+        _ => Ok(error_response(404, "NOT_FOUND", "Route not found")),
+    }
+}
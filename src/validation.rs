@@ -0,0 +1,278 @@
+use chrono::NaiveDate;
+use lambda_http::{Body, Response};
+use rust_decimal::Decimal;
+use serde::de::DeserializeOwned;
+
+use crate::{
+    error::{error_response, validation_response, FieldError},
+    handler::FEE_TYPES,
+    model::{user::User, vehicle::Vehicle, vehicle_number::VehicleNumber},
+    DeleteHistory, SellVehicle, UpdateVehicle,
+};
+
+/// Longest day window a request may ask for. Anything longer is a typo, and
+/// a window past the calendar range of `chrono` would panic.
+pub const MAX_DAYS: u32 = 36_500;
+
+/// A `YYYY-MM-DD` date. Single digit months and days are accepted.
+pub fn date(field: &str, value: &str) -> Result<NaiveDate, FieldError> {
+    NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+        .map_err(|_| FieldError::new(field, format!("{} must be a date like 2024-01-31", field)))
+}
+
+/// `date` written back as `YYYY-MM-DD`, the form every date is stored in.
+pub fn iso_date(field: &str, value: &str) -> Result<String, FieldError> {
+    date(field, value).map(|date| date.format("%Y-%m-%d").to_string())
+}
+
+/// A number of days from `min` to `MAX_DAYS`.
+pub fn days(field: &str, value: &str, min: u32) -> Result<u32, FieldError> {
+    match value.trim().parse::<u32>() {
+        Ok(days) if (min..=MAX_DAYS).contains(&days) => Ok(days),
+        _ => Err(FieldError::new(
+            field,
+            format!(
+                "{} must be a number of days from {} to {}",
+                field, min, MAX_DAYS
+            ),
+        )),
+    }
+}
+
+/// `tax`, `fitness`, `insurance` or `route`, in any case.
+pub fn fee_type(field: &str, value: &str) -> Result<&'static str, FieldError> {
+    let value = value.trim().to_lowercase();
+    FEE_TYPES
+        .into_iter()
+        .find(|fee_type| *fee_type == value)
+        .ok_or_else(|| {
+            FieldError::new(
+                field,
+                format!("{} must be one of tax, fitness, insurance or route", field),
+            )
+        })
+}
+
+pub fn vehicle_no(field: &str, value: &str) -> Result<VehicleNumber, FieldError> {
+    value
+        .parse::<VehicleNumber>()
+        .map_err(|err| FieldError::new(field, err))
+}
+
+pub fn not_blank(field: &str, value: &str) -> Result<(), FieldError> {
+    match value.trim().is_empty() {
+        true => Err(FieldError::new(field, format!("{} is empty", field))),
+        false => Ok(()),
+    }
+}
+
+/// A query parameter that must be there.
+pub fn required<'a>(field: &str, value: Option<&'a str>) -> Result<&'a str, FieldError> {
+    value.ok_or_else(|| FieldError::new(field, format!("{} is not provided", field)))
+}
+
+/// Collects the field errors of a request, so the client hears about every
+/// bad input at once rather than one per attempt.
+#[derive(Debug, Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The value of `result`, or `None` with its error recorded.
+    pub fn check<T>(&mut self, result: Result<T, FieldError>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.errors.push(error);
+                None
+            }
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn errors(&self) -> &[FieldError] {
+        &self.errors
+    }
+
+    /// The 422 response of the errors found so far.
+    pub fn to_response(&self) -> Response<Body> {
+        validation_response(&self.errors)
+    }
+}
+
+/// Field checks of a request body beyond what its types already enforce.
+pub trait Validate {
+    fn validate(&self, validator: &mut Validator);
+}
+
+/// Reads and validates a JSON body. A body that is not JSON at all is a 400
+/// `BAD_REQUEST`; a wrong, missing or invalid field is a 422
+/// `VALIDATION_ERROR` naming it.
+// The error is the response the handler returns as it is, once per request.
+#[allow(clippy::result_large_err)]
+pub fn json_body<T: DeserializeOwned + Validate>(body: &Body) -> Result<T, Response<Body>> {
+    let text = match body {
+        Body::Text(text) => text.as_str(),
+        Body::Binary(bytes) => std::str::from_utf8(bytes)
+            .map_err(|_| error_response(400, "BAD_REQUEST", "The msg body is not UTF-8"))?,
+        Body::Empty => return Err(error_response(400, "BAD_REQUEST", "The msg body is empty")),
+    };
+
+    let value =
+        serde_path_to_error::deserialize::<_, T>(&mut serde_json::Deserializer::from_str(text))
+            .map_err(|err| {
+                let path = err.path().to_string();
+                let err = err.into_inner();
+                if !err.is_data() {
+                    return error_response(400, "BAD_REQUEST", "the body is not valid JSON");
+                }
+
+                // serde_json appends where it stopped reading, which means nothing
+                // to the client once the field is named.
+                let message = err.to_string();
+                let message = message
+                    .strip_suffix(&format!(" at line {} column {}", err.line(), err.column()))
+                    .unwrap_or(&message);
+                let field = match message
+                    .strip_prefix("missing field `")
+                    .and_then(|field| field.strip_suffix('`'))
+                {
+                    Some(field) if path == "." => field.to_string(),
+                    Some(field) => format!("{}.{}", path, field),
+                    None => path,
+                };
+                let message = match message.starts_with("missing field") {
+                    true => format!("{} is required", field),
+                    false => format!("{} is not valid: {}", field, message),
+                };
+                validation_response(&[FieldError::new(&field, message)])
+            })?;
+
+    let mut validator = Validator::new();
+    value.validate(&mut validator);
+    match validator.is_valid() {
+        true => Ok(value),
+        false => Err(validator.to_response()),
+    }
+}
+
+impl Validate for User {
+    fn validate(&self, validator: &mut Validator) {
+        validator.check(not_blank("username", &self.username));
+        validator.check(not_blank("password", &self.password));
+    }
+}
+
+impl Validate for Vehicle {
+    fn validate(&self, validator: &mut Validator) {
+        validator.check(not_blank("owner", &self.owner));
+        for (field, value) in [
+            ("tax_date", &self.tax_date),
+            ("fitness_date", &self.fitness_date),
+            ("insurance_date", &self.insurance_date),
+            ("route_date", &self.route_date),
+        ] {
+            validator.check(date(field, value));
+        }
+    }
+}
+
+impl Validate for UpdateVehicle {
+    fn validate(&self, validator: &mut Validator) {
+        for (field, value) in [
+            ("tax_date", &self.tax_date),
+            ("fitness_date", &self.fitness_date),
+            ("insurance_date", &self.insurance_date),
+            ("route_date", &self.route_date),
+        ] {
+            if let Some(value) = value {
+                validator.check(date(field, value));
+            }
+        }
+        if let Some(owner) = &self.owner {
+            validator.check(not_blank("owner", owner));
+        }
+        if let Some(vehicle_class) = &self.vehicle_class {
+            validator.check(not_blank("vehicle_class", vehicle_class));
+        }
+        if self
+            .payment
+            .amount
+            .is_some_and(|amount| amount < Decimal::ZERO)
+        {
+            validator.check::<()>(Err(FieldError::new("amount", "amount can't be negative")));
+        }
+    }
+}
+
+impl Validate for SellVehicle {
+    fn validate(&self, validator: &mut Validator) {
+        validator.check(not_blank("buyer", &self.buyer));
+        if let Some(sale_date) = &self.sale_date {
+            validator.check(date("sale_date", sale_date));
+        }
+    }
+}
+
+impl Validate for DeleteHistory {
+    fn validate(&self, validator: &mut Validator) {
+        validator.check(fee_type("transaction_type", &self.transaction_type));
+        validator.check(iso_date("created_at", &self.created_at));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_are_checked_and_written_as_iso() {
+        assert_eq!(iso_date("tax_date", " 2024-1-5 ").unwrap(), "2024-01-05");
+        for value in ["2024-13-40", "2023-02-29", "31-01-2024", "2024/01/31", ""] {
+            let err = date("tax_date", value).unwrap_err();
+            assert_eq!(err.field, "tax_date", "{}", value);
+        }
+    }
+
+    #[test]
+    fn days_stay_within_the_window() {
+        assert_eq!(days("days", "0", 0).unwrap(), 0);
+        assert_eq!(days("days", " 30 ", 1).unwrap(), 30);
+        for value in ["0", "-1", "abc", "36501"] {
+            assert!(days("days", value, 1).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn fee_types_ignore_case() {
+        assert_eq!(fee_type("type", " Fitness ").unwrap(), "fitness");
+        assert!(fee_type("type", "parking").is_err());
+    }
+
+    #[test]
+    fn validator_keeps_every_error() {
+        let mut validator = Validator::new();
+        assert_eq!(validator.check(not_blank("owner", "Rahim")), Some(()));
+        validator.check(not_blank("owner", "  "));
+        validator.check(required("type", None));
+
+        assert!(!validator.is_valid());
+        assert_eq!(
+            validator
+                .errors()
+                .iter()
+                .map(|error| error.field.as_str())
+                .collect::<Vec<&str>>(),
+            ["owner", "type"]
+        );
+        assert_eq!(validator.to_response().status(), 422);
+    }
+}
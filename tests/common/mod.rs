@@ -0,0 +1,130 @@
+//! Drives the router against `InMemoryDataAccess` the way the dev server
+//! does, so the tests cover the handlers, validation and the store together.
+
+// Each test binary uses a different subset of the helpers.
+#![allow(dead_code)]
+
+use lambda_http::{
+    aws_lambda_events::query_map::QueryMap, http, lambda_runtime::Context, Body, Request,
+    RequestExt,
+};
+use serde_json::{json, Value};
+use vehicle_management_lambda::{router, InMemoryDataAccess};
+
+pub struct Api {
+    pub data_access: InMemoryDataAccess,
+}
+
+/// A response with its body as text, and as JSON when it is JSON.
+pub struct Reply {
+    pub status: u16,
+    pub text: String,
+    pub json: Value,
+}
+
+impl Api {
+    pub fn new() -> Self {
+        Self::with(InMemoryDataAccess::new())
+    }
+
+    pub fn with(data_access: InMemoryDataAccess) -> Self {
+        Self { data_access }
+    }
+
+    pub async fn call(&self, method: &str, uri: &str, token: Option<&str>, body: Body) -> Reply {
+        self.call_with_headers(method, uri, token, &[], body).await
+    }
+
+    pub async fn call_with_headers(
+        &self,
+        method: &str,
+        uri: &str,
+        token: Option<&str>,
+        headers: &[(&str, &str)],
+        body: Body,
+    ) -> Reply {
+        let mut builder = http::Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            builder = builder.header("Authorization", token);
+        }
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        let request = builder.body(body).unwrap();
+
+        let query = request
+            .uri()
+            .query()
+            .unwrap_or_default()
+            .parse::<QueryMap>()
+            .unwrap();
+        let path = request.uri().path().to_string();
+        let request: Request = request
+            .with_query_string_parameters(query)
+            .with_raw_http_path(path)
+            .with_lambda_context(Context::default());
+
+        let response = router::route(&self.data_access, request).await.unwrap();
+        let status = response.status().as_u16();
+        let text = match response.into_body() {
+            Body::Empty => String::new(),
+            Body::Text(text) => text,
+            Body::Binary(binary) => String::from_utf8_lossy(&binary).into_owned(),
+        };
+        let json = serde_json::from_str(&text).unwrap_or(Value::Null);
+        Reply { status, text, json }
+    }
+
+    pub async fn get(&self, uri: &str, token: &str) -> Reply {
+        self.call("GET", uri, Some(token), Body::Empty).await
+    }
+
+    pub async fn send(&self, method: &str, uri: &str, token: Option<&str>, body: Value) -> Reply {
+        self.call(method, uri, token, Body::Text(body.to_string()))
+            .await
+    }
+
+    /// Signs `username` up and returns the body of their login.
+    pub async fn login(&self, username: &str) -> Value {
+        let credentials = json!({ "username": username, "password": "secret" });
+        let signup = self.send("POST", "/users", None, credentials.clone()).await;
+        assert!(
+            signup.status == 201 || signup.status == 409,
+            "{}",
+            signup.text
+        );
+        let login = self.send("POST", "/sessions", None, credentials).await;
+        assert_eq!(login.status, 200, "{}", login.text);
+        login.json
+    }
+
+    /// Signs `username` up and returns their access token.
+    pub async fn token(&self, username: &str) -> String {
+        self.login(username).await["token"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    pub async fn add_vehicle(&self, token: &str, vehicle_no: &str) -> Reply {
+        self.send(
+            "POST",
+            "/vehicles",
+            Some(token),
+            vehicle(vehicle_no, "2030-01-01"),
+        )
+        .await
+    }
+}
+
+/// An add-vehicle body with every fee expiring on `date`.
+pub fn vehicle(vehicle_no: &str, date: &str) -> Value {
+    json!({
+        "vehicle_no": vehicle_no,
+        "owner": "Rahim",
+        "tax_date": date,
+        "fitness_date": date,
+        "insurance_date": date,
+        "route_date": date,
+    })
+}
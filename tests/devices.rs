@@ -0,0 +1,204 @@
+mod common;
+
+use chrono::{DateTime, Duration, Utc};
+use common::{Api, Reply};
+use lambda_http::Body;
+use serde_json::{json, Value};
+
+/// Logs `rahim` in from a named device, returning the login body.
+async fn login_from(api: &Api, device_name: &str, user_agent: &str, ip: &str) -> Value {
+    api.login("rahim").await;
+    let body = json!({ "username": "rahim", "password": "secret", "device_name": device_name });
+    let login = api
+        .call_with_headers(
+            "POST",
+            "/sessions",
+            None,
+            &[("User-Agent", user_agent), ("X-Forwarded-For", ip)],
+            Body::Text(body.to_string()),
+        )
+        .await;
+    assert_eq!(login.status, 200, "{}", login.text);
+    login.json
+}
+
+fn token(login: &Value) -> &str {
+    login["token"].as_str().unwrap()
+}
+
+async fn refresh(api: &Api, login: &Value) -> Reply {
+    api.send(
+        "POST",
+        "/sessions/refresh",
+        None,
+        json!({ "refresh_token": login["refresh_token"] }),
+    )
+    .await
+}
+
+async fn sessions(api: &Api, login: &Value) -> Vec<Value> {
+    let sessions = api.get("/sessions", token(login)).await;
+    assert_eq!(sessions.status, 200, "{}", sessions.text);
+    sessions.json.as_array().unwrap().clone()
+}
+
+fn current(sessions: &[Value]) -> &Value {
+    sessions
+        .iter()
+        .find(|session| session["current"] == true)
+        .unwrap()
+}
+
+#[tokio::test]
+async fn logout_keeps_the_other_devices_signed_in() {
+    let api = Api::new();
+    let phone = login_from(&api, "Phone", "Android", "203.0.113.7").await;
+    let laptop = login_from(&api, "Laptop", "Firefox", "198.51.100.2").await;
+
+    let logout = api
+        .call("DELETE", "/sessions", Some(token(&phone)), Body::Empty)
+        .await;
+    assert_eq!(logout.status, 200, "{}", logout.text);
+
+    assert_eq!(api.get("/vehicles", token(&phone)).await.status, 401);
+    assert_eq!(refresh(&api, &phone).await.status, 401);
+    assert_eq!(api.get("/vehicles", token(&laptop)).await.status, 200);
+
+    let left = sessions(&api, &laptop).await;
+    assert!(left.iter().all(|session| session["device_name"] != "Phone"));
+    assert_eq!(refresh(&api, &laptop).await.status, 200);
+}
+
+#[tokio::test]
+async fn sessions_list_the_device_of_each_login() {
+    let api = Api::new();
+    let before = Utc::now() - Duration::seconds(1);
+    login_from(&api, "Phone", "Android", "203.0.113.7").await;
+    let laptop = login_from(&api, "Laptop", "Firefox", "198.51.100.2, 10.0.0.1").await;
+
+    let listed = sessions(&api, &laptop).await;
+    let this = current(&listed);
+    assert_eq!(this["device_name"], "Laptop");
+    assert_eq!(this["user_agent"], "Firefox");
+    assert_eq!(this["source_ip"], "198.51.100.2");
+    let last_seen = DateTime::parse_from_rfc3339(this["last_seen_at"].as_str().unwrap()).unwrap();
+    assert!(last_seen >= before && last_seen <= Utc::now());
+
+    let phone = listed
+        .iter()
+        .find(|session| session["device_name"] == "Phone")
+        .unwrap();
+    assert_eq!(phone["user_agent"], "Android");
+    assert_eq!(phone["source_ip"], "203.0.113.7");
+    assert_eq!(phone["current"], false);
+    // The bare logins of `Api::login` have no device attributes.
+    assert!(listed
+        .iter()
+        .filter(|session| session["device_name"].is_null())
+        .all(|session| session["user_agent"].is_null() && session["source_ip"].is_null()));
+}
+
+#[tokio::test]
+async fn one_device_can_be_revoked() {
+    let api = Api::new();
+    let phone = login_from(&api, "Phone", "Android", "203.0.113.7").await;
+    let laptop = login_from(&api, "Laptop", "Firefox", "198.51.100.2").await;
+    let listed = sessions(&api, &laptop).await;
+    let phone_id = listed
+        .iter()
+        .find(|session| session["device_name"] == "Phone")
+        .unwrap()["id"]
+        .as_str()
+        .unwrap();
+
+    let revoked = api
+        .call(
+            "DELETE",
+            &format!("/sessions/device?id={}", phone_id),
+            Some(token(&laptop)),
+            Body::Empty,
+        )
+        .await;
+    assert_eq!(revoked.status, 200, "{}", revoked.text);
+    assert_eq!(api.get("/vehicles", token(&phone)).await.status, 401);
+    assert_eq!(refresh(&api, &phone).await.status, 401);
+    assert_eq!(api.get("/vehicles", token(&laptop)).await.status, 200);
+
+    let again = api
+        .call(
+            "DELETE",
+            &format!("/sessions/device?id={}", phone_id),
+            Some(token(&laptop)),
+            Body::Empty,
+        )
+        .await;
+    assert_eq!(again.status, 404, "{}", again.text);
+}
+
+#[tokio::test]
+async fn other_devices_can_be_revoked_at_once() {
+    let api = Api::new();
+    let phone = login_from(&api, "Phone", "Android", "203.0.113.7").await;
+    let tablet = login_from(&api, "Tablet", "Safari", "192.0.2.1").await;
+    let laptop = login_from(&api, "Laptop", "Firefox", "198.51.100.2").await;
+
+    let revoked = api
+        .call(
+            "DELETE",
+            "/sessions/others",
+            Some(token(&laptop)),
+            Body::Empty,
+        )
+        .await;
+    assert_eq!(revoked.status, 200, "{}", revoked.text);
+
+    for other in [&phone, &tablet] {
+        assert_eq!(api.get("/vehicles", token(other)).await.status, 401);
+        assert_eq!(refresh(&api, other).await.status, 401);
+    }
+    let left = sessions(&api, &laptop).await;
+    assert_eq!(left.len(), 1);
+    assert_eq!(current(&left)["device_name"], "Laptop");
+}
+
+#[tokio::test]
+async fn changing_the_password_can_revoke_the_other_devices() {
+    let api = Api::new();
+    let phone = login_from(&api, "Phone", "Android", "203.0.113.7").await;
+    let laptop = login_from(&api, "Laptop", "Firefox", "198.51.100.2").await;
+    let change = |old: &str, new: &str, revoke: bool| json!({ "old_password": old, "new_password": new, "revoke_other_sessions": revoke });
+
+    let wrong = api
+        .send(
+            "PUT",
+            "/users/password",
+            Some(token(&laptop)),
+            change("wrong", "next", true),
+        )
+        .await;
+    assert_eq!(wrong.status, 422, "{}", wrong.text);
+    assert_eq!(api.get("/vehicles", token(&phone)).await.status, 200);
+
+    let kept = api
+        .send(
+            "PUT",
+            "/users/password",
+            Some(token(&laptop)),
+            change("secret", "next", false),
+        )
+        .await;
+    assert_eq!(kept.status, 200, "{}", kept.text);
+    assert_eq!(api.get("/vehicles", token(&phone)).await.status, 200);
+
+    let revoked = api
+        .send(
+            "PUT",
+            "/users/password",
+            Some(token(&laptop)),
+            change("next", "last", true),
+        )
+        .await;
+    assert_eq!(revoked.status, 200, "{}", revoked.text);
+    assert_eq!(api.get("/vehicles", token(&phone)).await.status, 401);
+    assert_eq!(api.get("/vehicles", token(&laptop)).await.status, 200);
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use common::Api;
+use lambda_http::Body;
+use serde_json::json;
+
+const HEADER: &str = "vehicle_no,owner,tax_date,fitness_date,insurance_date,route_date";
+
+async fn import(api: &Api, token: &str, mode: &str, rows: &[&str]) -> serde_json::Value {
+    let csv = std::iter::once(HEADER)
+        .chain(rows.iter().copied())
+        .collect::<Vec<&str>>()
+        .join("\n");
+    let reply = api
+        .call(
+            "POST",
+            &format!("/vehicles/import?mode={}", mode),
+            Some(token),
+            Body::Text(csv),
+        )
+        .await;
+    assert_eq!(reply.status, 200, "{}", reply.text);
+    reply.json
+}
+
+#[tokio::test]
+async fn skip_keeps_stored_vehicles_and_upsert_overwrites_them() {
+    let api = Api::new();
+    let token = api.token("rahim").await;
+    api.add_vehicle(&token, "DHA-KA-11-1234").await;
+    let rows = [
+        "DHA-KA-11-1234,Karim,2031-01-01,2031-01-01,2031-01-01,2031-01-01",
+        "DHA-KA-11-1235,Salam,2031-01-01,2031-01-01,2031-01-01,2031-01-01",
+    ];
+
+    let skipped = import(&api, &token, "skip", &rows).await;
+    assert_eq!(skipped["added"], 1);
+    assert_eq!(skipped["skipped"], 1);
+    let details = api
+        .get("/vehicles/details?vehicle_no=DHA-KA-11-1234", &token)
+        .await;
+    assert_eq!(details.json["owner"], "Rahim");
+
+    let upserted = import(&api, &token, "upsert", &rows).await;
+    assert_eq!(upserted["updated"], 2);
+    assert_eq!(upserted["added"], 0);
+    let details = api
+        .get("/vehicles/details?vehicle_no=DHA-KA-11-1234", &token)
+        .await;
+    assert_eq!(details.json["owner"], "Karim");
+    assert_eq!(details.json["tax_date"], "2031-01-01");
+}
+
+#[tokio::test]
+async fn sold_and_duplicate_rows_are_reported() {
+    let api = Api::new();
+    let token = api.token("rahim").await;
+    api.add_vehicle(&token, "DHA-KA-11-1234").await;
+    let sale = api
+        .send(
+            "POST",
+            "/vehicles/sale",
+            Some(&token),
+            json!({ "vehicle_no": "DHA-KA-11-1234", "buyer": "Karim" }),
+        )
+        .await;
+    assert_eq!(sale.status, 200, "{}", sale.text);
+
+    let report = import(
+        &api,
+        &token,
+        "upsert",
+        &[
+            "DHA-KA-11-1234,Karim,2031-01-01,2031-01-01,2031-01-01,2031-01-01",
+            "DHA-KA-11-1235,Salam,2031-01-01,2031-01-01,2031-01-01,2031-01-01",
+            "DHA-KA-11-1235,Salam,2031-01-01,2031-01-01,2031-01-01,2031-01-01",
+            "DHA-KA-11-1235,Salam,2031-01-01,2031-01-01,2031-01-01,2031-01-01",
+        ],
+    )
+    .await;
+
+    assert_eq!(report["added"], 1);
+    assert_eq!(
+        report["errors"],
+        json!([
+            { "row": 2, "vehicle_no": "DHA-KA-11-1234", "message": "The car is sold!!" },
+            { "row": 4, "vehicle_no": "DHA-KA-11-1235", "message": "the vehicle is already in row 3" },
+            { "row": 5, "vehicle_no": "DHA-KA-11-1235", "message": "the vehicle is already in row 3" },
+        ])
+    );
+}
+
+#[tokio::test]
+async fn unknown_mode_is_rejected() {
+    let api = Api::new();
+    let token = api.token("rahim").await;
+
+    let reply = api
+        .call(
+            "POST",
+            "/vehicles/import?mode=replace",
+            Some(&token),
+            Body::Text(HEADER.to_string()),
+        )
+        .await;
+    assert_eq!(reply.status, 422, "{}", reply.text);
+    assert_eq!(reply.json["errors"][0]["field"], "mode");
+}
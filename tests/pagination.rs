@@ -0,0 +1,80 @@
+mod common;
+
+use common::Api;
+
+#[tokio::test]
+async fn pages_cover_every_vehicle_once() {
+    let api = Api::new();
+    let token = api.token("rahim").await;
+    for serial in 1000..1005 {
+        let added = api
+            .add_vehicle(&token, &format!("DHA-KA-11-{}", serial))
+            .await;
+        assert_eq!(added.status, 201, "{}", added.text);
+    }
+
+    let mut plates = Vec::new();
+    let mut uri = "/vehicles?limit=2".to_string();
+    loop {
+        let page = api.get(&uri, &token).await;
+        assert_eq!(page.status, 200, "{}", page.text);
+        let items = page.json["items"].as_array().unwrap();
+        assert!(items.len() <= 2);
+        plates.extend(
+            items
+                .iter()
+                .map(|item| item["vehicle_no"].as_str().unwrap().to_string()),
+        );
+        match page.json["next_cursor"].as_str() {
+            Some(cursor) => uri = format!("/vehicles?limit=2&cursor={}", cursor),
+            None => break,
+        }
+    }
+
+    plates.sort();
+    let expected = (1000..1005)
+        .map(|serial| format!("DHA-KA-11-{}", serial))
+        .collect::<Vec<String>>();
+    assert_eq!(plates, expected);
+}
+
+#[tokio::test]
+async fn limit_must_be_positive() {
+    let api = Api::new();
+    let token = api.token("rahim").await;
+
+    for limit in ["0", "-1", "many"] {
+        let page = api.get(&format!("/vehicles?limit={}", limit), &token).await;
+        assert_eq!(page.status, 422, "{}", limit);
+        assert_eq!(page.json["errors"][0]["field"], "limit");
+    }
+}
+
+#[tokio::test]
+async fn cursors_only_work_for_their_own_list() {
+    let api = Api::new();
+    let rahim = api.token("rahim").await;
+    let karim = api.token("karim").await;
+    for serial in 1000..1003 {
+        api.add_vehicle(&rahim, &format!("DHA-KA-11-{}", serial))
+            .await;
+    }
+
+    let page = api.get("/vehicles?limit=1", &rahim).await;
+    let cursor = page.json["next_cursor"].as_str().unwrap();
+
+    let other_org = api
+        .get(&format!("/vehicles?limit=1&cursor={}", cursor), &karim)
+        .await;
+    assert_eq!(other_org.status, 422, "{}", other_org.text);
+    assert_eq!(other_org.json["code"], "VALIDATION_ERROR");
+
+    let other_list = api
+        .get(&format!("/history?limit=1&cursor={}", cursor), &rahim)
+        .await;
+    assert_eq!(other_list.status, 422, "{}", other_list.text);
+    assert!(other_list.text.contains("cursor"), "{}", other_list.text);
+
+    let garbage = api.get("/vehicles?cursor=not-a-cursor", &rahim).await;
+    assert_eq!(garbage.status, 422, "{}", garbage.text);
+}
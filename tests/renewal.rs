@@ -0,0 +1,88 @@
+mod common;
+
+use common::{vehicle, Api};
+use serde_json::json;
+
+async fn pay(api: &Api, token: &str, fee_type: &str, body: serde_json::Value) {
+    let paid = api
+        .send(
+            "POST",
+            &format!("/payments?type={}", fee_type),
+            Some(token),
+            body,
+        )
+        .await;
+    assert_eq!(paid.status, 200, "{}", paid.text);
+}
+
+async fn details(api: &Api, token: &str) -> serde_json::Value {
+    api.get("/vehicles/details?vehicle_no=DHA-KA-11-1234", token)
+        .await
+        .json
+}
+
+#[tokio::test]
+async fn paying_a_fee_works_out_the_next_expiry() {
+    let api = Api::new();
+    let token = api.token("rahim").await;
+    let today = chrono::Local::now().date_naive();
+    let mut body = vehicle("DHA-KA-11-1234", "2030-01-31");
+    body["insurance_date"] = "2020-01-01".into();
+    api.send("POST", "/vehicles", Some(&token), body).await;
+
+    pay(
+        &api,
+        &token,
+        "tax",
+        json!({ "vehicle_no": "DHA-KA-11-1234" }),
+    )
+    .await;
+    pay(
+        &api,
+        &token,
+        "insurance",
+        json!({ "vehicle_no": "DHA-KA-11-1234" }),
+    )
+    .await;
+
+    let vehicle = details(&api, &token).await;
+    assert_eq!(vehicle["tax_date"], "2031-01-31");
+    let next_year = today + chrono::Months::new(12);
+    assert_eq!(vehicle["insurance_date"], next_year.to_string());
+}
+
+#[tokio::test]
+async fn vehicle_class_and_explicit_dates_are_respected() {
+    let api = Api::new();
+    let token = api.token("rahim").await;
+    api.add_vehicle(&token, "DHA-KA-11-1234").await;
+
+    let update = api
+        .send(
+            "PUT",
+            "/vehicles",
+            Some(&token),
+            json!({ "vehicle_no": "DHA-KA-11-1234", "vehicle_class": "private" }),
+        )
+        .await;
+    assert_eq!(update.status, 200, "{}", update.text);
+
+    pay(
+        &api,
+        &token,
+        "fitness",
+        json!({ "vehicle_no": "DHA-KA-11-1234" }),
+    )
+    .await;
+    pay(
+        &api,
+        &token,
+        "route",
+        json!({ "vehicle_no": "DHA-KA-11-1234", "route_date": "2030-06-30" }),
+    )
+    .await;
+
+    let vehicle = details(&api, &token).await;
+    assert_eq!(vehicle["fitness_date"], "2032-01-01");
+    assert_eq!(vehicle["route_date"], "2030-06-30");
+}
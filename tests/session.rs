@@ -0,0 +1,144 @@
+mod common;
+
+use common::Api;
+use serde_json::json;
+
+#[tokio::test]
+async fn login_returns_a_token_that_authorizes_requests() {
+    let api = Api::new();
+    let login = api.login("rahim").await;
+    for field in ["token", "expired_at", "refresh_token", "refresh_expired_at"] {
+        assert!(login[field].is_string(), "{} is missing: {}", field, login);
+    }
+
+    let vehicles = api.get("/vehicles", login["token"].as_str().unwrap()).await;
+    assert_eq!(vehicles.status, 200, "{}", vehicles.text);
+}
+
+#[tokio::test]
+async fn login_fails_with_a_wrong_password() {
+    let api = Api::new();
+    api.login("rahim").await;
+
+    let login = api
+        .send(
+            "POST",
+            "/sessions",
+            None,
+            json!({ "username": "rahim", "password": "wrong" }),
+        )
+        .await;
+    assert_eq!(login.status, 401);
+    assert_eq!(login.json["code"], "UNAUTHORIZED");
+}
+
+#[tokio::test]
+async fn signup_rejects_a_taken_username() {
+    let api = Api::new();
+    api.login("rahim").await;
+
+    let signup = api
+        .send(
+            "POST",
+            "/users",
+            None,
+            json!({ "username": "rahim", "password": "other" }),
+        )
+        .await;
+    assert_eq!(signup.status, 409);
+    assert_eq!(signup.json["code"], "CONFLICT");
+}
+
+#[tokio::test]
+async fn requests_need_a_known_token() {
+    let api = Api::new();
+
+    let missing = api
+        .call("GET", "/vehicles", None, lambda_http::Body::Empty)
+        .await;
+    assert_eq!(missing.status, 401);
+
+    let unknown = api.get("/vehicles", "not-a-token").await;
+    assert_eq!(unknown.status, 401);
+}
+
+#[tokio::test]
+async fn logout_revokes_the_token() {
+    let api = Api::new();
+    let token = api.token("rahim").await;
+
+    let logout = api
+        .call(
+            "DELETE",
+            "/sessions",
+            Some(&token),
+            lambda_http::Body::Empty,
+        )
+        .await;
+    assert_eq!(logout.status, 200, "{}", logout.text);
+
+    assert_eq!(api.get("/vehicles", &token).await.status, 401);
+}
+
+async fn refresh(api: &Api, refresh_token: &str) -> common::Reply {
+    api.send(
+        "POST",
+        "/sessions/refresh",
+        None,
+        json!({ "refresh_token": refresh_token }),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn refresh_rotates_both_tokens() {
+    let api = Api::new();
+    let login = api.login("rahim").await;
+
+    let refreshed = refresh(&api, login["refresh_token"].as_str().unwrap()).await;
+    assert_eq!(refreshed.status, 200, "{}", refreshed.text);
+    assert_ne!(refreshed.json["token"], login["token"]);
+    assert_ne!(refreshed.json["refresh_token"], login["refresh_token"]);
+
+    let vehicles = api
+        .get("/vehicles", refreshed.json["token"].as_str().unwrap())
+        .await;
+    assert_eq!(vehicles.status, 200, "{}", vehicles.text);
+
+    let again = refresh(&api, refreshed.json["refresh_token"].as_str().unwrap()).await;
+    assert_eq!(again.status, 200, "{}", again.text);
+}
+
+#[tokio::test]
+async fn reused_refresh_token_revokes_the_whole_family() {
+    let api = Api::new();
+    let login = api.login("rahim").await;
+    let other_device = api.login("rahim").await;
+    let first_refresh = login["refresh_token"].as_str().unwrap();
+
+    let refreshed = refresh(&api, first_refresh).await;
+    assert_eq!(refreshed.status, 200, "{}", refreshed.text);
+
+    let reused = refresh(&api, first_refresh).await;
+    assert_eq!(reused.status, 401, "{}", reused.text);
+
+    let token = refreshed.json["token"].as_str().unwrap();
+    assert_eq!(api.get("/vehicles", token).await.status, 401);
+    let rotated = refreshed.json["refresh_token"].as_str().unwrap();
+    assert_eq!(refresh(&api, rotated).await.status, 401);
+
+    let other_token = other_device["token"].as_str().unwrap();
+    assert_eq!(api.get("/vehicles", other_token).await.status, 200);
+    let other_refresh = other_device["refresh_token"].as_str().unwrap();
+    assert_eq!(refresh(&api, other_refresh).await.status, 200);
+}
+
+#[tokio::test]
+async fn unknown_refresh_token_is_rejected() {
+    let api = Api::new();
+    api.login("rahim").await;
+
+    let reply = refresh(&api, "not-a-refresh-token").await;
+    assert_eq!(reply.status, 401, "{}", reply.text);
+    assert_eq!(reply.json["code"], "UNAUTHORIZED");
+}
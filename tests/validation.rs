@@ -0,0 +1,187 @@
+mod common;
+
+use common::{vehicle, Api};
+use lambda_http::Body;
+use serde_json::json;
+
+fn fields(reply: &common::Reply) -> Vec<&str> {
+    reply.json["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|error| error["field"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn every_bad_field_of_a_body_is_reported() {
+    let api = Api::new();
+    let token = api.token("rahim").await;
+
+    let mut body = vehicle("DHA-KA-11-1234", "2024-13-40");
+    body["owner"] = " ".into();
+    body["route_date"] = "2030-01-01".into();
+    let reply = api.send("POST", "/vehicles", Some(&token), body).await;
+
+    assert_eq!(reply.status, 422, "{}", reply.text);
+    assert_eq!(reply.json["code"], "VALIDATION_ERROR");
+    assert_eq!(
+        fields(&reply),
+        ["owner", "tax_date", "fitness_date", "insurance_date"]
+    );
+    assert_eq!(
+        reply.json["errors"][1]["message"],
+        "tax_date must be a date like 2024-01-31"
+    );
+}
+
+#[tokio::test]
+async fn missing_and_mistyped_fields_are_named() {
+    let api = Api::new();
+    let token = api.token("rahim").await;
+
+    let mut body = vehicle("DHA-KA-11-1234", "2030-01-01");
+    body.as_object_mut().unwrap().remove("fitness_date");
+    let missing = api.send("POST", "/vehicles", Some(&token), body).await;
+    assert_eq!(missing.status, 422, "{}", missing.text);
+    assert_eq!(
+        missing.json["errors"],
+        json!([{ "field": "fitness_date", "message": "fitness_date is required" }])
+    );
+
+    let plate = api
+        .send(
+            "POST",
+            "/vehicles",
+            Some(&token),
+            vehicle("not a plate", "2030-01-01"),
+        )
+        .await;
+    assert_eq!(plate.status, 422, "{}", plate.text);
+    assert_eq!(fields(&plate), ["vehicle_no"]);
+
+    let mut body = vehicle("DHA-KA-11-1234", "2030-01-01");
+    body["owner"] = 42.into();
+    let mistyped = api.send("POST", "/vehicles", Some(&token), body).await;
+    assert_eq!(mistyped.status, 422, "{}", mistyped.text);
+    assert_eq!(fields(&mistyped), ["owner"]);
+}
+
+#[tokio::test]
+async fn a_body_that_is_not_json_is_a_bad_request() {
+    let api = Api::new();
+    let token = api.token("rahim").await;
+
+    for body in [Body::Text("{\"vehicle_no\":".to_string()), Body::Empty] {
+        let reply = api.call("POST", "/vehicles", Some(&token), body).await;
+        assert_eq!(reply.status, 400, "{}", reply.text);
+        assert_eq!(reply.json["code"], "BAD_REQUEST");
+    }
+}
+
+#[tokio::test]
+async fn query_parameters_are_checked() {
+    let api = Api::new();
+    let token = api.token("rahim").await;
+
+    let fee_type = api
+        .send(
+            "POST",
+            "/payments?type=parking",
+            Some(&token),
+            json!({ "vehicle_no": "DHA-KA-11-1234" }),
+        )
+        .await;
+    assert_eq!(fee_type.status, 422, "{}", fee_type.text);
+    assert_eq!(fields(&fee_type), ["type"]);
+
+    let no_type = api
+        .send(
+            "POST",
+            "/payments",
+            Some(&token),
+            json!({ "vehicle_no": "DHA-KA-11-1234" }),
+        )
+        .await;
+    assert_eq!(no_type.status, 422, "{}", no_type.text);
+    assert_eq!(no_type.json["errors"][0]["message"], "type is not provided");
+
+    let days = api.get("/history?days=-3&limit=0", &token).await;
+    assert_eq!(days.status, 422, "{}", days.text);
+    assert_eq!(fields(&days), ["days", "limit"]);
+
+    let due = api.get("/vehicles?due=parking", &token).await;
+    assert_eq!(due.status, 422, "{}", due.text);
+    assert_eq!(fields(&due), ["due"]);
+}
+
+#[tokio::test]
+async fn undo_checks_the_fee_type_and_date_of_the_entry() {
+    let api = Api::new();
+    let token = api.token("rahim").await;
+    let undo = |transaction_type: &str, created_at: &str| {
+        json!({
+            "vehicle_no": "DHA-KA-11-1234",
+            "transaction_type": transaction_type,
+            "created_at": created_at,
+        })
+    };
+
+    for (body, field) in [
+        (undo("parking", "2026-01-02"), "transaction_type"),
+        (undo("ownership", "2026-01-02"), "transaction_type"),
+        (undo("tax", "2024-13-40"), "created_at"),
+        (
+            undo("tax", "2026-01-02\", \"owner\": \"Karim"),
+            "created_at",
+        ),
+    ] {
+        let reply = api.send("DELETE", "/history", Some(&token), body).await;
+        assert_eq!(reply.status, 422, "{}", reply.text);
+        assert_eq!(fields(&reply), [field]);
+    }
+}
+
+#[tokio::test]
+async fn undo_writes_only_the_fee_date_and_drops_the_entry() {
+    let api = Api::new();
+    let token = api.token("rahim").await;
+    api.add_vehicle(&token, "DHA-KA-11-1234").await;
+    let paid = api
+        .send(
+            "POST",
+            "/payments?type=tax",
+            Some(&token),
+            json!({ "vehicle_no": "DHA-KA-11-1234" }),
+        )
+        .await;
+    assert_eq!(paid.status, 200, "{}", paid.text);
+
+    let history = api.get("/history", &token).await;
+    let entry = &history.json["items"][0];
+    assert_eq!(entry["transaction_type"], "tax");
+    let created_at = entry["created_at"].as_str().unwrap();
+
+    let reply = api
+        .send(
+            "DELETE",
+            "/history",
+            Some(&token),
+            json!({
+                "vehicle_no": "DHA-KA-11-1234",
+                "transaction_type": "tax",
+                "created_at": created_at,
+            }),
+        )
+        .await;
+    assert_eq!(reply.status, 200, "{}", reply.text);
+
+    let details = api
+        .get("/vehicles/details?vehicle_no=DHA-KA-11-1234", &token)
+        .await;
+    assert_eq!(details.json["tax_date"], created_at);
+    assert_eq!(details.json["fitness_date"], "2030-01-01");
+    assert_eq!(details.json["owner"], "Rahim");
+    let history = api.get("/history", &token).await;
+    assert!(history.json["items"].as_array().unwrap().is_empty());
+}
@@ -0,0 +1,177 @@
+mod common;
+
+use common::{vehicle, Api};
+use serde_json::json;
+
+#[tokio::test]
+async fn added_vehicle_can_be_read_back() {
+    let api = Api::new();
+    let token = api.token("rahim").await;
+
+    let added = api.add_vehicle(&token, "DHA-KA-11-1234").await;
+    assert_eq!(added.status, 201, "{}", added.text);
+
+    let details = api
+        .get("/vehicles/details?vehicle_no=DHA-KA-11-1234", &token)
+        .await;
+    assert_eq!(details.status, 200, "{}", details.text);
+    assert_eq!(details.json["vehicle_no"], "DHA-KA-11-1234");
+    assert_eq!(details.json["owner"], "Rahim");
+    assert_eq!(details.json["tax_date"], "2030-01-01");
+    assert_eq!(details.json["sold"], false);
+
+    let vehicles = api.get("/vehicles", &token).await;
+    assert_eq!(vehicles.json["items"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn the_same_plate_in_another_spelling_is_the_same_vehicle() {
+    let api = Api::new();
+    let token = api.token("rahim").await;
+    api.add_vehicle(&token, "ঢাকা-ক-১১-১২৩৪").await;
+
+    let again = api.add_vehicle(&token, "DHA KA111234").await;
+    assert_eq!(again.status, 409, "{}", again.text);
+
+    let details = api
+        .get("/vehicles/details?vehicle_no=dhaka-ka-11-1234", &token)
+        .await;
+    assert_eq!(details.status, 200, "{}", details.text);
+    assert_eq!(details.json["vehicle_no"], "ঢাকা-ক-১১-১২৩৪");
+}
+
+#[tokio::test]
+async fn vehicles_are_not_shared_between_organizations() {
+    let api = Api::new();
+    let rahim = api.token("rahim").await;
+    let karim = api.token("karim").await;
+    api.add_vehicle(&rahim, "DHA-KA-11-1234").await;
+
+    let details = api
+        .get("/vehicles/details?vehicle_no=DHA-KA-11-1234", &karim)
+        .await;
+    assert_eq!(details.status, 404);
+    assert!(api.get("/vehicles", &karim).await.json["items"]
+        .as_array()
+        .unwrap()
+        .is_empty());
+
+    let added = api.add_vehicle(&karim, "DHA-KA-11-1234").await;
+    assert_eq!(added.status, 201, "{}", added.text);
+}
+
+#[tokio::test]
+async fn unknown_vehicle_is_not_found() {
+    let api = Api::new();
+    let token = api.token("rahim").await;
+
+    let details = api
+        .get("/vehicles/details?vehicle_no=DHA-KA-11-1234", &token)
+        .await;
+    assert_eq!(details.status, 404);
+    assert_eq!(details.json["code"], "NOT_FOUND");
+
+    let update = api
+        .send(
+            "PUT",
+            "/vehicles",
+            Some(&token),
+            vehicle("DHA-KA-11-1234", "2031-01-01"),
+        )
+        .await;
+    assert_eq!(update.status, 404, "{}", update.text);
+}
+
+#[tokio::test]
+async fn details_count_the_days_left_on_each_fee() {
+    let api = Api::new();
+    let token = api.token("rahim").await;
+    let today = chrono::Local::now().date_naive();
+    let date = |days: i64| (today + chrono::Duration::days(days)).to_string();
+
+    let mut body = vehicle("DHA-KA-11-1234", &date(10));
+    body["fitness_date"] = date(-5).into();
+    body["insurance_date"] = date(0).into();
+    let added = api.send("POST", "/vehicles", Some(&token), body).await;
+    assert_eq!(added.status, 201, "{}", added.text);
+
+    let details = api
+        .get("/vehicles/details?vehicle_no=DHA-KA-11-1234", &token)
+        .await;
+    assert_eq!(details.status, 200, "{}", details.text);
+    let days = &details.json["days_remaining"];
+    assert_eq!(days["tax"], 10);
+    assert_eq!(days["fitness"], -5);
+    assert_eq!(days["insurance"], 0);
+    assert_eq!(days["route"], 10);
+    assert!(details.json["created_at"].is_string());
+    assert!(details.json["updated_at"].is_null());
+
+    let update = api
+        .send(
+            "PUT",
+            "/vehicles",
+            Some(&token),
+            json!({ "vehicle_no": "DHA-KA-11-1234", "owner": "Karim" }),
+        )
+        .await;
+    assert_eq!(update.status, 200, "{}", update.text);
+    let details = api
+        .get("/vehicles/details?vehicle_no=DHA-KA-11-1234", &token)
+        .await;
+    assert_eq!(details.json["owner"], "Karim");
+    assert!(details.json["updated_at"].is_string());
+}
+
+#[tokio::test]
+async fn details_show_a_sold_vehicle() {
+    let api = Api::new();
+    let token = api.token("rahim").await;
+    api.add_vehicle(&token, "DHA-KA-11-1234").await;
+
+    let sale = api
+        .send(
+            "POST",
+            "/vehicles/sale",
+            Some(&token),
+            json!({ "vehicle_no": "DHA-KA-11-1234", "buyer": "Karim", "sale_date": "2026-01-02" }),
+        )
+        .await;
+    assert_eq!(sale.status, 200, "{}", sale.text);
+
+    let details = api
+        .get("/vehicles/details?vehicle_no=DHA-KA-11-1234", &token)
+        .await;
+    assert_eq!(details.status, 200, "{}", details.text);
+    assert_eq!(details.json["sold"], true);
+    assert_eq!(details.json["buyer"], "Karim");
+}
+
+#[tokio::test]
+async fn a_sold_vehicle_can_not_be_updated() {
+    let api = Api::new();
+    let token = api.token("rahim").await;
+    api.add_vehicle(&token, "DHA-KA-11-1234").await;
+    api.send(
+        "POST",
+        "/vehicles/sale",
+        Some(&token),
+        json!({ "vehicle_no": "DHA-KA-11-1234", "buyer": "Karim" }),
+    )
+    .await;
+
+    for body in [
+        json!({ "vehicle_no": "DHA-KA-11-1234", "owner": "Salam" }),
+        json!({ "vehicle_no": "DHA-KA-11-1234", "tax_date": "2035-01-01" }),
+    ] {
+        let update = api.send("PUT", "/vehicles", Some(&token), body).await;
+        assert_eq!(update.status, 409, "{}", update.text);
+        assert_eq!(update.json["message"], "The car is sold!!");
+    }
+
+    let details = api
+        .get("/vehicles/details?vehicle_no=DHA-KA-11-1234", &token)
+        .await;
+    assert_eq!(details.json["owner"], "Karim");
+    assert_eq!(details.json["tax_date"], "2030-01-01");
+}